@@ -1,19 +1,170 @@
 use std::{
+    cmp::min,
     env,
-    io::Error,
+    fs::create_dir_all,
+    io::{stdin, stdout, Error, ErrorKind, IsTerminal, Read, Write},
+    iter::once,
     panic::{set_hook, take_hook},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
 };
 
-use crossterm::event::{read, Event, KeyEvent, KeyEventKind};
+use crossterm::event::{poll, read, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 
+mod arguments;
+mod backingstore;
+mod buffer;
+mod commandbar;
+mod commandline;
+mod config;
+mod datadir;
+mod documentstatus;
 mod editorcommand;
+mod encoding;
+mod filetype;
+mod filewatcher;
+mod fuzzypicker;
+mod gapbuffer;
+mod grep;
+mod hexview;
+mod history;
+mod largefile;
+mod lastlocations;
+mod line;
+mod lineending;
+mod location;
+mod mappedfile;
+mod messagebar;
+mod projectfiles;
+mod recentfiles;
+mod rope;
+mod savereport;
+mod search;
+mod searchhistory;
+mod snippet;
+mod statusbar;
+mod tabline;
+mod tags;
 mod terminal;
+mod undofile;
 mod view;
 
-use editorcommand::EditorCommand;
-use terminal::Terminal;
+use arguments::Arguments;
+use buffer::Buffer;
+use commandbar::CommandBar;
+use commandline::LineCommand;
+use config::Config;
+use editorcommand::{Edit, EditorCommand, SearchDirection, SortOrder, SplitLayout};
+use filewatcher::FileWatcher;
+use fuzzypicker::{FuzzyPicker, PickerEntry};
+use grep::{grep, GrepMatch};
+use hexview::HexView;
+use lineending::LineEnding;
+use location::Location;
+use messagebar::MessageBar;
+use projectfiles::project_files;
+use recentfiles::RecentFiles;
+use savereport::SaveReport;
+use search::{CaseMode, Pattern, SearchOptions};
+use searchhistory::SearchHistory;
+use statusbar::StatusBar;
+use tabline::TabLine;
+use tags::{tags_file, Address, Tag};
+use terminal::{Position, Size, Terminal};
 use view::View;
 
+/// How long the main loop waits for input before doing periodic work
+/// such as autosaving or reacting to changed files.
+const TICK: Duration = Duration::from_millis(200);
+
+/// The file name which stands for stdin on the command line.
+const STDIN_NAME: &str = "-";
+
+/// The screen row the view starts in, below the tab line.
+const VIEW_TOP: usize = 1;
+
+/// What the editor is asking the user for while the command bar is shown.
+#[derive(Default, Clone, Copy)]
+enum PromptType {
+    #[default]
+    SaveAs,
+    /// The file was changed by another program; overwrite, reload or cancel?
+    FileChanged,
+    /// Which line ending should the buffer be converted to?
+    LineEnding,
+    /// The directory to save to doesn't exist; create it?
+    CreateDirectories,
+    /// Saving failed for lack of permissions; retry through the privileged save command?
+    PrivilegedSave,
+    /// Which of the recently used files should be opened?
+    RecentFile,
+    /// How should the lines be sorted?
+    SortOrder,
+    /// Which entry of the kill ring should be pasted?
+    KillRing,
+    /// Which line, and optionally column, should the caret go to?
+    GoToLine,
+    /// Which letter should the caret's location be marked with?
+    SetMark,
+    /// Which mark should the caret go to?
+    JumpToMark,
+    /// Which of several definitions of a symbol should the caret go to?
+    Tag,
+    /// What should be searched for? Searches while the user types.
+    Search(SearchDirection),
+    /// What should be replaced? With `all` set, all matches are replaced
+    /// at once instead of asking about each.
+    ReplaceSearch { all: bool },
+    /// What should the matches be replaced with?
+    ReplaceWith,
+    /// Should the highlighted match be replaced?
+    ReplaceConfirm,
+    /// What should be searched for in the files under the working directory?
+    Grep,
+    /// Which of the lines found by the grep should the caret go to?
+    GrepMatch,
+    /// Which of the files listed by the picker should be opened?
+    FindFile,
+    /// Which of the buffers listed by the picker should be shown?
+    SwitchBuffer,
+    /// Which named command should be run?
+    CommandLine,
+}
+
+/// How far the user got inserting a character with Ctrl+V.
+enum QuotedInsert {
+    /// Ctrl+V was pressed: `u` starts a code point, Ctrl with a letter
+    /// inserts the control character, and other keys are inserted as they
+    /// are.
+    Started,
+    /// The hexadecimal digits of the code point typed so far.
+    CodePoint(String),
+}
+
+/// A replacement of the matches of `pattern`, either walking through those
+/// after the caret or replacing all of them at once.
+struct Replace {
+    pattern: Pattern,
+    replacement: String,
+    /// Whether to replace all matches in the selection or the buffer at once.
+    all: bool,
+    /// How many matches were replaced so far.
+    count: usize,
+}
+
+/// The window which doesn't have the focus while the screen is split.
+struct Split {
+    view: View,
+    /// The index of the buffer it shows.
+    buffer: usize,
+    /// Whether it is the upper, or left, one of the two windows.
+    first: bool,
+    layout: SplitLayout,
+    /// How many rows, or columns side by side, the upper or left window
+    /// asks for. Kept within the screen when it is laid out.
+    first_size: usize,
+}
+
 /// The `Editor` struct represents a basic text editor.
 ///
 /// This struct manages the main editor loop, user inputs, and screen rendering.
@@ -22,7 +173,61 @@ use view::View;
 pub struct Editor {
     /// A flag to indicate whether the editor should quit.
     should_quit: bool,
+    /// All open buffers, one per file. Never empty.
+    buffers: Vec<Buffer>,
+    /// The index of the buffer shown in the view.
+    current_buffer: usize,
+    /// The window which has the focus.
     view: View,
+    /// The other window, if the screen is split.
+    split: Option<Split>,
+    hex_view: HexView,
+    tab_line: TabLine,
+    status_bar: StatusBar,
+    message_bar: MessageBar,
+    /// The prompt in the bottom row, if the editor is currently asking for input.
+    command_bar: Option<CommandBar>,
+    prompt_type: PromptType,
+    /// Set while a character is being inserted with Ctrl+V.
+    quoted_insert: Option<QuotedInsert>,
+    /// The file name to save to once the user answered the prompt about
+    /// creating its directories or saving it with elevated privileges.
+    pending_file_name: Option<String>,
+    /// An external change to the file the user already declined to reload,
+    /// so regaining focus doesn't ask about it again.
+    ignored_disk_change: Option<SystemTime>,
+    terminal_size: Size,
+    config: Config,
+    file_watcher: FileWatcher,
+    /// When the user last pressed a key or resized the terminal.
+    last_input: Instant,
+    recent_files: RecentFiles,
+    /// The entry of `recent_files` shown in the picker.
+    recent_selection: usize,
+    /// The entry of the kill ring shown in the picker.
+    kill_ring_selection: usize,
+    /// The definitions to pick from when a symbol has several.
+    tag_matches: Vec<Tag>,
+    /// The entry of `tag_matches` shown in the picker.
+    tag_selection: usize,
+    /// The lines found by the last grep, to pick from.
+    grep_matches: Vec<GrepMatch>,
+    /// The list shown over the view while picking a file, a buffer or a
+    /// line found by the grep.
+    picker: Option<FuzzyPicker>,
+    /// How the text searched for is matched, kept from one search to the next.
+    search_options: SearchOptions,
+    search_history: SearchHistory,
+    /// The entry of `search_history` shown in the search prompt while
+    /// going through it with Up and Down.
+    search_history_selection: Option<usize>,
+    /// The pattern and direction of the last search, for repeating it.
+    last_search: Option<(Pattern, SearchDirection)>,
+    /// The replacement in progress, from when the text to replace is known.
+    replace: Option<Replace>,
+    /// In filter mode, the buffer holding stdin, which is written to
+    /// stdout when the editor quits.
+    filter_buffer: Option<usize>,
 }
 
 impl Editor {
@@ -32,19 +237,163 @@ impl Editor {
             let _ = Terminal::terminate();
             current_hook(panic_info);
         }));
+        let arguments = Arguments::parse(env::args().skip(1));
+        // Piped input has to be read before the terminal is set up. From then
+        // on crossterm reads keys from /dev/tty since stdin isn't a terminal.
+        let mut stdin_bytes = if arguments.file_names.iter().any(|name| name == STDIN_NAME) {
+            Some(Self::read_stdin()?)
+        } else {
+            None
+        };
+        if arguments.filter {
+            Terminal::draw_to_tty();
+        }
         Terminal::initialize()?;
 
-        let mut view = View::default();
-
-        let args: Vec<String> = env::args().collect();
-        if let Some(file_name) = args.get(1) {
-            view.load(file_name);
+        let config = Config::load();
+        let mut buffers = Vec::new();
+        let mut load_errors = Vec::new();
+        let mut filter_buffer = None;
+        for file_name in &arguments.file_names {
+            if file_name == STDIN_NAME {
+                if let Some(bytes) = stdin_bytes.take() {
+                    let mut buffer = Buffer::from_bytes(bytes);
+                    buffer.read_only = arguments.read_only;
+                    if arguments.filter {
+                        filter_buffer = Some(buffers.len());
+                    }
+                    buffers.push(buffer);
+                }
+                continue;
+            }
+            match Buffer::load(file_name, arguments.read_only, &config) {
+                Ok(buffer) => buffers.push(buffer),
+                Err(err) if err.kind() == ErrorKind::NotFound => {
+                    let mut buffer = Buffer::new_file(file_name);
+                    buffer.read_only = arguments.read_only;
+                    buffers.push(buffer);
+                }
+                Err(err) => load_errors.push(format!("Could not open {file_name}: {err}")),
+            }
+        }
+        if buffers.is_empty() {
+            let mut buffer = Buffer::default();
+            buffer.read_only = arguments.read_only;
+            buffers.push(buffer);
         }
 
-        Ok(Self {
+        let mut editor = Self {
             should_quit: false,
-            view,
-        })
+            buffers,
+            current_buffer: 0,
+            view: View::default(),
+            split: None,
+            hex_view: HexView::default(),
+            tab_line: TabLine::default(),
+            status_bar: StatusBar::default(),
+            message_bar: MessageBar::default(),
+            command_bar: None,
+            prompt_type: PromptType::default(),
+            quoted_insert: None,
+            pending_file_name: None,
+            ignored_disk_change: None,
+            terminal_size: Size::default(),
+            config,
+            file_watcher: FileWatcher::new(),
+            last_input: Instant::now(),
+            recent_files: RecentFiles::load(),
+            recent_selection: 0,
+            kill_ring_selection: 0,
+            tag_matches: Vec::new(),
+            tag_selection: 0,
+            grep_matches: Vec::new(),
+            picker: None,
+            search_options: SearchOptions::default(),
+            search_history: SearchHistory::load(),
+            search_history_selection: None,
+            last_search: None,
+            replace: None,
+            filter_buffer,
+        };
+        editor.configure_view();
+        editor.resize(Terminal::size().unwrap_or_default());
+        editor.view.show_buffer(&editor.buffers[0]);
+        if arguments.file_names.is_empty() {
+            editor.view.show_welcome();
+        }
+        if let Some(line) = arguments.start_line {
+            editor.go_to_line(line, None);
+        }
+        editor.watch_files();
+        for file_name in &arguments.file_names {
+            editor.recent_files.add(file_name);
+        }
+        editor.show_startup_message(&load_errors);
+        Ok(editor)
+    }
+
+    /// Tells the user why a file couldn't be opened, or else how the
+    /// current buffer is shown if it isn't shown as usual.
+    fn show_startup_message(&mut self, load_errors: &[String]) {
+        if let Some(message) = load_errors.first() {
+            self.set_message(message);
+        } else if self.buffer().is_write_protected() && !self.buffer().read_only {
+            self.warn_write_protected();
+        } else if self.buffer().is_large_file() {
+            self.set_message("Large file: reading lines as needed.");
+        } else if self.buffer().show_hex {
+            self.set_message("Binary file: showing hex view. Alt+X switches to text.");
+        } else if self.buffer().raw_bytes().is_some() {
+            self.set_message("Not valid UTF-8, decoded as Latin-1. Alt+X shows hex view.");
+        }
+    }
+
+    /// Passes the editing settings from the config file on to the view.
+    fn configure_view(&mut self) {
+        let config = &self.config;
+        let views = once(&mut self.view).chain(self.split.as_mut().map(|split| &mut split.view));
+        for view in views {
+            view.set_tabs(config.tab_width, config.expand_tab);
+            view.set_auto_indent(config.auto_indent, &config.indent_after);
+            view.set_auto_close(config.auto_close);
+            view.set_snippets(config.snippets.clone());
+            view.set_abbreviations(config.abbreviations.clone());
+            view.set_scrolloff(config.scrolloff);
+            view.set_wrap(config.wrap);
+            view.set_line_numbers(config.line_numbers, config.relative_line_numbers);
+        }
+    }
+
+    /// Reads everything piped into stdin. Nothing is read if stdin is
+    /// a terminal, as there is nothing piped in then.
+    fn read_stdin() -> Result<Vec<u8>, Error> {
+        let mut bytes = Vec::new();
+        let mut stdin = stdin();
+        if !stdin.is_terminal() {
+            stdin.read_to_end(&mut bytes)?;
+        }
+        Ok(bytes)
+    }
+
+    /// Writes the filtered buffer to stdout. Stdout is only used for this
+    /// in filter mode, so a failure can only be reported on stderr.
+    fn write_filter_output(&self, index: usize) {
+        let result = self.buffers[index].to_bytes().and_then(|bytes| {
+            let mut stdout = stdout().lock();
+            stdout.write_all(&bytes)?;
+            stdout.flush()
+        });
+        if let Err(err) = result {
+            eprintln!("hecto: could not write to stdout: {err}");
+        }
+    }
+
+    fn buffer(&self) -> &Buffer {
+        &self.buffers[self.current_buffer]
+    }
+
+    fn buffer_mut(&mut self) -> &mut Buffer {
+        &mut self.buffers[self.current_buffer]
     }
 
     pub fn run(&mut self) {
@@ -55,16 +404,337 @@ impl Editor {
                 break;
             }
 
-            match read() {
-                Ok(event) => self.evaluate_event(event),
+            match poll(TICK) {
+                Ok(true) => match read() {
+                    Ok(event) => {
+                        self.last_input = Instant::now();
+                        if let Event::Key(KeyEvent {
+                            kind: KeyEventKind::Press,
+                            ..
+                        }) = event
+                        {
+                            // Messages and the welcome screen are only shown until
+                            // the user does something else.
+                            self.message_bar.clear();
+                            self.view.hide_welcome();
+                        }
+                        self.evaluate_event(event);
+                    }
+                    Err(err) => {
+                        #[cfg(debug_assertions)]
+                        {
+                            panic!("Could not read event: {err:?}");
+                        }
+                    }
+                },
+                Ok(false) => self.on_tick(),
                 Err(err) => {
                     #[cfg(debug_assertions)]
                     {
-                        panic!("Could not read event: {err:?}");
+                        panic!("Could not poll for events: {err:?}");
+                    }
+                }
+            }
+            while let Some(path) = self.file_watcher.try_recv() {
+                self.on_file_changed(&path);
+            }
+        }
+    }
+
+    /// Runs the periodic work which doesn't depend on user input.
+    fn on_tick(&mut self) {
+        self.message_bar.expire();
+        if let Some(interval) = self.config.autosave_interval {
+            if self.last_input.elapsed() >= interval {
+                self.autosave();
+                // Restart the idle timer so a failing autosave isn't retried on every tick.
+                self.last_input = Instant::now();
+            }
+        }
+    }
+
+    /// Reloads a buffer if its file was changed by another program and
+    /// there are no unsaved changes to lose. For the current buffer with
+    /// unsaved changes, asks what to do; other buffers are checked again
+    /// when the user switches to them.
+    fn on_file_changed(&mut self, path: &Path) {
+        let Some(index) = self
+            .buffers
+            .iter()
+            .position(|buffer| buffer.file_name.as_deref().map(Path::new) == Some(path))
+        else {
+            return;
+        };
+        let buffer = &mut self.buffers[index];
+        if buffer.changed_on_disk().is_none() {
+            return;
+        }
+        if buffer.is_mapped() {
+            // The file may have been truncated, so it can't be read through
+            // the old map any more. Mapped buffers are read-only, so there
+            // are no changes to lose by mapping it again right away.
+            let result = buffer.reload(&self.config);
+            self.refresh_views_of(index);
+            if let Err(err) = result {
+                self.set_message(&format!("Could not reload file: {err}"));
+            }
+            return;
+        }
+        if index != self.current_buffer {
+            if !buffer.is_dirty() {
+                let _ = buffer.reload(&self.config);
+            }
+        } else if self.buffer().is_dirty() {
+            self.check_disk_change();
+        } else if self.command_bar.is_none() {
+            self.reload();
+        }
+    }
+
+    fn watch_files(&self) {
+        let paths = self
+            .buffers
+            .iter()
+            .filter_map(|buffer| buffer.file_name.as_deref().map(PathBuf::from))
+            .collect();
+        self.file_watcher.watch(paths);
+    }
+
+    /// Stores where the caret is in each file, so reopening the file
+    /// continues there.
+    fn remember_locations(&mut self) {
+        if let Some(split) = &self.split {
+            self.buffers[split.buffer].last_location = split.view.location();
+        }
+        self.buffers[self.current_buffer].last_location = self.view.location();
+        let locations: Vec<_> = self
+            .buffers
+            .iter()
+            .filter_map(|buffer| Some((buffer.file_name.as_deref()?, buffer.last_location)))
+            .collect();
+        let _ = lastlocations::store(&locations);
+    }
+
+    /// Shows the buffer at `index`, remembering where the caret was in the current one.
+    fn switch_to_buffer(&mut self, index: usize) {
+        if index >= self.buffers.len() || index == self.current_buffer {
+            return;
+        }
+        self.buffers[self.current_buffer].last_location = self.view.location();
+        self.current_buffer = index;
+        self.ignored_disk_change = None;
+        if self.split.is_some() {
+            // The hex view takes up the whole screen, so it isn't used while it's split.
+            self.buffers[index].show_hex = false;
+        }
+        self.view.show_buffer(&self.buffers[index]);
+        self.hex_view.reset();
+        let message = format!(
+            "Buffer {} of {}: {}",
+            index.saturating_add(1),
+            self.buffers.len(),
+            self.buffer().file_name.as_deref().unwrap_or("[No Name]")
+        );
+        self.set_message(&message);
+        self.check_disk_change();
+    }
+
+    /// Splits the screen into two windows of the same size, which both show
+    /// the current buffer at first. The lower or right one gets the focus.
+    fn split_window(&mut self, layout: SplitLayout) {
+        if self.split.is_some() {
+            self.set_message("The screen is split already.");
+            return;
+        }
+        if self.buffer().show_hex {
+            self.set_message("Switch to the text view with Alt+X to split the screen.");
+            return;
+        }
+        // Edits not followed yet are in the new window's copy already.
+        self.follow_changes();
+        let total = along(self.view_size(), layout);
+        self.split = Some(Split {
+            view: self.view.clone(),
+            buffer: self.current_buffer,
+            first: true,
+            layout,
+            first_size: total.saturating_sub(1).saturating_div(2),
+        });
+        self.layout();
+    }
+
+    /// Moves the boundary between the windows by `delta` rows or columns,
+    /// growing the focused window if `delta` is positive.
+    fn resize_window(&mut self, delta: isize) {
+        let view_size = self.view_size();
+        let Some(split) = &mut self.split else {
+            self.set_message("There is only one window.");
+            return;
+        };
+        // The focused window is the upper or left one if the other isn't.
+        let grow_first = (delta > 0) != split.first;
+        let first_size = if grow_first {
+            split.first_size.saturating_add(delta.unsigned_abs())
+        } else {
+            split.first_size.saturating_sub(delta.unsigned_abs())
+        };
+        // Kept within the screen, so that pressing on at its edge doesn't pile up.
+        let (first, _, _) = split_sizes(view_size, split.layout, first_size);
+        split.first_size = along(first, split.layout);
+        self.layout();
+    }
+
+    /// Moves the focus to the other window.
+    fn focus_other_window(&mut self) {
+        // The focused window's edits have to be followed before it swaps places.
+        self.follow_changes();
+        let Some(split) = &mut self.split else {
+            self.set_message("There is only one window.");
+            return;
+        };
+        std::mem::swap(&mut self.view, &mut split.view);
+        std::mem::swap(&mut self.current_buffer, &mut split.buffer);
+        split.first = !split.first;
+        self.view.take_kill_ring(&mut split.view);
+        self.ignored_disk_change = None;
+        // The buffer may have been changed in the other window meanwhile.
+        self.view.snap_location(&self.buffers[self.current_buffer]);
+        self.view.mark_redraw();
+        self.check_disk_change();
+    }
+
+    /// Closes the window which has the focus, leaving the whole screen to
+    /// the other one.
+    fn close_window(&mut self) {
+        let Some(mut split) = self.split.take() else {
+            self.set_message("There is only one window.");
+            return;
+        };
+        self.buffers[self.current_buffer].last_location = self.view.location();
+        split.view.take_kill_ring(&mut self.view);
+        self.view = split.view;
+        self.current_buffer = split.buffer;
+        self.ignored_disk_change = None;
+        self.view.snap_location(&self.buffers[self.current_buffer]);
+        self.layout();
+        self.check_disk_change();
+    }
+
+    /// Opens an empty scratch buffer, which asks for a file name when it's first saved.
+    fn new_buffer(&mut self) {
+        self.buffers.push(Buffer::default());
+        self.switch_to_buffer(self.buffers.len().saturating_sub(1));
+    }
+
+    /// Shows the buffer for `file_name`, loading it first unless it's already open.
+    fn open_file(&mut self, file_name: &str) {
+        let path = Path::new(file_name).canonicalize().ok();
+        if let Some(index) = self.buffers.iter().position(|buffer| {
+            path.is_some()
+                && buffer
+                    .file_name
+                    .as_deref()
+                    .and_then(|name| Path::new(name).canonicalize().ok())
+                    == path
+        }) {
+            self.switch_to_buffer(index);
+            return;
+        }
+        match Buffer::load(file_name, false, &self.config) {
+            Ok(buffer) => {
+                self.buffers.push(buffer);
+                self.recent_files.add(file_name);
+                self.watch_files();
+                self.switch_to_buffer(self.buffers.len().saturating_sub(1));
+                if self.buffer().is_write_protected() {
+                    self.warn_write_protected();
+                }
+            }
+            Err(err) => self.set_message(&format!("Could not open {file_name}: {err}")),
+        }
+    }
+
+    fn warn_write_protected(&mut self) {
+        let message = format!(
+            "Warning: {} is not writable; saving will need elevated privileges.",
+            self.buffer().file_name.as_deref().unwrap_or_default()
+        );
+        self.set_message(&message);
+    }
+
+    fn next_buffer(&mut self) {
+        let next = self.current_buffer.saturating_add(1);
+        self.switch_to_buffer(if next < self.buffers.len() { next } else { 0 });
+    }
+
+    fn previous_buffer(&mut self) {
+        let previous = self
+            .current_buffer
+            .checked_sub(1)
+            .unwrap_or_else(|| self.buffers.len().saturating_sub(1));
+        self.switch_to_buffer(previous);
+    }
+
+    /// Saves the buffer after the configured idle interval has passed,
+    /// as long as it has unsaved changes and a file to go to.
+    fn autosave(&mut self) {
+        let mut saved = Vec::new();
+        let mut changed = Vec::new();
+        let mut failure = None;
+        for (index, buffer) in self.buffers.iter_mut().enumerate() {
+            if !buffer.is_dirty() || buffer.read_only || buffer.changed_on_disk().is_some() {
+                continue;
+            }
+            let Some(file_name) = buffer.file_name.clone() else {
+                continue;
+            };
+            match buffer.save(&self.config) {
+                Ok(report) => {
+                    if report.changed_lines() {
+                        changed.push(index);
                     }
+                    saved.push(file_name);
+                }
+                Err(err) => {
+                    failure = Some(format!("Autosave of {file_name} failed: {err}"));
+                    break;
                 }
             }
         }
+        for index in changed {
+            self.refresh_views_of(index);
+        }
+        if let Some(failure) = failure {
+            self.set_message(&failure);
+        } else if !saved.is_empty() {
+            self.set_message(&format!("Autosaved {}.", saved.join(", ")));
+        }
+    }
+
+    /// Moves the carets of the windows showing the buffer at `index` back
+    /// into its text and redraws them, after its lines were changed other
+    /// than through them, e.g. by stripping whitespace when saving.
+    fn refresh_views_of(&mut self, index: usize) {
+        if self.current_buffer == index {
+            self.view.snap_location(&self.buffers[index]);
+            self.view.mark_redraw();
+        }
+        if let Some(split) = self.split.as_mut().filter(|split| split.buffer == index) {
+            split.view.snap_location(&self.buffers[index]);
+            split.view.mark_redraw();
+        }
+    }
+
+    /// Moves the carets of the window without the focus along with the
+    /// edits made to its buffer since the last refresh, e.g. through the
+    /// focused window showing the same buffer.
+    fn follow_changes(&mut self) {
+        for (index, buffer) in self.buffers.iter_mut().enumerate() {
+            let changes = buffer.take_changes();
+            if let Some(split) = self.split.as_mut().filter(|split| split.buffer == index) {
+                split.view.adjust_to(&changes, buffer);
+            }
+        }
     }
 
     // needless_pass_by_value: Event is not huge, so there is not a
@@ -75,18 +745,28 @@ impl Editor {
         let should_process = match &event {
             Event::Key(KeyEvent { kind, .. }) => kind == &KeyEventKind::Press,
             Event::Resize(_, _) => true,
+            Event::FocusGained => {
+                self.check_disk_change();
+                return;
+            }
+            Event::FocusLost => return,
             _ => false,
         };
 
-        if should_process {
+        if !should_process {
+            #[cfg(debug_assertions)]
+            {
+                panic!("Received and discarded unsupported or non-press event.");
+            }
+        } else if let (Some(_), Event::Key(key_event)) = (&self.quoted_insert, &event) {
+            if !self.evaluate_quoted_insert_key(*key_event) {
+                self.evaluate_event(event);
+            }
+        } else if let (Some(_), Event::Key(key_event)) = (&self.command_bar, &event) {
+            self.evaluate_prompt_key(*key_event);
+        } else {
             match EditorCommand::try_from(event) {
-                Ok(command) => {
-                    if matches!(command, EditorCommand::Quit) {
-                        self.should_quit = true;
-                    } else {
-                        self.view.handle_command(command);
-                    }
-                }
+                Ok(command) => self.execute(command),
                 Err(err) => {
                     #[cfg(debug_assertions)]
                     {
@@ -94,29 +774,2111 @@ impl Editor {
                     }
                 }
             }
-        } else {
-            #[cfg(debug_assertions)]
+        }
+    }
+
+    /// Carries out `command`, e.g. one bound to the key just pressed.
+    fn execute(&mut self, command: EditorCommand) {
+        if command.scans_buffer() {
+            // Scanning would stop at the last line indexed so far otherwise.
+            self.buffers[self.current_buffer].ensure_loaded(usize::MAX);
+        }
+        if !matches!(command, EditorCommand::Edit(Edit::KillLine)) {
+            self.view.end_kill_sequence();
+        }
+        match command {
+            EditorCommand::Quit => self.should_quit = true,
+            EditorCommand::Save => self.save(),
+            EditorCommand::SaveAs => self.start_save_as(),
+            EditorCommand::ConvertLineEndings => self.start_line_ending_prompt(),
+            EditorCommand::SortLines => self.start_sort_order_prompt(),
+            EditorCommand::GoToLine => self.start_go_to_line_prompt(),
+            EditorCommand::SetMark => self.start_mark_prompt(PromptType::SetMark),
+            EditorCommand::JumpToMark => self.start_mark_prompt(PromptType::JumpToMark),
+            EditorCommand::PasteFromKillRing => self.start_kill_ring_prompt(),
+            EditorCommand::GoToDefinition => self.go_to_definition(),
+            EditorCommand::OpenFileAtCaret => self.open_file_at_caret(),
+            EditorCommand::Search(direction) => self.start_search_prompt(direction),
+            EditorCommand::RepeatSearch { reverse } => self.repeat_search(reverse),
+            EditorCommand::SearchWordAtCaret => self.search_word_at_caret(),
+            EditorCommand::CountMatches => self.count_matches(),
+            EditorCommand::Grep => self.start_grep_prompt(),
+            EditorCommand::Replace => self.start_replace_prompt(false),
+            EditorCommand::ReplaceAll => self.start_replace_prompt(true),
+            EditorCommand::QuotedInsert => {
+                self.quoted_insert = Some(QuotedInsert::Started);
+                self.set_message(
+                    "Type a key to insert as it is, or u and a hexadecimal code point.",
+                );
+            }
+            EditorCommand::NewBuffer => self.new_buffer(),
+            EditorCommand::OpenRecentFile => self.start_recent_file_prompt(),
+            EditorCommand::FindFile => self.start_file_finder(),
+            EditorCommand::SwitchBuffer => self.start_buffer_switcher(),
+            EditorCommand::CommandLine => self.start_command_line(),
+            EditorCommand::SplitWindow(layout) => self.split_window(layout),
+            EditorCommand::FocusOtherWindow => self.focus_other_window(),
+            EditorCommand::ResizeWindow(delta) => self.resize_window(delta),
+            EditorCommand::CloseWindow => self.close_window(),
+            EditorCommand::NextBuffer => self.next_buffer(),
+            EditorCommand::PreviousBuffer => self.previous_buffer(),
+            EditorCommand::Resize(size) => self.resize(size),
+            EditorCommand::ToggleHexView => self.toggle_hex_view(),
+            EditorCommand::Edit(edit) => self.edit(&edit),
+            command @ (EditorCommand::Select(_)
+            | EditorCommand::SelectBlock(_)
+            | EditorCommand::AddCaretAbove
+            | EditorCommand::AddCaretBelow
+            | EditorCommand::AddCaretAtNextMatch
+            | EditorCommand::JumpToMatchingBracket
+            | EditorCommand::JumpBack
+            | EditorCommand::JumpForward
+            | EditorCommand::Recenter
+            | EditorCommand::ScrollUp
+            | EditorCommand::ScrollDown
+            | EditorCommand::RemoveExtraCarets
+            | EditorCommand::ClearSearchHighlights
+            | EditorCommand::ToggleOverwrite
+            | EditorCommand::Copy) => {
+                let buffer = &mut self.buffers[self.current_buffer];
+                if !buffer.show_hex {
+                    buffer.end_undo_step();
+                    buffer.ensure_loaded(self.view.lines_needed());
+                    self.view.handle_command(command, buffer);
+                }
+            }
+            EditorCommand::Move(direction) => {
+                let buffer = &mut self.buffers[self.current_buffer];
+                if let (true, Some(bytes)) = (buffer.show_hex, buffer.raw_bytes()) {
+                    self.hex_view.move_offset(&direction, bytes.len());
+                } else {
+                    buffer.end_undo_step();
+                    buffer.ensure_loaded(self.view.lines_needed());
+                    self.view
+                        .handle_command(EditorCommand::Move(direction), buffer);
+                }
+            }
+        }
+    }
+
+    /// Changes the current buffer at the caret, unless it's shown as hex
+    /// or can't be changed.
+    fn edit(&mut self, edit: &Edit) {
+        if self.reject_if_read_only() {
+            return;
+        }
+        if self.buffer().show_hex {
+            self.set_message("Switch to the text view with Alt+X to edit.");
+            return;
+        }
+        let buffer = &mut self.buffers[self.current_buffer];
+        self.view.handle_edit(edit, buffer);
+        if matches!(edit, Edit::UndoEarlier | Edit::UndoLater) {
+            let (state, count) = self.buffer().undo_position();
+            self.set_message(&format!("Undo state {state} of {count}."));
+        }
+    }
+
+    /// Feeds a key press into the Ctrl+V sequence. While typing a code point,
+    /// the message bar shows the character it stands for; Enter inserts it.
+    /// Any other key which doesn't belong to the code point inserts it,
+    /// too, and returns `false` so the key can be handled as usual.
+    fn evaluate_quoted_insert_key(&mut self, key_event: KeyEvent) -> bool {
+        let Some(state) = self.quoted_insert.take() else {
+            return false;
+        };
+        let digits = match (state, key_event.code) {
+            (QuotedInsert::Started, KeyCode::Char(character))
+                if key_event.modifiers.contains(KeyModifiers::CONTROL) =>
             {
-                panic!("Received and discarded unsupported or non-press event.");
+                self.set_message("");
+                if let Some(character) = control_character(character) {
+                    self.edit(&Edit::InsertLiteral(character));
+                }
+                return true;
+            }
+            (QuotedInsert::Started, KeyCode::Char('u')) => String::new(),
+            (QuotedInsert::Started, KeyCode::Char(character)) => {
+                self.set_message("");
+                self.edit(&Edit::InsertLiteral(character));
+                return true;
+            }
+            (QuotedInsert::Started, KeyCode::Tab) => {
+                self.set_message("");
+                self.edit(&Edit::InsertLiteral('\t'));
+                return true;
+            }
+            (QuotedInsert::Started, _) | (QuotedInsert::CodePoint(_), KeyCode::Esc) => {
+                self.set_message("");
+                return true;
+            }
+            (QuotedInsert::CodePoint(mut digits), KeyCode::Char(character))
+                if character.is_ascii_hexdigit() && digits.len() < 6 =>
+            {
+                digits.push(character.to_ascii_uppercase());
+                digits
+            }
+            (QuotedInsert::CodePoint(mut digits), KeyCode::Backspace) => {
+                digits.pop();
+                digits
+            }
+            (QuotedInsert::CodePoint(digits), code) => {
+                match code_point(&digits) {
+                    Some(character) => {
+                        self.set_message("");
+                        self.edit(&Edit::InsertLiteral(character));
+                    }
+                    None => self.set_message(&format!("U+{digits} is not a valid code point.")),
+                }
+                return code == KeyCode::Enter;
+            }
+        };
+        let preview = match code_point(&digits) {
+            _ if digits.is_empty() => "type it in hexadecimal".to_string(),
+            Some(character) if character.is_control() => "a control character".to_string(),
+            Some(character) => character.to_string(),
+            None => "not a valid code point".to_string(),
+        };
+        self.set_message(&format!(
+            "U+{digits}: {preview} (Enter inserts it, Esc cancels)"
+        ));
+        self.quoted_insert = Some(QuotedInsert::CodePoint(digits));
+        true
+    }
+
+    /// Feeds a key press into the active prompt. `Enter` accepts the input,
+    /// `Esc` dismisses the prompt.
+    fn evaluate_prompt_key(&mut self, key_event: KeyEvent) {
+        match self.prompt_type {
+            PromptType::SaveAs => self.evaluate_save_as_key(key_event),
+            PromptType::FileChanged => self.evaluate_file_changed_key(key_event),
+            PromptType::LineEnding => self.evaluate_line_ending_key(key_event),
+            PromptType::CreateDirectories => self.evaluate_create_directories_key(key_event),
+            PromptType::PrivilegedSave => self.evaluate_privileged_save_key(key_event),
+            PromptType::RecentFile => self.evaluate_recent_file_key(key_event),
+            PromptType::SortOrder => self.evaluate_sort_order_key(key_event),
+            PromptType::KillRing => self.evaluate_kill_ring_key(key_event),
+            PromptType::Tag => self.evaluate_tag_key(key_event),
+            PromptType::Grep => self.evaluate_grep_key(key_event),
+            PromptType::FindFile | PromptType::SwitchBuffer | PromptType::GrepMatch => {
+                self.evaluate_picker_key(key_event);
             }
+            PromptType::Search(direction) => self.evaluate_search_key(key_event, direction),
+            PromptType::ReplaceSearch { all } => self.evaluate_replace_search_key(key_event, all),
+            PromptType::ReplaceWith => self.evaluate_replace_with_key(key_event),
+            PromptType::ReplaceConfirm => self.evaluate_replace_confirm_key(key_event),
+            PromptType::GoToLine => self.evaluate_go_to_line_key(key_event),
+            PromptType::CommandLine => self.evaluate_command_line_key(key_event),
+            PromptType::SetMark => self.evaluate_set_mark_key(key_event),
+            PromptType::JumpToMark => self.evaluate_jump_to_mark_key(key_event),
         }
     }
 
-    fn refresh_screen(&mut self) {
-        let _ = Terminal::hide_caret();
-        self.view.render();
+    fn evaluate_save_as_key(&mut self, key_event: KeyEvent) {
+        let Some(command_bar) = &mut self.command_bar else {
+            return;
+        };
+        match (key_event.code, key_event.modifiers) {
+            (KeyCode::Enter, _) => {
+                let file_name = command_bar.value().to_string();
+                self.dismiss_prompt();
+                self.save_as(&file_name);
+            }
+            (KeyCode::Esc, _) => {
+                self.dismiss_prompt();
+                self.set_message("Save aborted.");
+            }
+            _ => {
+                command_bar.handle_key(key_event);
+            }
+        }
+    }
 
-        let _ = Terminal::move_caret_to(self.view.get_position());
+    fn evaluate_line_ending_key(&mut self, key_event: KeyEvent) {
+        let line_ending = match key_event.code {
+            KeyCode::Char('l' | 'L') => LineEnding::Lf,
+            KeyCode::Char('c' | 'C') => LineEnding::CrLf,
+            KeyCode::Esc => {
+                self.dismiss_prompt();
+                return;
+            }
+            _ => return,
+        };
+        self.dismiss_prompt();
+        self.buffer_mut().set_line_ending(line_ending);
+        self.set_message(&format!("Line endings set to {line_ending}."));
+    }
 
-        let _ = Terminal::show_caret();
-        let _ = Terminal::execute();
+    fn start_line_ending_prompt(&mut self) {
+        if self.reject_if_read_only() {
+            return;
+        }
+        self.prompt_type = PromptType::LineEnding;
+        self.command_bar = Some(CommandBar::new(
+            &format!(
+                "Convert line endings from {} to (l)f or (c)rlf? ",
+                self.buffer().line_ending
+            ),
+            self.terminal_size,
+        ));
     }
-}
 
-impl Drop for Editor {
-    fn drop(&mut self) {
-        let _ = Terminal::terminate();
-        if self.should_quit {
+    fn evaluate_go_to_line_key(&mut self, key_event: KeyEvent) {
+        let Some(command_bar) = &mut self.command_bar else {
+            return;
+        };
+        match (key_event.code, key_event.modifiers) {
+            (KeyCode::Enter, _) => {
+                let input = command_bar.value().to_string();
+                self.dismiss_prompt();
+                if let Some(percent) = input.trim().strip_suffix('%') {
+                    match percent.trim().parse() {
+                        Ok(percent) => self.go_to_percent(percent),
+                        Err(_) => self.set_message(&format!("Not a percentage: {input}")),
+                    }
+                    return;
+                }
+                let (line, column) = match input.split_once(':') {
+                    Some((line, column)) => (line, Some(column)),
+                    None => (input.as_str(), None),
+                };
+                match (
+                    line.trim().parse(),
+                    column.map(|column| column.trim().parse()),
+                ) {
+                    (Ok(line), None) => self.go_to_line(line, None),
+                    (Ok(line), Some(Ok(column))) => self.go_to_line(line, Some(column)),
+                    _ => self.set_message(&format!("Not a line number: {input}")),
+                }
+            }
+            (KeyCode::Esc, _) => self.dismiss_prompt(),
+            _ => {
+                command_bar.handle_key(key_event);
+            }
+        }
+    }
+
+    fn start_go_to_line_prompt(&mut self) {
+        if self.buffer().show_hex {
+            self.set_message("Switch to the text view with Alt+X to go to a line.");
+            return;
+        }
+        self.prompt_type = PromptType::GoToLine;
+        self.command_bar = Some(CommandBar::new(
+            "Go to line (line, line:column or percent%): ",
+            self.terminal_size,
+        ));
+    }
+
+    fn start_command_line(&mut self) {
+        self.prompt_type = PromptType::CommandLine;
+        self.command_bar = Some(CommandBar::new(
+            &command_line_prompt(&[]),
+            self.terminal_size,
+        ));
+    }
+
+    /// Enter runs the command typed, and Tab completes its name, listing
+    /// the commands it could be in the prompt if there are several.
+    fn evaluate_command_line_key(&mut self, key_event: KeyEvent) {
+        let Some(command_bar) = &mut self.command_bar else {
+            return;
+        };
+        match (key_event.code, key_event.modifiers) {
+            (KeyCode::Enter, _) => {
+                let input = command_bar.value().to_string();
+                self.dismiss_prompt();
+                match commandline::parse(&input) {
+                    Ok(command) => self.run_line_command(command),
+                    Err(message) => self.set_message(&message),
+                }
+            }
+            (KeyCode::Esc, _) => self.dismiss_prompt(),
+            (KeyCode::Tab, _) => {
+                let candidates = commandline::complete(command_bar.value());
+                match candidates.as_slice() {
+                    [] => {}
+                    [(name, takes_argument)] => {
+                        let space = if *takes_argument { " " } else { "" };
+                        command_bar.set_value(&format!("{name}{space}"));
+                        command_bar.set_prompt(&command_line_prompt(&[]));
+                    }
+                    [(first, _), rest @ ..] => {
+                        let common = rest.iter().fold(*first, |common, (name, _)| {
+                            let len = common
+                                .chars()
+                                .zip(name.chars())
+                                .take_while(|(a, b)| a == b)
+                                .count();
+                            &common[..len]
+                        });
+                        command_bar.set_value(common);
+                        let names: Vec<&str> = candidates.iter().map(|(name, _)| *name).collect();
+                        command_bar.set_prompt(&command_line_prompt(&names));
+                    }
+                }
+            }
+            _ => {
+                command_bar.handle_key(key_event);
+            }
+        }
+    }
+
+    /// Carries out a command typed on the command line.
+    fn run_line_command(&mut self, command: LineCommand) {
+        match command {
+            LineCommand::Run(command) => self.execute(command),
+            LineCommand::Write(None) => self.save(),
+            LineCommand::Write(Some(file_name)) => {
+                if !self.reject_if_read_only() {
+                    self.save_as(&file_name);
+                }
+            }
+            LineCommand::WriteQuit => {
+                self.save();
+                if !self.buffer().is_dirty() {
+                    self.should_quit = true;
+                }
+            }
+            LineCommand::Set { key, value } => {
+                if !self.config.set(&key, &value) {
+                    self.set_message(&format!(
+                        "Unknown setting or invalid value: {key} = {value}"
+                    ));
+                    return;
+                }
+                self.configure_view();
+                self.set_message(&format!("{key} = {value}"));
+            }
+            LineCommand::Open(file_name) => self.open_file(&file_name),
+            LineCommand::GoToLine(line) => self.go_to_line(line, None),
+        }
+    }
+
+    /// Moves the caret to the line `percent` percent of the way through
+    /// the buffer, as Vim's `N%` does.
+    fn go_to_percent(&mut self, percent: usize) {
+        if self.buffer().is_large_file() {
+            self.set_message("Percentages aren't known in large-file mode.");
+            return;
+        }
+        let height = self.buffer().height();
+        let line = min(percent, 100)
+            .saturating_mul(height)
+            .saturating_add(99)
+            .checked_div(100)
+            .unwrap_or(0);
+        self.go_to_line(line.max(1), None);
+    }
+
+    /// Moves the caret to `line` and `column`, both counted from 1, staying
+    /// within the buffer. Without a column, the caret goes to the start of the line.
+    fn go_to_line(&mut self, line: usize, column: Option<usize>) {
+        let y = line.saturating_sub(1);
+        let buffer = &mut self.buffers[self.current_buffer];
+        buffer.end_undo_step();
+        // In large-file mode, the line may not have been read yet.
+        buffer.ensure_loaded(y.saturating_add(self.terminal_size.height));
+        let location = Location {
+            x: column.unwrap_or(1).saturating_sub(1),
+            y: min(y, buffer.height().saturating_sub(1)),
+        };
+        self.view.go_to(location, buffer);
+    }
+
+    fn evaluate_search_key(&mut self, key_event: KeyEvent, direction: SearchDirection) {
+        let Some(command_bar) = &mut self.command_bar else {
+            return;
+        };
+        match (key_event.code, key_event.modifiers) {
+            (KeyCode::Enter, _) => {
+                let query = command_bar.value().to_string();
+                self.dismiss_prompt();
+                match Pattern::new(&query, self.search_options) {
+                    Ok(pattern) if !query.is_empty() => {
+                        let buffer = &self.buffers[self.current_buffer];
+                        match self.view.search(&pattern, buffer) {
+                            Some(true) => {
+                                self.set_message(wrapped_message(direction));
+                            }
+                            Some(false) => {}
+                            None => self.set_message(&format!("No match for {query}.")),
+                        }
+                        self.search_history.add(&query);
+                        self.last_search = Some((pattern, direction));
+                    }
+                    Ok(_) => {}
+                    Err(_) => {
+                        self.set_message(&format!("{query} is not a valid regular expression."));
+                    }
+                }
+                self.view.finish_search(&self.buffers[self.current_buffer]);
+            }
+            (KeyCode::Esc, _) => {
+                self.dismiss_prompt();
+                self.view.cancel_search(&self.buffers[self.current_buffer]);
+            }
+            (KeyCode::Char('r'), KeyModifiers::ALT) => {
+                self.search_options.regex = !self.search_options.regex;
+                self.update_search();
+            }
+            (KeyCode::Char('c'), KeyModifiers::ALT) => {
+                self.search_options.case = self.search_options.case.next();
+                self.update_search();
+            }
+            (KeyCode::Char('w'), KeyModifiers::ALT) => {
+                self.search_options.whole_word = !self.search_options.whole_word;
+                self.update_search();
+            }
+            (KeyCode::Up, _) => {
+                let older = self
+                    .search_history_selection
+                    .map_or(0, |index| index.saturating_add(1));
+                if let Some(query) = self.search_history.queries().get(older) {
+                    command_bar.set_value(query);
+                    self.search_history_selection = Some(older);
+                    self.update_search();
+                }
+            }
+            (KeyCode::Down, _) => {
+                let Some(index) = self.search_history_selection else {
+                    return;
+                };
+                // Going past the newest query leaves an empty prompt to type into.
+                let newer = index.checked_sub(1);
+                let query = newer
+                    .and_then(|index| self.search_history.queries().get(index))
+                    .map_or("", String::as_str);
+                command_bar.set_value(query);
+                self.search_history_selection = newer;
+                self.update_search();
+            }
+            _ => {
+                if command_bar.handle_key(key_event) {
+                    self.update_search();
+                }
+            }
+        }
+    }
+
+    /// Searches for the text typed so far, saying so in the prompt if
+    /// there is no match. While the text isn't a valid regular expression,
+    /// the caret stays at the last match.
+    fn update_search(&mut self) {
+        let Some(command_bar) = &mut self.command_bar else {
+            return;
+        };
+        let PromptType::Search(direction) = self.prompt_type else {
+            return;
+        };
+        let query = command_bar.value();
+        let status = match Pattern::new(query, self.search_options) {
+            Ok(pattern) => {
+                let found = self
+                    .view
+                    .search(&pattern, &self.buffers[self.current_buffer]);
+                match (found, query.is_empty()) {
+                    (Some(true), _) => " (wrapped)",
+                    (Some(false), _) | (None, true) => "",
+                    (None, false) => " (no match)",
+                }
+            }
+            Err(_) => " (invalid pattern)",
+        };
+        command_bar.set_prompt(&search_prompt(
+            direction,
+            self.search_options,
+            self.view.searches_selection(),
+            status,
+        ));
+    }
+
+    /// Asks for text to search for, from the caret in `direction`, or only
+    /// within the selection if there is one. Enter leaves the caret at the
+    /// match, Esc goes back to where it was. Alt+R switches between literal
+    /// text and regular expressions, Alt+C between matching case, ignoring
+    /// it and smart case, and Alt+W whether only whole words match. Up and
+    /// Down go through earlier searches.
+    fn start_search_prompt(&mut self, direction: SearchDirection) {
+        if self.buffer().show_hex {
+            self.set_message("Switch to the text view with Alt+X to search.");
+            return;
+        }
+        self.buffer_mut().end_undo_step();
+        self.view.start_search(direction);
+        self.search_history_selection = None;
+        self.prompt_type = PromptType::Search(direction);
+        let prompt = search_prompt(
+            direction,
+            self.search_options,
+            self.view.searches_selection(),
+            "",
+        );
+        self.command_bar = Some(CommandBar::new(&prompt, self.terminal_size));
+    }
+
+    /// Goes to the next match of the last search, in its direction unless
+    /// `reverse` is set.
+    fn repeat_search(&mut self, reverse: bool) {
+        let Some((pattern, direction)) = &self.last_search else {
+            self.set_message("Nothing was searched for yet.");
+            return;
+        };
+        if self.buffer().show_hex {
+            self.set_message("Switch to the text view with Alt+X to search.");
+            return;
+        }
+        let direction = if reverse {
+            direction.reversed()
+        } else {
+            *direction
+        };
+        let buffer = &self.buffers[self.current_buffer];
+        match self.view.find_next(pattern, direction, buffer) {
+            Some(true) => self.set_message(wrapped_message(direction)),
+            Some(false) => {}
+            None => self.set_message(&format!("No match for {}.", pattern.text())),
+        }
+    }
+
+    /// Goes to the next occurrence of the word at the caret as a whole word,
+    /// matching case, and remembers it as the last search.
+    fn search_word_at_caret(&mut self) {
+        if self.buffer().show_hex {
+            self.set_message("Switch to the text view with Alt+X to search.");
+            return;
+        }
+        let Some(word) = self.view.word_at_caret(self.buffer()) else {
+            self.set_message("There is no word at the caret to search for.");
+            return;
+        };
+        let options = SearchOptions {
+            whole_word: true,
+            ..SearchOptions::default()
+        };
+        let Ok(pattern) = Pattern::new(&word, options) else {
+            return;
+        };
+        let buffer = &self.buffers[self.current_buffer];
+        if self
+            .view
+            .find_next(&pattern, SearchDirection::Forward, buffer)
+            == Some(true)
+        {
+            self.set_message(wrapped_message(SearchDirection::Forward));
+        }
+        self.last_search = Some((pattern, SearchDirection::Forward));
+    }
+
+    /// Says how many matches of the last search, or else of the word at the
+    /// caret, there are in the buffer, and which of them the caret is on.
+    fn count_matches(&mut self) {
+        if self.buffer().show_hex {
+            self.set_message("Switch to the text view with Alt+X to count matches.");
+            return;
+        }
+        let word_pattern = || {
+            let options = SearchOptions {
+                whole_word: true,
+                ..SearchOptions::default()
+            };
+            let word = self.view.word_at_caret(self.buffer())?;
+            Pattern::new(&word, options).ok()
+        };
+        let pattern = self
+            .last_search
+            .as_ref()
+            .map(|(pattern, _)| pattern.clone())
+            .or_else(word_pattern);
+        let Some(pattern) = pattern else {
+            self.set_message("Nothing was searched for yet, and there is no word at the caret.");
+            return;
+        };
+        let (total, current) = search::count(self.buffer(), &pattern, self.view.location());
+        let text = pattern.text();
+        let message = match (total, current) {
+            (0, _) => format!("No match for {text}."),
+            (_, Some(current)) => format!("Match {current} of {total} for {text}."),
+            (1, None) => format!("1 match for {text}."),
+            (_, None) => format!("{total} matches for {text}."),
+        };
+        self.set_message(&message);
+    }
+
+    /// Asks for text to replace, then for what to replace it with, and then
+    /// goes through the matches in the selection, or else after the caret,
+    /// asking about each one, or with `all` set, replaces all of them in the
+    /// selection or the buffer.
+    /// Alt+R, Alt+C and Alt+W switch how matches are found, as in a search.
+    fn start_replace_prompt(&mut self, all: bool) {
+        if self.reject_if_read_only() {
+            return;
+        }
+        if self.buffer().show_hex {
+            self.set_message("Switch to the text view with Alt+X to replace.");
+            return;
+        }
+        self.prompt_type = PromptType::ReplaceSearch { all };
+        let prompt = replace_prompt(self.search_options, all);
+        self.command_bar = Some(CommandBar::new(&prompt, self.terminal_size));
+    }
+
+    fn evaluate_replace_search_key(&mut self, key_event: KeyEvent, all: bool) {
+        let Some(command_bar) = &mut self.command_bar else {
+            return;
+        };
+        match (key_event.code, key_event.modifiers) {
+            (KeyCode::Enter, _) => {
+                let query = command_bar.value().to_string();
+                self.dismiss_prompt();
+                match Pattern::new(&query, self.search_options) {
+                    Ok(_) if query.is_empty() => {}
+                    Ok(pattern) => {
+                        self.replace = Some(Replace {
+                            pattern,
+                            replacement: String::new(),
+                            all,
+                            count: 0,
+                        });
+                        // Only regular expressions have groups to refer to.
+                        let hint = if self.search_options.regex && all {
+                            " ($1 for a group)"
+                        } else {
+                            ""
+                        };
+                        self.prompt_type = PromptType::ReplaceWith;
+                        self.command_bar = Some(CommandBar::new(
+                            &format!("Replace {query} with{hint}: "),
+                            self.terminal_size,
+                        ));
+                    }
+                    Err(_) => {
+                        self.set_message(&format!("{query} is not a valid regular expression."));
+                    }
+                }
+            }
+            (KeyCode::Esc, _) => self.dismiss_prompt(),
+            (KeyCode::Char('r'), KeyModifiers::ALT) => {
+                self.search_options.regex = !self.search_options.regex;
+                command_bar.set_prompt(&replace_prompt(self.search_options, all));
+            }
+            (KeyCode::Char('c'), KeyModifiers::ALT) => {
+                self.search_options.case = self.search_options.case.next();
+                command_bar.set_prompt(&replace_prompt(self.search_options, all));
+            }
+            (KeyCode::Char('w'), KeyModifiers::ALT) => {
+                self.search_options.whole_word = !self.search_options.whole_word;
+                command_bar.set_prompt(&replace_prompt(self.search_options, all));
+            }
+            _ => {
+                command_bar.handle_key(key_event);
+            }
+        }
+    }
+
+    fn evaluate_replace_with_key(&mut self, key_event: KeyEvent) {
+        let Some(command_bar) = &mut self.command_bar else {
+            return;
+        };
+        match (key_event.code, key_event.modifiers) {
+            (KeyCode::Enter, _) => {
+                let replacement = command_bar.value().to_string();
+                self.dismiss_prompt();
+                let Some(replace) = &mut self.replace else {
+                    return;
+                };
+                replace.replacement = replacement;
+                let buffer = &mut self.buffers[self.current_buffer];
+                buffer.end_undo_step();
+                if replace.all {
+                    replace.count =
+                        self.view
+                            .replace_all(&replace.pattern, &replace.replacement, buffer);
+                    self.finish_replace();
+                } else {
+                    let from = self.view.start_replace();
+                    self.ask_about_next_match(from);
+                }
+            }
+            (KeyCode::Esc, _) => {
+                self.dismiss_prompt();
+                self.replace = None;
+            }
+            _ => {
+                command_bar.handle_key(key_event);
+            }
+        }
+    }
+
+    /// `y` replaces the highlighted match, `n` skips it, `a` replaces it and
+    /// all further matches as one undo step, and `q` or Esc stops.
+    fn evaluate_replace_confirm_key(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Char('y' | 'Y') => {
+                self.dismiss_prompt();
+                let from = self.replace_match();
+                self.ask_about_next_match(from);
+            }
+            KeyCode::Char('n' | 'N') => {
+                self.dismiss_prompt();
+                let location = self.view.location();
+                self.ask_about_next_match(Location {
+                    x: location.x.saturating_add(1),
+                    ..location
+                });
+            }
+            KeyCode::Char('a' | 'A') => {
+                self.dismiss_prompt();
+                self.buffer_mut().start_undo_group();
+                loop {
+                    let from = self.replace_match();
+                    let (Some(replace), buffer) =
+                        (&self.replace, &self.buffers[self.current_buffer])
+                    else {
+                        break;
+                    };
+                    if !self.view.show_next_match(&replace.pattern, from, buffer) {
+                        break;
+                    }
+                }
+                self.buffer_mut().end_undo_group();
+                self.finish_replace();
+            }
+            KeyCode::Char('q' | 'Q') | KeyCode::Esc => {
+                self.dismiss_prompt();
+                self.finish_replace();
+            }
+            _ => {}
+        }
+    }
+
+    /// Highlights the first match at or after `from` and asks whether to
+    /// replace it, or finishes if there is none.
+    fn ask_about_next_match(&mut self, from: Location) {
+        let Some(replace) = &self.replace else {
+            return;
+        };
+        let buffer = &self.buffers[self.current_buffer];
+        if self.view.show_next_match(&replace.pattern, from, buffer) {
+            self.prompt_type = PromptType::ReplaceConfirm;
+            self.command_bar = Some(CommandBar::new(
+                "Replace this match? (y)es, (n)o, (a)ll, (q)uit ",
+                self.terminal_size,
+            ));
+        } else {
+            self.finish_replace();
+        }
+    }
+
+    /// Replaces the highlighted match. Returns where to look for the next one.
+    fn replace_match(&mut self) -> Location {
+        let Some(replace) = &mut self.replace else {
+            return self.view.location();
+        };
+        replace.count = replace.count.saturating_add(1);
+        self.view.replace_match(
+            &replace.pattern,
+            &replace.replacement,
+            &mut self.buffers[self.current_buffer],
+        )
+    }
+
+    /// Says how many matches were replaced, and keeps the pattern to
+    /// repeat the search with.
+    fn finish_replace(&mut self) {
+        self.view.finish_replace();
+        let Some(replace) = self.replace.take() else {
+            return;
+        };
+        let matches = if replace.count == 1 {
+            "match"
+        } else {
+            "matches"
+        };
+        self.set_message(&format!("Replaced {} {matches}.", replace.count));
+        self.last_search = Some((replace.pattern, SearchDirection::Forward));
+    }
+
+    fn evaluate_set_mark_key(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Char(name) if name.is_ascii_alphabetic() => {
+                self.dismiss_prompt();
+                let location = self.view.location();
+                self.buffer_mut().set_mark(name, location);
+                self.set_message(&format!(
+                    "Mark {name} set at line {}.",
+                    location.y.saturating_add(1)
+                ));
+            }
+            KeyCode::Esc => self.dismiss_prompt(),
+            _ => {}
+        }
+    }
+
+    fn evaluate_jump_to_mark_key(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Char(name) if name.is_ascii_alphabetic() => {
+                self.dismiss_prompt();
+                let buffer = &mut self.buffers[self.current_buffer];
+                if let Some(location) = buffer.mark(name) {
+                    buffer.end_undo_step();
+                    self.view.go_to(location, buffer);
+                } else {
+                    self.set_message(&format!("Mark {name} is not set."));
+                }
+            }
+            KeyCode::Esc => self.dismiss_prompt(),
+            _ => {}
+        }
+    }
+
+    /// Asks for the letter of a mark to set or jump to. The prompt lists
+    /// the marks set in the buffer along with their lines.
+    fn start_mark_prompt(&mut self, prompt_type: PromptType) {
+        if self.buffer().show_hex {
+            self.set_message("Switch to the text view with Alt+X to use marks.");
+            return;
+        }
+        let marks: Vec<String> = self
+            .buffer()
+            .marks()
+            .map(|(name, location)| format!("{name}:{}", location.y.saturating_add(1)))
+            .collect();
+        let action = if matches!(prompt_type, PromptType::SetMark) {
+            "Set mark (a-z)"
+        } else if marks.is_empty() {
+            self.set_message("No marks set. Set one with Alt+M.");
+            return;
+        } else {
+            "Jump to mark"
+        };
+        let prompt = if marks.is_empty() {
+            format!("{action}: ")
+        } else {
+            format!("{action} [{}]: ", marks.join(" "))
+        };
+        self.prompt_type = prompt_type;
+        self.command_bar = Some(CommandBar::new(&prompt, self.terminal_size));
+    }
+
+    fn evaluate_sort_order_key(&mut self, key_event: KeyEvent) {
+        let order = match key_event.code {
+            KeyCode::Char('l' | 'L') => SortOrder::Lexicographic,
+            KeyCode::Char('n' | 'N') => SortOrder::Numeric,
+            KeyCode::Char('u' | 'U') => SortOrder::Unique,
+            KeyCode::Esc => {
+                self.dismiss_prompt();
+                return;
+            }
+            _ => return,
+        };
+        self.dismiss_prompt();
+        self.edit(&Edit::SortLines(order));
+    }
+
+    fn start_sort_order_prompt(&mut self) {
+        if self.reject_if_read_only() {
+            return;
+        }
+        self.prompt_type = PromptType::SortOrder;
+        self.command_bar = Some(CommandBar::new(
+            "Sort lines (l)exicographically, (n)umerically or (u)nique? ",
+            self.terminal_size,
+        ));
+    }
+
+    fn evaluate_create_directories_key(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Char('y' | 'Y') => {
+                self.dismiss_prompt();
+                let Some(file_name) = self.pending_file_name.take() else {
+                    return;
+                };
+                let Some(directory) = missing_directory(&file_name) else {
+                    self.save_as(&file_name);
+                    return;
+                };
+                match create_dir_all(&directory) {
+                    Ok(()) => self.save_as(&file_name),
+                    Err(err) => self
+                        .set_message(&format!("Could not create {}: {err}", directory.display())),
+                }
+            }
+            KeyCode::Char('n' | 'N') | KeyCode::Esc => {
+                self.dismiss_prompt();
+                self.pending_file_name = None;
+                self.set_message("Save aborted.");
+            }
+            _ => {}
+        }
+    }
+
+    /// Asks whether to create the directories leading up to `file_name`
+    /// before saving there.
+    fn start_create_directories_prompt(&mut self, file_name: &str, directory: &Path) {
+        self.prompt_type = PromptType::CreateDirectories;
+        self.pending_file_name = Some(file_name.to_string());
+        self.command_bar = Some(CommandBar::new(
+            &format!("{} does not exist. Create it? (y/n) ", directory.display()),
+            self.terminal_size,
+        ));
+    }
+
+    fn evaluate_privileged_save_key(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Char('y' | 'Y') => {
+                self.dismiss_prompt();
+                if let Some(file_name) = self.pending_file_name.take() {
+                    self.privileged_save(&file_name);
+                }
+            }
+            KeyCode::Char('n' | 'N') | KeyCode::Esc => {
+                self.dismiss_prompt();
+                self.pending_file_name = None;
+                self.set_message("Save aborted.");
+            }
+            _ => {}
+        }
+    }
+
+    fn start_privileged_save_prompt(&mut self, file_name: &str) {
+        self.prompt_type = PromptType::PrivilegedSave;
+        self.pending_file_name = Some(file_name.to_string());
+        self.command_bar = Some(CommandBar::new(
+            &format!(
+                "Permission denied. Save with `{}`? (y/n) ",
+                self.config.privileged_save_command
+            ),
+            self.terminal_size,
+        ));
+    }
+
+    /// Saves through the configured command, handing the terminal over to
+    /// it meanwhile so it can ask for a password.
+    fn privileged_save(&mut self, file_name: &str) {
+        let _ = Terminal::terminate();
+        let result =
+            self.buffers[self.current_buffer].save_through_command(file_name, &self.config);
+        let _ = Terminal::initialize();
+        self.resize(self.terminal_size);
+        self.view.mark_redraw();
+        self.hex_view.mark_redraw();
+        let message = match result {
+            Ok(report) => {
+                self.ignored_disk_change = None;
+                self.watch_files();
+                self.finish_save(report)
+            }
+            Err(err) => format!("Could not save file: {err}"),
+        };
+        self.set_message(&message);
+    }
+
+    fn evaluate_recent_file_key(&mut self, key_event: KeyEvent) {
+        let count = self.recent_files.paths().len();
+        match key_event.code {
+            KeyCode::Up => {
+                self.recent_selection = self
+                    .recent_selection
+                    .checked_sub(1)
+                    .unwrap_or_else(|| count.saturating_sub(1));
+                self.show_recent_selection();
+            }
+            KeyCode::Down => {
+                let next = self.recent_selection.saturating_add(1);
+                self.recent_selection = if next < count { next } else { 0 };
+                self.show_recent_selection();
+            }
+            KeyCode::Enter => {
+                self.dismiss_prompt();
+                if let Some(file_name) = self.recent_files.paths().get(self.recent_selection) {
+                    let file_name = file_name.clone();
+                    self.open_file(&file_name);
+                }
+            }
+            KeyCode::Esc => self.dismiss_prompt(),
+            _ => {}
+        }
+    }
+
+    /// Lets the user pick one of the recently used files with Up and Down.
+    fn start_recent_file_prompt(&mut self) {
+        if self.recent_files.paths().is_empty() {
+            self.set_message("No recent files.");
+            return;
+        }
+        self.prompt_type = PromptType::RecentFile;
+        self.recent_selection = 0;
+        self.show_recent_selection();
+    }
+
+    fn show_recent_selection(&mut self) {
+        let paths = self.recent_files.paths();
+        let prompt = format!(
+            "Open recent ({}/{}, Up/Down to pick): ",
+            self.recent_selection.saturating_add(1),
+            paths.len()
+        );
+        let mut command_bar = CommandBar::new(&prompt, self.terminal_size);
+        if let Some(path) = paths.get(self.recent_selection) {
+            command_bar.set_value(path);
+        }
+        self.command_bar = Some(command_bar);
+    }
+
+    /// Lists the files under the working directory over the view, narrowed
+    /// down to those fuzzily matching what the user types, to open one.
+    fn start_file_finder(&mut self) {
+        let root = Path::new(".");
+        let entries: Vec<PickerEntry> = project_files(root)
+            .iter()
+            .map(|file| PickerEntry {
+                name: file
+                    .strip_prefix(root)
+                    .unwrap_or(file)
+                    .to_string_lossy()
+                    .into_owned(),
+                marked: false,
+            })
+            .collect();
+        if entries.is_empty() {
+            self.set_message("There are no files in the working directory.");
+            return;
+        }
+        self.start_picker(PromptType::FindFile, entries);
+    }
+
+    /// Lists the open buffers over the view, modified ones marked with a
+    /// `*`, narrowed down to those fuzzily matching what the user types,
+    /// to switch to one.
+    fn start_buffer_switcher(&mut self) {
+        if self.buffers.len() < 2 {
+            self.set_message("There are no other buffers to switch to.");
+            return;
+        }
+        let entries = self.buffer_entries();
+        self.start_picker(PromptType::SwitchBuffer, entries);
+    }
+
+    /// The open buffers as the buffer switcher and the tab line list them.
+    fn buffer_entries(&self) -> Vec<PickerEntry> {
+        self.buffers
+            .iter()
+            .map(|buffer| PickerEntry {
+                name: buffer
+                    .file_name
+                    .clone()
+                    .unwrap_or_else(|| "[No Name]".to_string()),
+                marked: buffer.is_dirty(),
+            })
+            .collect()
+    }
+
+    /// Shows `entries` over the view to pick one of them with Up and Down
+    /// after narrowing them down by typing. Enter picks the selected one.
+    fn start_picker(&mut self, prompt_type: PromptType, entries: Vec<PickerEntry>) {
+        let picker = FuzzyPicker::new(entries, VIEW_TOP, self.view_size());
+        self.prompt_type = prompt_type;
+        self.command_bar = Some(CommandBar::new(
+            &picker_prompt(prompt_type, &picker),
+            self.terminal_size,
+        ));
+        self.picker = Some(picker);
+    }
+
+    fn evaluate_picker_key(&mut self, key_event: KeyEvent) {
+        let (Some(command_bar), Some(picker)) = (&mut self.command_bar, &mut self.picker) else {
+            return;
+        };
+        match (key_event.code, key_event.modifiers) {
+            (KeyCode::Up, _) => picker.select_previous(),
+            (KeyCode::Down, _) => picker.select_next(),
+            (KeyCode::Enter, _) => {
+                let selected = picker.selected();
+                let file_name = selected
+                    .and_then(|index| picker.name(index))
+                    .map(ToString::to_string);
+                let prompt_type = self.prompt_type;
+                let grep_matches = std::mem::take(&mut self.grep_matches);
+                self.close_picker();
+                match (prompt_type, selected, file_name) {
+                    (PromptType::FindFile, _, Some(file_name)) => self.open_file(&file_name),
+                    (PromptType::SwitchBuffer, Some(index), _) => self.switch_to_buffer(index),
+                    (PromptType::GrepMatch, Some(index), _) => {
+                        if let Some(found) = grep_matches.get(index) {
+                            self.go_to_grep_match(found);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            (KeyCode::Esc, _) => {
+                self.close_picker();
+                self.grep_matches.clear();
+            }
+            _ => {
+                if command_bar.handle_key(key_event) {
+                    picker.filter(command_bar.value());
+                    command_bar.set_prompt(&picker_prompt(self.prompt_type, picker));
+                }
+            }
+        }
+    }
+
+    /// Hides the picker and the prompt, and redraws what it covered.
+    fn close_picker(&mut self) {
+        self.dismiss_prompt();
+        self.picker = None;
+        self.view.mark_redraw();
+        if let Some(split) = &mut self.split {
+            split.view.mark_redraw();
+        }
+        self.hex_view.mark_redraw();
+    }
+
+    fn evaluate_kill_ring_key(&mut self, key_event: KeyEvent) {
+        let count = self.view.kill_ring().len();
+        match key_event.code {
+            KeyCode::Up => {
+                self.kill_ring_selection = self
+                    .kill_ring_selection
+                    .checked_sub(1)
+                    .unwrap_or_else(|| count.saturating_sub(1));
+                self.show_kill_ring_selection();
+            }
+            KeyCode::Down => {
+                let next = self.kill_ring_selection.saturating_add(1);
+                self.kill_ring_selection = if next < count { next } else { 0 };
+                self.show_kill_ring_selection();
+            }
+            KeyCode::Enter => {
+                self.dismiss_prompt();
+                self.edit(&Edit::Paste(self.kill_ring_selection));
+            }
+            KeyCode::Esc => self.dismiss_prompt(),
+            _ => {}
+        }
+    }
+
+    /// Lets the user look through the texts cut or copied before with Up
+    /// and Down, and paste one of them.
+    fn start_kill_ring_prompt(&mut self) {
+        if self.reject_if_read_only() {
+            return;
+        }
+        if self.view.kill_ring().is_empty() {
+            self.set_message("The kill ring is empty. Cut with Ctrl+K or copy with Alt+W.");
+            return;
+        }
+        self.prompt_type = PromptType::KillRing;
+        self.kill_ring_selection = 0;
+        self.show_kill_ring_selection();
+    }
+
+    fn show_kill_ring_selection(&mut self) {
+        let entries = self.view.kill_ring();
+        let prompt = format!(
+            "Paste ({}/{}, Up/Down to pick): ",
+            self.kill_ring_selection.saturating_add(1),
+            entries.len()
+        );
+        // Line breaks are shown escaped, and long entries cut short, so
+        // the start of the entry fits into the bottom row.
+        let width = self
+            .terminal_size
+            .width
+            .saturating_sub(prompt.chars().count())
+            .saturating_sub(2);
+        let entry = entries
+            .get(self.kill_ring_selection)
+            .map(|entry| entry.replace('\n', "\\n"))
+            .unwrap_or_default();
+        let mut value: String = entry.chars().take(width).collect();
+        if entry.chars().count() > width {
+            value.push('…');
+        }
+        let mut command_bar = CommandBar::new(&prompt, self.terminal_size);
+        command_bar.set_value(&value);
+        self.command_bar = Some(command_bar);
+    }
+
+    /// Goes to the definition of the word at the caret, using the `tags`
+    /// file next to the buffer's file or in a directory above it, or else
+    /// in or above the working directory. Asks which one if there are several.
+    fn go_to_definition(&mut self) {
+        if self.buffer().show_hex {
+            self.set_message("Switch to the text view with Alt+X to go to a definition.");
+            return;
+        }
+        let Some(name) = self.view.word_at_caret(self.buffer()) else {
+            self.set_message("There is no word at the caret to look up.");
+            return;
+        };
+        let file_directory = self
+            .buffer()
+            .file_name
+            .as_deref()
+            .and_then(|file_name| Path::new(file_name).canonicalize().ok())
+            .and_then(|path| path.parent().map(Path::to_path_buf));
+        let Some(tags_path) = file_directory
+            .and_then(|directory| tags_file(&directory))
+            .or_else(|| {
+                env::current_dir()
+                    .ok()
+                    .and_then(|directory| tags_file(&directory))
+            })
+        else {
+            self.set_message("No tags file found. Create one with ctags -R.");
+            return;
+        };
+        match Tag::find(&name, &tags_path) {
+            Ok(tags) => match tags.as_slice() {
+                [] => self.set_message(&format!("No definition of {name} found.")),
+                [tag] => self.go_to_tag(&tag.clone()),
+                _ => {
+                    self.tag_matches = tags;
+                    self.tag_selection = 0;
+                    self.prompt_type = PromptType::Tag;
+                    self.show_tag_selection();
+                }
+            },
+            Err(err) => self.set_message(&format!("Could not read {}: {err}", tags_path.display())),
+        }
+    }
+
+    /// Opens the file of `tag` and moves the caret to the definition. The
+    /// jump is recorded in the jump list of the definition's buffer, as the
+    /// jump list doesn't reach across buffers.
+    fn go_to_tag(&mut self, tag: &Tag) {
+        self.open_file(&tag.file.to_string_lossy());
+        if !self.shows_file(&tag.file) {
+            // `open_file` already said why.
+            return;
+        }
+        let buffer = &mut self.buffers[self.current_buffer];
+        let y = match &tag.address {
+            Address::Line(line) => {
+                buffer.ensure_loaded(line.saturating_add(1));
+                Some(*line).filter(|&line| line < buffer.height())
+            }
+            Address::Pattern { text, whole_line } => {
+                buffer.ensure_loaded(usize::MAX);
+                (0..buffer.height()).find(|&y| {
+                    buffer.line(y).is_some_and(|line| {
+                        let line = line.to_string();
+                        if *whole_line {
+                            line == *text
+                        } else {
+                            line.starts_with(text.as_str())
+                        }
+                    })
+                })
+            }
+        };
+        let Some(y) = y else {
+            self.set_message(&format!(
+                "The definition of {} is no longer where the tags file says.",
+                tag.name
+            ));
+            return;
+        };
+        let x = buffer
+            .line(y)
+            .and_then(|line| line.find_word(&tag.name, 0))
+            .unwrap_or(0);
+        buffer.end_undo_step();
+        self.view.go_to(Location { x, y }, buffer);
+    }
+
+    /// Whether the current buffer is the file at `path`, e.g. to check
+    /// whether `open_file` succeeded.
+    fn shows_file(&self, path: &Path) -> bool {
+        let path = path.canonicalize().ok();
+        path.is_some()
+            && self
+                .buffer()
+                .file_name
+                .as_deref()
+                .and_then(|file_name| Path::new(file_name).canonicalize().ok())
+                == path
+    }
+
+    /// Opens the file whose path is at the caret, resolved against the
+    /// directory of the buffer's file and then the working directory.
+    /// A `:line` or `:line:column` after the path, as in compiler messages,
+    /// moves the caret there.
+    fn open_file_at_caret(&mut self) {
+        if self.buffer().show_hex {
+            self.set_message("Switch to the text view with Alt+X to open a file from it.");
+            return;
+        }
+        let Some(token) = self.view.path_at_caret(self.buffer()) else {
+            self.set_message("There is no file name at the caret.");
+            return;
+        };
+        let (path, line, column) = split_line_and_column(&token);
+        let file_directory = self
+            .buffer()
+            .file_name
+            .as_deref()
+            .and_then(|file_name| Path::new(file_name).parent().map(Path::to_path_buf));
+        let found = [file_directory, env::current_dir().ok()]
+            .into_iter()
+            .flatten()
+            .map(|directory| directory.join(path))
+            .find(|candidate| candidate.is_file());
+        let Some(found) = found else {
+            self.set_message(&format!(
+                "No file {path} found next to this file or in the working directory."
+            ));
+            return;
+        };
+        self.open_file(&found.to_string_lossy());
+        if let (true, Some(line)) = (self.shows_file(&found), line) {
+            self.go_to_line(line, column);
+        }
+    }
+
+    fn evaluate_tag_key(&mut self, key_event: KeyEvent) {
+        let count = self.tag_matches.len();
+        match key_event.code {
+            KeyCode::Up => {
+                self.tag_selection = self
+                    .tag_selection
+                    .checked_sub(1)
+                    .unwrap_or_else(|| count.saturating_sub(1));
+                self.show_tag_selection();
+            }
+            KeyCode::Down => {
+                let next = self.tag_selection.saturating_add(1);
+                self.tag_selection = if next < count { next } else { 0 };
+                self.show_tag_selection();
+            }
+            KeyCode::Enter => {
+                self.dismiss_prompt();
+                let tags = std::mem::take(&mut self.tag_matches);
+                if let Some(tag) = tags.get(self.tag_selection) {
+                    self.go_to_tag(tag);
+                }
+            }
+            KeyCode::Esc => {
+                self.dismiss_prompt();
+                self.tag_matches.clear();
+            }
+            _ => {}
+        }
+    }
+
+    fn show_tag_selection(&mut self) {
+        let prompt = format!(
+            "Definition ({}/{}, Up/Down to pick): ",
+            self.tag_selection.saturating_add(1),
+            self.tag_matches.len()
+        );
+        let mut command_bar = CommandBar::new(&prompt, self.terminal_size);
+        if let Some(tag) = self.tag_matches.get(self.tag_selection) {
+            command_bar.set_value(&tag.description());
+        }
+        self.command_bar = Some(command_bar);
+    }
+
+    /// Asks for text to search for in all files under the working
+    /// directory, then lists the lines found over the view to pick one to
+    /// go to. Alt+R, Alt+C and Alt+W switch how matches are
+    /// found, as in a search.
+    fn start_grep_prompt(&mut self) {
+        self.prompt_type = PromptType::Grep;
+        let prompt = grep_prompt(self.search_options);
+        self.command_bar = Some(CommandBar::new(&prompt, self.terminal_size));
+    }
+
+    fn evaluate_grep_key(&mut self, key_event: KeyEvent) {
+        let Some(command_bar) = &mut self.command_bar else {
+            return;
+        };
+        match (key_event.code, key_event.modifiers) {
+            (KeyCode::Enter, _) => {
+                let query = command_bar.value().to_string();
+                self.dismiss_prompt();
+                match Pattern::new(&query, self.search_options) {
+                    Ok(_) if query.is_empty() => {}
+                    Ok(pattern) => self.grep_working_directory(&query, pattern),
+                    Err(_) => {
+                        self.set_message(&format!("{query} is not a valid regular expression."));
+                    }
+                }
+            }
+            (KeyCode::Esc, _) => self.dismiss_prompt(),
+            (KeyCode::Char('r'), KeyModifiers::ALT) => {
+                self.search_options.regex = !self.search_options.regex;
+                command_bar.set_prompt(&grep_prompt(self.search_options));
+            }
+            (KeyCode::Char('c'), KeyModifiers::ALT) => {
+                self.search_options.case = self.search_options.case.next();
+                command_bar.set_prompt(&grep_prompt(self.search_options));
+            }
+            (KeyCode::Char('w'), KeyModifiers::ALT) => {
+                self.search_options.whole_word = !self.search_options.whole_word;
+                command_bar.set_prompt(&grep_prompt(self.search_options));
+            }
+            _ => {
+                command_bar.handle_key(key_event);
+            }
+        }
+    }
+
+    /// Searches the files under the working directory for `pattern`, or
+    /// those searched until Esc is pressed, then lists the lines found over
+    /// the view to pick one, narrowed down by typing like the file finder.
+    fn grep_working_directory(&mut self, query: &str, pattern: Pattern) {
+        self.set_message("Searching the working directory, Esc stops...");
+        self.refresh_screen();
+        let mut cancelled = false;
+        self.grep_matches = grep(Path::new("."), &pattern, || {
+            cancelled = cancelled || esc_pressed();
+            cancelled
+        });
+        self.set_message("");
+        if self.grep_matches.is_empty() {
+            let message = if cancelled {
+                "Stopped searching before finding a match.".to_string()
+            } else {
+                format!("No match for {query} in the working directory.")
+            };
+            self.set_message(&message);
+            return;
+        }
+        self.search_history.add(query);
+        self.last_search = Some((pattern, SearchDirection::Forward));
+        let entries = self
+            .grep_matches
+            .iter()
+            .map(|found| PickerEntry {
+                name: found.description(),
+                marked: false,
+            })
+            .collect();
+        self.start_picker(PromptType::GrepMatch, entries);
+    }
+
+    /// Opens the file of `found` and goes to the match in it.
+    fn go_to_grep_match(&mut self, found: &GrepMatch) {
+        self.open_file(&found.file.to_string_lossy());
+        if !self.shows_file(&found.file) {
+            // `open_file` already said why.
+            return;
+        }
+        let buffer = &mut self.buffers[self.current_buffer];
+        buffer.ensure_loaded(found.line.saturating_add(1));
+        if found.line >= buffer.height() {
+            self.set_message(&format!(
+                "{} no longer has a line {}.",
+                found.file.display(),
+                found.line.saturating_add(1)
+            ));
+            return;
+        }
+        buffer.end_undo_step();
+        let location = Location {
+            x: found.column,
+            y: found.line,
+        };
+        self.view.go_to(location, buffer);
+    }
+
+    fn evaluate_file_changed_key(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Char('o' | 'O') => {
+                self.dismiss_prompt();
+                self.force_save();
+            }
+            KeyCode::Char('r' | 'R') => {
+                self.dismiss_prompt();
+                self.reload();
+            }
+            KeyCode::Char('c' | 'C') | KeyCode::Esc => {
+                self.ignored_disk_change = self.buffer().changed_on_disk();
+                self.dismiss_prompt();
+                self.set_message("Kept the version in the editor.");
+            }
+            _ => {}
+        }
+    }
+
+    /// Asks how to proceed if another program modified the file since we
+    /// loaded or saved it; called when the terminal regains focus.
+    fn check_disk_change(&mut self) {
+        if self.command_bar.is_some() {
+            return;
+        }
+        if let Some(mtime) = self.buffer().changed_on_disk() {
+            if self.ignored_disk_change != Some(mtime) {
+                self.start_file_changed_prompt();
+            }
+        }
+    }
+
+    fn start_file_changed_prompt(&mut self) {
+        self.prompt_type = PromptType::FileChanged;
+        self.command_bar = Some(CommandBar::new(
+            "File changed on disk: (o)verwrite, (r)eload, (c)ancel? ",
+            self.terminal_size,
+        ));
+    }
+
+    fn reload(&mut self) {
+        let message = match self.buffers[self.current_buffer].reload(&self.config) {
+            Ok(()) => {
+                self.view.snap_location(&self.buffers[self.current_buffer]);
+                self.view.mark_redraw();
+                format!(
+                    "Reloaded {} after it changed on disk.",
+                    self.buffer().file_name.as_deref().unwrap_or_default()
+                )
+            }
+            Err(err) => format!("Could not reload file: {err}"),
+        };
+        self.ignored_disk_change = None;
+        self.set_message(&message);
+    }
+
+    /// Tells the user that the command they issued would change a read-only
+    /// buffer, or one in large-file mode which couldn't be read completely
+    /// for editing. Returns whether the command has to be rejected.
+    fn reject_if_read_only(&mut self) -> bool {
+        if self.buffer().read_only {
+            self.set_message("Buffer is read-only; command rejected.");
+            return true;
+        }
+        let Err(err) = self.buffer_mut().make_editable() else {
+            return false;
+        };
+        let message = if self.buffer().read_only {
+            format!("Can't edit the file: {err}. It is read-only now.")
+        } else {
+            format!("Could not read the file for editing: {err}")
+        };
+        self.set_message(&message);
+        true
+    }
+
+    fn save(&mut self) {
+        if self.reject_if_read_only() {
+            return;
+        }
+        let Some(file_name) = self.buffer().file_name.clone() else {
+            self.start_save_as();
+            return;
+        };
+        if let Some(directory) = missing_directory(&file_name) {
+            self.start_create_directories_prompt(&file_name, &directory);
+            return;
+        }
+        if self.buffer().changed_on_disk().is_some() {
+            self.start_file_changed_prompt();
+            return;
+        }
+        self.force_save();
+    }
+
+    /// Saves without checking whether the file was changed on disk.
+    fn force_save(&mut self) {
+        let message = match self.buffers[self.current_buffer].save(&self.config) {
+            Ok(report) => {
+                self.ignored_disk_change = None;
+                self.finish_save(report)
+            }
+            Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+                let file_name = self.buffer().file_name.clone().unwrap_or_default();
+                self.start_privileged_save_prompt(&file_name);
+                return;
+            }
+            Err(err) => format!("Could not save file: {err}"),
+        };
+        self.set_message(&message);
+    }
+
+    fn start_save_as(&mut self) {
+        if self.reject_if_read_only() {
+            return;
+        }
+        self.prompt_type = PromptType::SaveAs;
+        self.command_bar = Some(CommandBar::new("Save as: ", self.terminal_size));
+    }
+
+    fn save_as(&mut self, file_name: &str) {
+        if file_name.is_empty() {
+            self.set_message("Save aborted.");
+            return;
+        }
+        if let Some(directory) = missing_directory(file_name) {
+            self.start_create_directories_prompt(file_name, &directory);
+            return;
+        }
+        let message = match self.buffers[self.current_buffer].save_as(file_name, &self.config) {
+            Ok(report) => {
+                self.watch_files();
+                self.finish_save(report)
+            }
+            Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+                self.start_privileged_save_prompt(file_name);
+                return;
+            }
+            Err(err) => format!("Could not save file: {err}"),
+        };
+        self.set_message(&message);
+    }
+
+    /// Remembers the saved file and redraws lines the save-time transforms
+    /// changed. Returns the message confirming the save.
+    fn finish_save(&mut self, report: SaveReport) -> String {
+        if let Some(file_name) = &self.buffers[self.current_buffer].file_name {
+            self.recent_files.add(file_name);
+        }
+        if report.changed_lines() {
+            self.refresh_views_of(self.current_buffer);
+        }
+        format!(
+            "Saved {} lines to {}.{report}",
+            self.buffer().height(),
+            self.buffer().file_name.as_deref().unwrap_or_default()
+        )
+    }
+
+    /// Hides the prompt and gives the bottom row back to the message bar.
+    fn dismiss_prompt(&mut self) {
+        self.command_bar = None;
+        self.message_bar.resize(self.terminal_size);
+    }
+
+    /// Switches between the text and the hex dump of a binary file.
+    fn toggle_hex_view(&mut self) {
+        if self.split.is_some() {
+            self.set_message("Close the other window with Alt+0 to use the hex view.");
+            return;
+        }
+        let buffer = &mut self.buffers[self.current_buffer];
+        if buffer.raw_bytes().is_none() {
+            self.set_message("Hex view is only available for binary or non-UTF-8 files.");
+            return;
+        }
+        buffer.show_hex = !buffer.show_hex;
+        if buffer.show_hex {
+            buffer.refresh_raw_bytes();
+        }
+        self.hex_view.mark_redraw();
+        self.view.mark_redraw();
+    }
+
+    /// Shows `message` in the message bar until the next key is pressed, or
+    /// until it expires.
+    fn set_message(&mut self, message: &str) {
+        self.message_bar.update_message(message);
+    }
+
+    /// Splits the terminal between the tab line in the first row, the view,
+    /// the status bar in the second-to-last row and the message bar in the
+    /// last row.
+    fn resize(&mut self, size: Size) {
+        self.terminal_size = size;
+        let view_size = self.view_size();
+        if let Some(picker) = &mut self.picker {
+            picker.resize(VIEW_TOP, view_size);
+        }
+        self.layout();
+        self.hex_view.resize(VIEW_TOP, view_size);
+        self.tab_line.resize(size);
+        self.status_bar.resize(size);
+        self.message_bar.resize(size);
+        if let Some(command_bar) = &mut self.command_bar {
+            command_bar.resize(size);
+        }
+    }
+
+    /// Places the windows: the focused one across the whole view, or if the
+    /// screen is split, both of them on either side of the row or column
+    /// between them.
+    fn layout(&mut self) {
+        let view_size = self.view_size();
+        let Some(split) = &mut self.split else {
+            self.view.resize(
+                Position {
+                    row: VIEW_TOP,
+                    col: 0,
+                },
+                view_size,
+                &self.buffers[self.current_buffer],
+            );
+            return;
+        };
+        let (first_size, second_origin, second_size) =
+            split_sizes(view_size, split.layout, split.first_size);
+        let first = (
+            Position {
+                row: VIEW_TOP,
+                col: 0,
+            },
+            first_size,
+        );
+        let second = (
+            Position {
+                row: second_origin.row.saturating_add(VIEW_TOP),
+                ..second_origin
+            },
+            second_size,
+        );
+        let (focused, other) = if split.first {
+            (second, first)
+        } else {
+            (first, second)
+        };
+        self.view
+            .resize(focused.0, focused.1, &self.buffers[self.current_buffer]);
+        split
+            .view
+            .resize(other.0, other.1, &self.buffers[split.buffer]);
+    }
+
+    /// Draws the window which doesn't have the focus, if the screen is
+    /// split, and what's between the windows: a row showing the name of the
+    /// upper window's file, or a column of lines side by side. The other
+    /// window is drawn again along with the focused one if they show the
+    /// same buffer.
+    fn render_other_window(&mut self, focused_redrawn: bool) {
+        let view_size = self.view_size();
+        let Some(split) = &mut self.split else {
+            return;
+        };
+        if focused_redrawn && split.buffer == self.current_buffer {
+            split.view.mark_redraw();
+        }
+        let redraw_separator = focused_redrawn || split.view.needs_redraw();
+        let buffer = &mut self.buffers[split.buffer];
+        buffer.ensure_loaded(split.view.lines_needed());
+        split.view.render(buffer);
+        if !redraw_separator {
+            return;
+        }
+        let (first_size, _, _) = split_sizes(view_size, split.layout, split.first_size);
+        if split.layout == SplitLayout::SideBySide {
+            for row in 0..view_size.height {
+                let result = Terminal::move_caret_to(Position {
+                    row: row.saturating_add(VIEW_TOP),
+                    col: first_size.width,
+                })
+                .and_then(|()| Terminal::print("│"));
+                debug_assert!(result.is_ok(), "Failed to render window separator");
+            }
+            return;
+        }
+        let upper = if split.first {
+            split.buffer
+        } else {
+            self.current_buffer
+        };
+        let buffer = &self.buffers[upper];
+        let name = buffer.file_name.as_deref().unwrap_or("[No Name]");
+        let modified = if buffer.is_dirty() { " [modified]" } else { "" };
+        let width = self.terminal_size.width;
+        let text: String = format!("{name}{modified}{:width$}", "")
+            .chars()
+            .take(width)
+            .collect();
+        let result =
+            Terminal::print_inverted_row(first_size.height.saturating_add(VIEW_TOP), &text);
+        debug_assert!(result.is_ok(), "Failed to render window separator");
+    }
+
+    /// The size of the view: the terminal between the tab line and the
+    /// status bar.
+    const fn view_size(&self) -> Size {
+        Size {
+            height: self
+                .terminal_size
+                .height
+                .saturating_sub(2)
+                .saturating_sub(VIEW_TOP),
+            width: self.terminal_size.width,
+        }
+    }
+
+    fn refresh_screen(&mut self) {
+        let _ = Terminal::hide_caret();
+        self.follow_changes();
+        let buffer = &mut self.buffers[self.current_buffer];
+        let hex_bytes = buffer.raw_bytes().filter(|_| buffer.show_hex);
+        let mut status = buffer.get_status();
+        let mut focused_redrawn = false;
+        if let Some(bytes) = hex_bytes {
+            self.hex_view.render(bytes);
+            status.hex_offset = Some(self.hex_view.offset());
+        } else {
+            buffer.ensure_loaded(self.view.lines_needed());
+            focused_redrawn = self.view.needs_redraw();
+            self.view.render(buffer);
+            status.overwrite = self.view.is_overwrite();
+            let caret = self.view.location();
+            let line = caret.y.saturating_add(1);
+            status.caret = Some((line, caret.x.saturating_add(1)));
+            if !buffer.is_large_file() {
+                status.percent = Some(percent_of(min(line, buffer.height()), buffer.height()));
+            }
+        }
+        self.render_other_window(focused_redrawn);
+        let picker = self.picker.as_ref();
+        if let Some(picker) = picker {
+            picker.render();
+        }
+        // While switching buffers, the tab of the one picked is highlighted.
+        let active = picker
+            .filter(|_| matches!(self.prompt_type, PromptType::SwitchBuffer))
+            .and_then(FuzzyPicker::selected)
+            .unwrap_or(self.current_buffer);
+        self.tab_line.update_tabs(&self.buffer_entries(), active);
+        self.tab_line.render();
+        self.status_bar.update_status(status);
+        self.status_bar.render();
+
+        let caret_position = if let Some(command_bar) = &mut self.command_bar {
+            command_bar.render();
+            Position {
+                col: command_bar.caret_col(),
+                row: command_bar.position_y(),
+            }
+        } else {
+            self.message_bar.render();
+            if self.buffer().show_hex {
+                self.hex_view.get_position()
+            } else {
+                self.view.get_position(self.buffer())
+            }
+        };
+        let _ = Terminal::move_caret_to(caret_position);
+        let _ = Terminal::set_caret_shape(
+            self.command_bar.is_none() && !self.buffer().show_hex && self.view.is_overwrite(),
+        );
+
+        let _ = Terminal::show_caret();
+        let _ = Terminal::execute();
+    }
+}
+
+/// The rows of `size` if windows are stacked in it, or its columns if they
+/// are side by side.
+const fn along(size: Size, layout: SplitLayout) -> usize {
+    match layout {
+        SplitLayout::Stacked => size.height,
+        SplitLayout::SideBySide => size.width,
+    }
+}
+
+/// How the view is split between two windows, with the upper or left one
+/// `first_size` rows or columns big as far as there's room: the size of that
+/// one, where the other one starts, past the row or column between them,
+/// and its size.
+fn split_sizes(view_size: Size, layout: SplitLayout, first_size: usize) -> (Size, Position, Size) {
+    let total = along(view_size, layout);
+    // Each window keeps at least one row or column.
+    let first = first_size.clamp(1, total.saturating_sub(2).max(1));
+    let second_start = first.saturating_add(1);
+    let second = total.saturating_sub(second_start);
+    match layout {
+        SplitLayout::Stacked => (
+            Size {
+                height: first,
+                width: view_size.width,
+            },
+            Position {
+                row: second_start,
+                col: 0,
+            },
+            Size {
+                height: second,
+                width: view_size.width,
+            },
+        ),
+        SplitLayout::SideBySide => (
+            Size {
+                height: view_size.height,
+                width: first,
+            },
+            Position {
+                row: 0,
+                col: second_start,
+            },
+            Size {
+                height: view_size.height,
+                width: second,
+            },
+        ),
+    }
+}
+
+/// The prompt of the command line, listing `candidates` for the name typed
+/// so far if Tab found several.
+fn command_line_prompt(candidates: &[&str]) -> String {
+    if candidates.is_empty() {
+        ":".to_string()
+    } else {
+        format!("({}) :", candidates.join(" "))
+    }
+}
+
+/// What to tell the user when a search in `direction` went on from the
+/// other end of the buffer.
+const fn wrapped_message(direction: SearchDirection) -> &'static str {
+    match direction {
+        SearchDirection::Forward => "Search wrapped around to the top.",
+        SearchDirection::Backward => "Search wrapped around to the bottom.",
+    }
+}
+
+/// The prompt of a search in `direction`, followed by `status`, e.g. to
+/// say there is no match.
+fn search_prompt(
+    direction: SearchDirection,
+    options: SearchOptions,
+    in_selection: bool,
+    status: &str,
+) -> String {
+    let kind = if options.regex {
+        "Regex search"
+    } else {
+        "Search"
+    };
+    let direction = match direction {
+        SearchDirection::Forward => "",
+        SearchDirection::Backward => " backward",
+    };
+    let region = if in_selection { " in selection" } else { "" };
+    let label = options_label(options);
+    format!("{kind}{direction}{region}{label}{status}: ")
+}
+
+/// The prompt asking for the text to replace, all at once with `all` set.
+fn replace_prompt(options: SearchOptions, all: bool) -> String {
+    let all = if all { " all" } else { "" };
+    let regex = if options.regex { " regex" } else { "" };
+    let label = options_label(options);
+    format!("Replace{all}{regex}{label}: ")
+}
+
+/// How the prompts of searches show the case mode and whole-word matching,
+/// e.g. " (ignoring case, whole words)"; the defaults show as nothing.
+fn options_label(options: SearchOptions) -> String {
+    let mut parts = Vec::new();
+    match options.case {
+        CaseMode::Sensitive => {}
+        CaseMode::Insensitive => parts.push("ignoring case"),
+        CaseMode::Smart => parts.push("smart case"),
+    }
+    if options.whole_word {
+        parts.push("whole words");
+    }
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", parts.join(", "))
+    }
+}
+
+/// The prompt asking for the text to search for in all files.
+fn grep_prompt(options: SearchOptions) -> String {
+    let regex = if options.regex { " regex" } else { "" };
+    let label = options_label(options);
+    format!("Grep{regex}{label}: ")
+}
+
+/// Whether Esc was pressed since the last check, without waiting for a
+/// key, e.g. to stop a long search. Other events meanwhile are dropped.
+fn esc_pressed() -> bool {
+    let mut pressed = false;
+    while poll(Duration::ZERO).unwrap_or(false) {
+        if let Ok(Event::Key(KeyEvent {
+            code: KeyCode::Esc,
+            kind: KeyEventKind::Press,
+            ..
+        })) = read()
+        {
+            pressed = true;
+        }
+    }
+    pressed
+}
+
+/// The prompt of the picker for `prompt_type`, saying how many entries match.
+fn picker_prompt(prompt_type: PromptType, picker: &FuzzyPicker) -> String {
+    let title = match prompt_type {
+        PromptType::SwitchBuffer => "Switch buffer",
+        PromptType::GrepMatch => "Go to match",
+        _ => "Find file",
+    };
+    format!(
+        "{title} ({}/{}, Up/Down to pick): ",
+        picker.match_count(),
+        picker.entry_count()
+    )
+}
+
+/// What percentage `part` is of `whole`, rounded down; 100 for an empty whole.
+fn percent_of(part: usize, whole: usize) -> usize {
+    part.saturating_mul(100).checked_div(whole).unwrap_or(100)
+}
+
+/// The character with the hexadecimal code point `digits`, if there is one.
+fn code_point(digits: &str) -> Option<char> {
+    u32::from_str_radix(digits, 16)
+        .ok()
+        .and_then(char::from_u32)
+}
+
+/// The control character typed as Ctrl+`character`, e.g. U+0001 for
+/// Ctrl+A and a tab for Ctrl+I.
+fn control_character(character: char) -> Option<char> {
+    match character.to_ascii_uppercase() {
+        '?' => Some('\u{7f}'),
+        upper @ ('@'..='_') => char::from_u32(u32::from(upper) ^ 0x40),
+        _ => None,
+    }
+}
+
+/// Splits a `:line` or `:line:column` suffix off `token`, ignoring
+/// punctuation after it as at the end of a sentence.
+fn split_line_and_column(token: &str) -> (&str, Option<usize>, Option<usize>) {
+    let number = |text: &str| text.parse::<usize>().ok().filter(|&number| number > 0);
+    let token = token.trim_end_matches(['.', ':']);
+    let Some((rest, last)) = token
+        .rsplit_once(':')
+        .filter(|(_, last)| number(last).is_some())
+    else {
+        return (token, None, None);
+    };
+    match rest
+        .rsplit_once(':')
+        .filter(|(_, line)| number(line).is_some())
+    {
+        Some((path, line)) => (path, number(line), number(last)),
+        None => (rest, number(last), None),
+    }
+}
+
+/// The directory `file_name` would be saved in, if it doesn't exist yet.
+fn missing_directory(file_name: &str) -> Option<PathBuf> {
+    Path::new(file_name)
+        .parent()
+        .filter(|directory| !directory.as_os_str().is_empty() && !directory.exists())
+        .map(Path::to_path_buf)
+}
+
+impl Drop for Editor {
+    fn drop(&mut self) {
+        let _ = Terminal::terminate();
+        self.remember_locations();
+        if let Some(index) = self.filter_buffer {
+            if self.should_quit {
+                self.write_filter_output(index);
+            }
+        } else if self.should_quit {
             let _ = Terminal::print("Goodbye.\r\n");
         }
     }
@@ -0,0 +1,76 @@
+/// The command line hecto was started with.
+#[derive(Default)]
+pub struct Arguments {
+    /// The files to open, each into its own buffer. `-` stands for stdin.
+    pub file_names: Vec<String>,
+    /// Open the file without allowing any changes (`--readonly` or `-R`).
+    pub read_only: bool,
+    /// Read stdin into the first buffer and write it to stdout on quit,
+    /// so hecto can sit in a pipeline (`--filter`).
+    pub filter: bool,
+    /// The line to start at in the first file, counted from 1 (`+N`).
+    pub start_line: Option<usize>,
+}
+
+impl Arguments {
+    /// Parses the arguments following the program name. Unknown flags
+    /// are ignored; every other argument names a file to open.
+    pub fn parse(args: impl Iterator<Item = String>) -> Self {
+        let mut arguments = Self::default();
+        for arg in args {
+            match arg.as_str() {
+                "--readonly" | "-R" => arguments.read_only = true,
+                "--filter" => arguments.filter = true,
+                line if line.len() > 1 && line.starts_with('+') => {
+                    if let Ok(line) = line[1..].parse() {
+                        arguments.start_line = Some(line);
+                    }
+                }
+                flag if flag.starts_with("--") => {}
+                _ => arguments.file_names.push(arg),
+            }
+        }
+        if arguments.filter && !arguments.file_names.iter().any(|name| name == "-") {
+            arguments.file_names.insert(0, "-".to_string());
+        }
+        arguments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Arguments;
+
+    fn parse(args: &[&str]) -> Arguments {
+        Arguments::parse(args.iter().map(ToString::to_string))
+    }
+
+    #[test]
+    fn takes_files_flags_and_a_start_line() {
+        let arguments = parse(&["-R", "+12", "a.txt", "--unknown", "b.txt"]);
+        assert_eq!(arguments.file_names, ["a.txt", "b.txt"]);
+        assert!(arguments.read_only);
+        assert!(!arguments.filter);
+        assert_eq!(arguments.start_line, Some(12));
+    }
+
+    #[test]
+    fn plus_without_a_number_is_a_file_name_or_ignored() {
+        assert_eq!(parse(&["+"]).file_names, ["+"]);
+        let arguments = parse(&["+abc"]);
+        assert_eq!(arguments.start_line, None);
+        assert!(arguments.file_names.is_empty());
+    }
+
+    #[test]
+    fn filter_reads_stdin_first_unless_it_is_named() {
+        let arguments = parse(&["--filter", "a.txt"]);
+        assert!(arguments.filter);
+        assert_eq!(arguments.file_names, ["-", "a.txt"]);
+        assert_eq!(
+            parse(&["a.txt", "-", "--filter"]).file_names,
+            ["a.txt", "-"]
+        );
+        assert_eq!(parse(&["-"]).file_names, ["-"]);
+    }
+}
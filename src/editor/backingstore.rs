@@ -0,0 +1,79 @@
+use std::{borrow::Cow, io::Error};
+
+use super::{largefile::LargeFile, line::Line, mappedfile::MappedFile, rope::Rope};
+
+/// Where a `Buffer` keeps its lines.
+pub enum BackingStore {
+    /// All lines, held in memory.
+    Lines(Rope<Line>),
+    /// A large file, whose lines are indexed and read as needed.
+    Lazy(LargeFile),
+    /// A large read-only file mapped into memory.
+    Mapped(MappedFile),
+}
+
+impl Default for BackingStore {
+    fn default() -> Self {
+        Self::Lines(Rope::default())
+    }
+}
+
+impl BackingStore {
+    /// The number of lines available, which for large files is the
+    /// number read or indexed so far.
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Lines(lines) => lines.len(),
+            Self::Lazy(file) => file.len(),
+            Self::Mapped(mapped) => mapped.len(),
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<Cow<'_, Line>> {
+        match self {
+            Self::Lines(lines) => lines.get(index).map(Cow::Borrowed),
+            Self::Lazy(file) => file.line(index).map(Cow::Owned),
+            Self::Mapped(mapped) => mapped.line(index).map(Cow::Owned),
+        }
+    }
+
+    /// Reads a large file completely so that its lines can be edited.
+    /// Mapped files are read-only and stay mapped.
+    pub fn make_editable(&mut self) -> Result<(), Error> {
+        if let Self::Lazy(file) = self {
+            *self = Self::Lines(file.read_all()?);
+        }
+        Ok(())
+    }
+
+    /// The lines for editing in place, unless they are still read from a
+    /// large file: see `make_editable`.
+    pub fn lines_mut(&mut self) -> Option<&mut Rope<Line>> {
+        match self {
+            Self::Lines(lines) => Some(lines),
+            Self::Lazy(_) | Self::Mapped(_) => None,
+        }
+    }
+
+    /// Makes at least `line_count` lines available, unless the file has fewer.
+    pub fn ensure_loaded(&mut self, line_count: usize) {
+        match self {
+            Self::Lines(_) => {}
+            Self::Lazy(file) => file.index_lines(line_count),
+            Self::Mapped(mapped) => mapped.index_lines(line_count),
+        }
+    }
+
+    /// For large files, how much of the file has been read so far, in percent.
+    pub fn percent_read(&self) -> Option<u64> {
+        match self {
+            Self::Lines(_) => None,
+            Self::Lazy(file) => Some(file.percent_read()),
+            Self::Mapped(mapped) => Some(mapped.percent_read()),
+        }
+    }
+
+    pub const fn is_large_file(&self) -> bool {
+        !matches!(self, Self::Lines(_))
+    }
+}
@@ -0,0 +1,775 @@
+use std::{
+    borrow::Cow,
+    cmp::min,
+    collections::BTreeMap,
+    ffi::OsString,
+    fs::{
+        copy, create_dir_all, metadata, read, remove_file, rename, set_permissions, File,
+        OpenOptions,
+    },
+    io::{Error, ErrorKind, Write},
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+    time::SystemTime,
+};
+
+use super::{
+    backingstore::BackingStore,
+    config::Config,
+    documentstatus::DocumentStatus,
+    encoding::Encoding,
+    filetype::FileType,
+    history::{Change, History},
+    largefile::LargeFile,
+    lastlocations,
+    line::Line,
+    lineending::LineEnding,
+    location::Location,
+    mappedfile::MappedFile,
+    savereport::SaveReport,
+    undofile,
+};
+
+// clippy::struct_excessive_bools: the flags are independent of each other,
+// not the states of one state machine.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Default)]
+pub struct Buffer {
+    store: BackingStore,
+    pub file_name: Option<String>,
+    /// How the file is encoded on disk.
+    pub encoding: Encoding,
+    /// How lines are terminated on disk.
+    pub line_ending: LineEnding,
+    /// Rejects all commands which would change the buffer or its file.
+    pub read_only: bool,
+    /// The file's last line isn't terminated, so no line ending is written
+    /// after it either.
+    missing_final_newline: bool,
+    /// The edits made to the lines, for undo and redo.
+    history: History,
+    /// Whether the lines have changed since they were last loaded or saved.
+    dirty: bool,
+    /// The modification time of the file when it was last loaded or saved.
+    disk_mtime: Option<SystemTime>,
+    /// Where the caret was when the user last switched away from this
+    /// buffer, or when hecto was last quit while the file was open.
+    pub last_location: Location,
+    /// The locations the user marked, by name. They move along with the
+    /// text when it is edited.
+    marks: BTreeMap<char, Location>,
+    /// The closing brackets and quotes which were inserted along with their
+    /// opening ones. They move along with the text like the marks, and are
+    /// forgotten once deleted.
+    auto_closers: Vec<Location>,
+    /// The changes carried out since they were last taken, for the other
+    /// window showing the buffer to follow them.
+    changes: Vec<Change>,
+    /// The file's contents as read from disk, kept for files which look
+    /// binary or aren't valid UTF-8 so they can be shown in the hex view.
+    raw_bytes: Option<Vec<u8>>,
+    /// Show the raw bytes in the hex view instead of the text. Files
+    /// containing NUL bytes start out in the hex view.
+    pub show_hex: bool,
+    /// The line which was last edited within itself, and so keeps its text
+    /// in a gap buffer until editing moves to another line.
+    editing_line: Option<usize>,
+}
+
+impl Buffer {
+    /// Loads the file. Files bigger than the configured threshold are
+    /// opened in large-file mode instead: memory-mapped if the user asked
+    /// for a read-only buffer, or otherwise read lazily in chunks.
+    pub fn load(file_name: &str, read_only: bool, config: &Config) -> Result<Self, Error> {
+        if metadata(file_name)?.len() > config.large_file_threshold {
+            return Self::load_large(file_name, read_only);
+        }
+        let bytes = read(file_name)?;
+        let history = config
+            .persistent_undo
+            .then(|| undofile::load(file_name, &bytes))
+            .flatten();
+        let mut buffer = Self::from_bytes(bytes);
+        buffer.history = history.unwrap_or_default();
+        buffer.last_location = lastlocations::load(file_name).unwrap_or_default();
+        buffer.file_name = Some(file_name.to_string());
+        buffer.read_only = read_only;
+        buffer.disk_mtime = modified_time(file_name);
+        Ok(buffer)
+    }
+
+    /// An unnamed buffer holding `bytes`, decoded the same way as a
+    /// file's contents. Used for text piped in through stdin.
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        let (encoding, contents) = Encoding::decode(&bytes);
+        let line_ending = LineEnding::detect(&contents);
+        let has_nul = !encoding.is_utf16() && bytes.contains(&0);
+        let raw_bytes = (has_nul || encoding == Encoding::Latin1).then_some(bytes);
+        let missing_final_newline = !contents.is_empty() && !contents.ends_with('\n');
+        Self {
+            store: BackingStore::Lines(contents.lines().map(Line::from).collect()),
+            encoding,
+            line_ending,
+            missing_final_newline,
+            raw_bytes,
+            show_hex: has_nul,
+            ..Self::default()
+        }
+    }
+
+    /// Opens the file without reading it; lines are made available by
+    /// `ensure_loaded` as they are needed. Mapped files are only used for
+    /// buffers opened read-only.
+    fn load_large(file_name: &str, map: bool) -> Result<Self, Error> {
+        let (store, line_ending, missing_final_newline) = if map {
+            let mapped = MappedFile::open(file_name)?;
+            let line_ending = mapped.line_ending();
+            let missing_final_newline = mapped.missing_final_newline();
+            (
+                BackingStore::Mapped(mapped),
+                line_ending,
+                missing_final_newline,
+            )
+        } else {
+            let file = LargeFile::open(file_name)?;
+            let line_ending = file.line_ending();
+            let missing_final_newline = file.missing_final_newline();
+            (BackingStore::Lazy(file), line_ending, missing_final_newline)
+        };
+        Ok(Self {
+            store,
+            file_name: Some(file_name.to_string()),
+            line_ending,
+            missing_final_newline,
+            read_only: map,
+            disk_mtime: modified_time(file_name),
+            ..Self::default()
+        })
+    }
+
+    /// For large files, reads or indexes lines until at least `line_count`
+    /// lines are available or the end of the file is reached.
+    pub fn ensure_loaded(&mut self, line_count: usize) {
+        self.store.ensure_loaded(line_count);
+    }
+
+    pub const fn is_large_file(&self) -> bool {
+        self.store.is_large_file()
+    }
+
+    /// Whether the file is mapped into memory rather than read.
+    pub const fn is_mapped(&self) -> bool {
+        matches!(self.store, BackingStore::Mapped(_))
+    }
+
+    /// Reads all of a large file, which is needed before it can be edited.
+    /// A file which isn't valid UTF-8 is made read-only instead.
+    pub fn make_editable(&mut self) -> Result<(), Error> {
+        let result = self.store.make_editable();
+        if result
+            .as_ref()
+            .is_err_and(|err| err.kind() == ErrorKind::InvalidData)
+        {
+            self.read_only = true;
+        }
+        result
+    }
+
+    /// The raw file contents, for buffers which can be shown in the hex view.
+    pub fn raw_bytes(&self) -> Option<&[u8]> {
+        self.raw_bytes.as_deref()
+    }
+
+    /// Re-encodes the lines into the raw bytes after they were edited, so
+    /// the hex view shows what would be saved. Keeps the old bytes if the
+    /// lines can't be encoded.
+    pub fn refresh_raw_bytes(&mut self) {
+        if self.raw_bytes.is_some() && self.dirty {
+            if let Ok(bytes) = self.to_bytes() {
+                self.raw_bytes = Some(bytes);
+            }
+        }
+    }
+
+    pub fn line(&self, index: usize) -> Option<Cow<'_, Line>> {
+        self.store.get(index)
+    }
+
+    /// The kind of text in the buffer, going by its file name.
+    pub fn file_type(&self) -> FileType {
+        self.file_name
+            .as_deref()
+            .map_or_else(FileType::default, FileType::detect)
+    }
+
+    /// The number of lines, which for large files is the number of
+    /// lines read so far.
+    pub fn height(&self) -> usize {
+        self.store.len()
+    }
+
+    /// An empty buffer which will be saved to `file_name`, for files
+    /// which don't exist yet.
+    pub fn new_file(file_name: &str) -> Self {
+        Self {
+            file_name: Some(file_name.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Whether the file exists but can't be written by us, so saving it
+    /// will probably fail.
+    pub fn is_write_protected(&self) -> bool {
+        self.file_name.as_deref().is_some_and(|file_name| {
+            Path::new(file_name).exists() && OpenOptions::new().write(true).open(file_name).is_err()
+        })
+    }
+
+    /// Replaces the lines with the file's current contents on disk.
+    /// Settings such as the read-only flag and the last location are kept.
+    pub fn reload(&mut self, config: &Config) -> Result<(), Error> {
+        let Some(file_name) = self.file_name.as_deref() else {
+            return Err(Error::other("Buffer has no file name"));
+        };
+        let loaded = Self::load(file_name, self.read_only, config)?;
+        self.read_only = loaded.read_only;
+        self.store = loaded.store;
+        self.encoding = loaded.encoding;
+        self.line_ending = loaded.line_ending;
+        self.missing_final_newline = loaded.missing_final_newline;
+        self.history = loaded.history;
+        self.show_hex = (self.show_hex && loaded.raw_bytes.is_some()) || loaded.show_hex;
+        self.raw_bytes = loaded.raw_bytes;
+        self.dirty = false;
+        self.disk_mtime = loaded.disk_mtime;
+        Ok(())
+    }
+
+    /// Returns the file's current modification time if another program
+    /// has changed it since we last loaded or saved it.
+    pub fn changed_on_disk(&self) -> Option<SystemTime> {
+        let file_name = self.file_name.as_deref()?;
+        let current = modified_time(file_name)?;
+        (self.disk_mtime != Some(current)).then_some(current)
+    }
+
+    /// Writes all lines back to the file this buffer was loaded from.
+    /// Fails if the buffer has no file name or the file cannot be written.
+    pub fn save(&mut self, config: &Config) -> Result<SaveReport, Error> {
+        let Some(file_name) = self.file_name.clone() else {
+            return Err(Error::other("Buffer has no file name"));
+        };
+        let bytes = self.bytes_to_save(config)?;
+        Self::write_to(&file_name, &bytes, config)?;
+        Ok(self.finish_save(&file_name, &bytes, config))
+    }
+
+    /// Writes all lines to the given file and, on success, remembers it
+    /// as the buffer's file name.
+    pub fn save_as(&mut self, file_name: &str, config: &Config) -> Result<SaveReport, Error> {
+        let bytes = self.bytes_to_save(config)?;
+        Self::write_to(file_name, &bytes, config)?;
+        self.file_name = Some(file_name.to_string());
+        Ok(self.finish_save(file_name, &bytes, config))
+    }
+
+    /// Makes the configured changes which were made to what was written
+    /// to the lines too, such as stripping trailing whitespace or
+    /// terminating the last line, once the write succeeded.
+    fn apply_save_transforms(&mut self, config: &Config) -> SaveReport {
+        let mut report = SaveReport::default();
+        if config.ensure_final_newline && self.missing_final_newline {
+            self.missing_final_newline = false;
+            report.added_final_newline = true;
+        }
+        if !config.strip_trailing_whitespace {
+            return report;
+        }
+        // Recorded like any edit, as one step, so undo and the marks stay
+        // in line with the text.
+        self.start_undo_group();
+        for y in 0..self.height() {
+            let Some((start, end)) = self
+                .line(y)
+                .map(|line| (line.trailing_whitespace_start(), line.len()))
+            else {
+                break;
+            };
+            if start < end && self.delete_range(Location { x: start, y }, Location { x: end, y }) {
+                report.stripped_lines = report.stripped_lines.saturating_add(1);
+            }
+        }
+        self.end_undo_group();
+        report
+    }
+
+    /// Writes all lines by piping them into the privileged save command, run with
+    /// the file name as its last argument, e.g. `sudo tee <file>`. Used
+    /// when the user lacks the permissions to write the file directly.
+    pub fn save_through_command(
+        &mut self,
+        file_name: &str,
+        config: &Config,
+    ) -> Result<SaveReport, Error> {
+        let mut words = config.privileged_save_command.split_whitespace();
+        let Some(program) = words.next() else {
+            return Err(Error::new(ErrorKind::InvalidInput, "no command configured"));
+        };
+        let bytes = self.bytes_to_save(config)?;
+        let mut child = Command::new(program)
+            .args(words)
+            .arg(file_name)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|err| with_context(&err, &format!("could not run {program}")))?;
+        // Dropping stdin after writing closes it, so the command sees the end of its input.
+        let written = child
+            .stdin
+            .take()
+            .map_or(Ok(()), |mut stdin| stdin.write_all(&bytes));
+        if let Err(err) = written {
+            // Stop the command rather than let it finish a truncated file,
+            // and reap it either way.
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(with_context(&err, &format!("could not write to {program}")));
+        }
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(Error::other(format!("{program} failed ({status})")));
+        }
+        self.file_name = Some(file_name.to_string());
+        Ok(self.finish_save(file_name, &bytes, config))
+    }
+
+    /// Records that `bytes` were written to `file_name`: the lines get the
+    /// save-time changes made to what was written, and the buffer is no
+    /// longer modified.
+    fn finish_save(&mut self, file_name: &str, bytes: &[u8], config: &Config) -> SaveReport {
+        let report = self.apply_save_transforms(config);
+        self.dirty = false;
+        self.disk_mtime = modified_time(file_name);
+        self.store_undo_file(file_name, bytes, config);
+        report
+    }
+
+    /// Keeps the undo history for the next time the file is opened. Not
+    /// being able to is no reason to fail the save. The history is only
+    /// kept if it leads to the lines as they were written, `contents`, as
+    /// it would replay changes at the wrong places otherwise.
+    fn store_undo_file(&self, file_name: &str, contents: &[u8], config: &Config) {
+        if config.persistent_undo && self.to_bytes().is_ok_and(|bytes| bytes == contents) {
+            let _ = undofile::store(file_name, contents, &self.history);
+        }
+    }
+
+    /// Saves atomically: the lines go to a temporary file next to the target,
+    /// which is then renamed over it. A crash or a full disk mid-write thus
+    /// leaves the previous contents intact instead of a truncated file.
+    fn write_to(file_name: &str, bytes: &[u8], config: &Config) -> Result<(), Error> {
+        let path = Path::new(file_name);
+        if path.exists() {
+            // Renaming over the file only needs the directory to be writable,
+            // so check the file's own permissions first instead of bypassing
+            // them. Being denied leads to the privileged save.
+            OpenOptions::new()
+                .write(true)
+                .open(path)
+                .map_err(|err| with_context(&err, "could not write file"))?;
+        }
+        if config.backup && path.exists() {
+            Self::back_up(file_name, config.backup_dir.as_deref())
+                .map_err(|err| with_context(&err, "could not create backup"))?;
+        }
+        let temp_path = Self::temp_path_for(path)?;
+        if let Err(err) = write_synced(&temp_path, bytes) {
+            let _ = remove_file(&temp_path);
+            return Err(with_context(&err, "could not write temporary file"));
+        }
+        if let Ok(meta) = metadata(path) {
+            // A fresh file gets default permissions; keep the original's instead.
+            if let Err(err) = set_permissions(&temp_path, meta.permissions()) {
+                let _ = remove_file(&temp_path);
+                return Err(with_context(&err, "could not keep file permissions"));
+            }
+        }
+        if let Err(err) = rename(&temp_path, path) {
+            let _ = remove_file(&temp_path);
+            return Err(with_context(&err, "could not replace file"));
+        }
+        Ok(())
+    }
+
+    /// All lines as they would be written to disk, with the buffer's
+    /// line ending and encoding applied.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        self.encode_lines(false, !self.missing_final_newline)
+    }
+
+    /// What a save writes: `to_bytes` with the configured save-time
+    /// changes made, which the lines themselves only get once it's written.
+    /// A large file is indexed completely first, so all of it is written.
+    fn bytes_to_save(&mut self, config: &Config) -> Result<Vec<u8>, Error> {
+        self.ensure_loaded(usize::MAX);
+        self.encode_lines(
+            config.strip_trailing_whitespace,
+            !self.missing_final_newline || config.ensure_final_newline,
+        )
+    }
+
+    /// All lines, joined by the buffer's line ending and encoded, without
+    /// trailing whitespace if `strip_trailing_whitespace` is set and
+    /// followed by a line ending if `final_newline` is.
+    fn encode_lines(
+        &self,
+        strip_trailing_whitespace: bool,
+        final_newline: bool,
+    ) -> Result<Vec<u8>, Error> {
+        let mut contents = String::new();
+        for index in 0..self.height() {
+            let Some(line) = self.line(index) else {
+                break;
+            };
+            if index > 0 {
+                contents.push_str(self.line_ending.as_str());
+            }
+            let text = line.to_string();
+            if strip_trailing_whitespace {
+                contents.push_str(text.trim_end_matches([' ', '\t']));
+            } else {
+                contents.push_str(&text);
+            }
+        }
+        if !self.is_empty() && final_newline {
+            contents.push_str(self.line_ending.as_str());
+        }
+        self.encoding.encode(&contents)
+    }
+
+    /// A hidden file in the target's directory, so the final rename never
+    /// crosses file systems.
+    fn temp_path_for(path: &Path) -> Result<PathBuf, Error> {
+        let Some(name) = path.file_name() else {
+            return Err(Error::new(ErrorKind::InvalidInput, "not a file name"));
+        };
+        let mut temp_name = OsString::from(".");
+        temp_name.push(name);
+        temp_name.push(format!(".{}.tmp", process::id()));
+        Ok(path.with_file_name(temp_name))
+    }
+
+    /// Copies the current file contents to `<name>~`, either next to the
+    /// file or inside `backup_dir`. Any error aborts the save, so the
+    /// original is never overwritten without a backup.
+    fn back_up(file_name: &str, backup_dir: Option<&Path>) -> Result<(), Error> {
+        let path = Path::new(file_name);
+        let mut backup_name = path
+            .file_name()
+            .map_or_else(OsString::new, ToOwned::to_owned);
+        backup_name.push("~");
+        let backup_path = if let Some(dir) = backup_dir {
+            create_dir_all(dir)?;
+            dir.join(backup_name)
+        } else {
+            path.with_file_name(backup_name)
+        };
+        copy(path, backup_path)?;
+        Ok(())
+    }
+
+    /// Inserts `text`, which may span several lines, at `at` and records
+    /// the change for undo. Inserting just below the last line appends a
+    /// new line. Returns where the caret belongs after the inserted text.
+    pub fn insert(&mut self, at: Location, text: &str) -> Option<Location> {
+        let lines = self.store.lines_mut()?;
+        let change = match lines.get(at.y) {
+            Some(line) => Change::Insert {
+                at: Location {
+                    x: min(at.x, line.len()),
+                    y: at.y,
+                },
+                text: text.to_string(),
+            },
+            None if at.y == lines.len() => match lines.last() {
+                // Continuing after the last line means breaking it first.
+                Some(last) => Change::Insert {
+                    at: Location {
+                        x: last.len(),
+                        y: lines.len().saturating_sub(1),
+                    },
+                    text: format!("\n{text}"),
+                },
+                None => Change::Insert {
+                    at,
+                    text: text.to_string(),
+                },
+            },
+            None => return None,
+        };
+        self.apply(&change);
+        let caret = change.caret_after();
+        self.history.record(change);
+        self.dirty = true;
+        Some(caret)
+    }
+
+    /// Removes the text from `start` up to `end`. Returns whether there was any.
+    pub fn delete_range(&mut self, start: Location, end: Location) -> bool {
+        let text = self.text_between(start, end);
+        if text.is_empty() || self.store.lines_mut().is_none() {
+            return false;
+        }
+        let change = Change::Delete { at: start, text };
+        self.apply(&change);
+        self.history.record(change);
+        self.dirty = true;
+        true
+    }
+
+    /// The text from `start` up to `end`, with lines separated by `\n`.
+    pub fn text_between(&self, start: Location, end: Location) -> String {
+        let mut parts = Vec::new();
+        for y in start.y..=end.y {
+            let Some(line) = self.line(y) else {
+                break;
+            };
+            let from = if y == start.y { start.x } else { 0 };
+            let to = if y == end.y { end.x } else { line.len() };
+            parts.push(line.chars_between(from, to));
+        }
+        parts.join("\n")
+    }
+
+    /// Removes the character at `at`. At the end of a line, joins the next
+    /// line onto it instead. Returns whether the buffer was changed.
+    pub fn delete(&mut self, at: Location) -> bool {
+        let Some(lines) = self.store.lines_mut() else {
+            return false;
+        };
+        let Some(line) = lines.get(at.y) else {
+            return false;
+        };
+        let text = if at.x < line.len() {
+            line.chars_between(at.x, line.next_grapheme_boundary(at.x))
+        } else if at.y.saturating_add(1) < lines.len() {
+            "\n".to_string()
+        } else {
+            return false;
+        };
+        let change = Change::Delete { at, text };
+        self.apply(&change);
+        self.history.record(change);
+        self.dirty = true;
+        true
+    }
+
+    /// Reverts the last undo step. Returns where the caret belongs then.
+    pub fn undo(&mut self) -> Option<Location> {
+        let changes = self.history.undo()?;
+        self.apply_all(&changes)
+    }
+
+    /// Reapplies the last undone step. Returns where the caret belongs then.
+    pub fn redo(&mut self) -> Option<Location> {
+        let changes = self.history.redo()?;
+        self.apply_all(&changes)
+    }
+
+    /// Goes back to the state before the current one in the order the
+    /// states were created, across branches of the undo tree.
+    pub fn undo_earlier(&mut self) -> Option<Location> {
+        let changes = self.history.earlier()?;
+        self.apply_all(&changes)
+    }
+
+    /// Goes forward to the state created after the current one.
+    pub fn undo_later(&mut self) -> Option<Location> {
+        let changes = self.history.later()?;
+        self.apply_all(&changes)
+    }
+
+    /// The number of the current undo state and how many states there are.
+    pub const fn undo_position(&self) -> (usize, usize) {
+        self.history.position()
+    }
+
+    /// Makes the next edit start a new undo step, e.g. after the caret moved.
+    pub fn end_undo_step(&mut self) {
+        self.history.end_step();
+    }
+
+    /// Makes all changes up to `end_undo_group` a single undo step.
+    pub fn start_undo_group(&mut self) {
+        self.history.start_group();
+    }
+
+    /// Starts an undo group which joins the current undo step if that ends
+    /// with text typed right up to `at`.
+    pub fn start_continuing_undo_group(&mut self, at: Location) {
+        self.history.start_continuing_group(at);
+    }
+
+    pub fn end_undo_group(&mut self) {
+        self.history.end_group();
+    }
+
+    fn apply_all(&mut self, changes: &[Change]) -> Option<Location> {
+        for change in changes {
+            self.apply(change);
+        }
+        self.dirty = true;
+        changes.last().map(Change::caret_after)
+    }
+
+    pub fn set_mark(&mut self, name: char, location: Location) {
+        self.marks.insert(name, location);
+    }
+
+    pub fn mark(&self, name: char) -> Option<Location> {
+        self.marks.get(&name).copied()
+    }
+
+    /// The marks' names and locations, ordered by name.
+    pub fn marks(&self) -> impl Iterator<Item = (char, Location)> + '_ {
+        self.marks.iter().map(|(&name, &location)| (name, location))
+    }
+
+    /// Remembers that the closer at `location` was inserted automatically.
+    pub fn add_auto_closer(&mut self, location: Location) {
+        self.auto_closers.push(location);
+    }
+
+    /// Whether the closer at `location` was inserted automatically.
+    pub fn is_auto_closer(&self, location: Location) -> bool {
+        self.auto_closers.contains(&location)
+    }
+
+    /// Forgets that the closer at `location` was inserted automatically.
+    pub fn remove_auto_closer(&mut self, location: Location) {
+        self.auto_closers.retain(|&closer| closer != location);
+    }
+
+    /// The changes carried out since this was last called.
+    pub fn take_changes(&mut self) -> Vec<Change> {
+        std::mem::take(&mut self.changes)
+    }
+
+    /// Carries out `change` on the lines without recording it.
+    fn apply(&mut self, change: &Change) {
+        let Some(lines) = self.store.lines_mut() else {
+            return;
+        };
+        self.changes.push(change.clone());
+        for mark in self.marks.values_mut() {
+            *mark = mark.adjusted_to(change);
+        }
+        if let Change::Delete { at, .. } = change {
+            let key = |location: Location| (location.y, location.x);
+            let end = change.text_end();
+            self.auto_closers
+                .retain(|&closer| key(closer) < key(*at) || key(closer) >= key(end));
+        }
+        for closer in &mut self.auto_closers {
+            *closer = closer.adjusted_to(change);
+        }
+        let (at, text) = match change {
+            Change::Insert { at, text } | Change::Delete { at, text } => (at, text),
+        };
+        // Changes which split or join lines shift the indices after them,
+        // so the edited line is compacted before, while its index is valid.
+        let within_line = !text.contains('\n')
+            && (matches!(change, Change::Insert { .. })
+                || at.x.saturating_add(text.chars().count())
+                    <= lines.get(at.y).map_or(0, Line::len));
+        if let Some(previous) = self.editing_line.take() {
+            if !within_line || previous != at.y {
+                if let Some(line) = lines.get_mut(previous) {
+                    line.compact();
+                }
+            }
+        }
+        if within_line {
+            self.editing_line = Some(at.y);
+        }
+        match change {
+            Change::Insert { at, text } => {
+                if at.y == lines.len() {
+                    lines.push(Line::from(""));
+                }
+                let Location { mut x, mut y } = *at;
+                for (index, part) in text.split('\n').enumerate() {
+                    if index > 0 {
+                        let rest = lines[y].split_off(x);
+                        y = y.saturating_add(1);
+                        lines.insert(y, rest);
+                        x = 0;
+                    }
+                    lines[y].insert_str(part, x);
+                    x = x.saturating_add(part.chars().count());
+                }
+            }
+            Change::Delete { at, text } => {
+                for _ in text.chars() {
+                    let Some(line) = lines.get_mut(at.y) else {
+                        break;
+                    };
+                    if at.x < line.len() {
+                        line.remove(at.x);
+                    } else if at.y.saturating_add(1) < lines.len() {
+                        if let Some(next) = lines.remove(at.y.saturating_add(1)) {
+                            lines[at.y].append(&next);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Changes how lines will be terminated when the buffer is next saved.
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        if line_ending != self.line_ending {
+            self.line_ending = line_ending;
+            self.dirty = true;
+        }
+    }
+
+    pub fn get_status(&self) -> DocumentStatus {
+        DocumentStatus {
+            file_name: self.file_name.clone(),
+            read_only: self.read_only,
+            modified: self.dirty,
+            line_count: self.height(),
+            encoding: self.encoding,
+            line_ending: self.line_ending,
+            large_file_percent: self.store.percent_read(),
+            hex_offset: None,
+            overwrite: false,
+            percent: None,
+            caret: None,
+        }
+    }
+
+    pub const fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.height() == 0
+    }
+}
+
+fn write_synced(path: &Path, bytes: &[u8]) -> Result<(), Error> {
+    let mut file = File::create(path)?;
+    file.write_all(bytes)?;
+    file.sync_all()
+}
+
+/// Prefixes an error's message while keeping its kind.
+fn with_context(err: &Error, context: &str) -> Error {
+    Error::new(err.kind(), format!("{context}: {err}"))
+}
+
+fn modified_time(file_name: &str) -> Option<SystemTime> {
+    metadata(file_name).and_then(|meta| meta.modified()).ok()
+}
@@ -0,0 +1,126 @@
+use std::cmp::min;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use super::terminal::{Size, Terminal};
+
+/// An input line in the bottom row which asks the user for a value,
+/// e.g. the file name to save to.
+#[derive(Default)]
+pub struct CommandBar {
+    prompt: String,
+    value: String,
+    /// Where in `value` typed characters go, as a character index.
+    caret: usize,
+    needs_redraw: bool,
+    width: usize,
+    position_y: usize,
+}
+
+impl CommandBar {
+    pub fn new(prompt: &str, size: Size) -> Self {
+        let mut command_bar = Self {
+            prompt: prompt.to_string(),
+            ..Self::default()
+        };
+        command_bar.resize(size);
+        command_bar
+    }
+
+    /// Edits the input or moves the caret within it if `key_event` is one
+    /// of the keys for that: characters, Backspace, Delete, Left, Right,
+    /// Home and End. Returns whether the input changed, e.g. to act on it
+    /// while the user types.
+    pub fn handle_key(&mut self, key_event: KeyEvent) -> bool {
+        let len = self.value.chars().count();
+        let caret = match (key_event.code, key_event.modifiers) {
+            (KeyCode::Char(character), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                let at = self.byte_index(self.caret);
+                self.value.insert(at, character);
+                self.caret.saturating_add(1)
+            }
+            (KeyCode::Backspace, _) if self.caret > 0 => {
+                let at = self.byte_index(self.caret.saturating_sub(1));
+                self.value.remove(at);
+                self.caret.saturating_sub(1)
+            }
+            (KeyCode::Delete, _) if self.caret < len => {
+                let at = self.byte_index(self.caret);
+                self.value.remove(at);
+                self.caret
+            }
+            (KeyCode::Left, _) => self.caret.saturating_sub(1),
+            (KeyCode::Right, _) => min(self.caret.saturating_add(1), len),
+            (KeyCode::Home, _) => 0,
+            (KeyCode::End, _) => len,
+            _ => return false,
+        };
+        self.caret = caret;
+        self.needs_redraw = true;
+        self.value.chars().count() != len
+    }
+
+    /// The byte offset in `value` of the character at `index`.
+    fn byte_index(&self, index: usize) -> usize {
+        self.value
+            .char_indices()
+            .nth(index)
+            .map_or(self.value.len(), |(at, _)| at)
+    }
+
+    /// Changes what the bar asks for, e.g. to tell the user that the
+    /// input so far doesn't lead anywhere.
+    pub fn set_prompt(&mut self, prompt: &str) {
+        if self.prompt != prompt {
+            prompt.clone_into(&mut self.prompt);
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Replaces the input, e.g. with an entry the user picked from a list.
+    pub fn set_value(&mut self, value: &str) {
+        value.clone_into(&mut self.value);
+        self.caret = self.value.chars().count();
+        self.needs_redraw = true;
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// The column the caret should be placed at while the user types.
+    pub fn caret_col(&self) -> usize {
+        min(self.caret_offset(), self.width.saturating_sub(1))
+    }
+
+    /// How many characters of the prompt and the input come before the caret.
+    fn caret_offset(&self) -> usize {
+        self.prompt.chars().count().saturating_add(self.caret)
+    }
+
+    pub fn position_y(&self) -> usize {
+        self.position_y
+    }
+
+    /// Places the bar on the last row of a terminal of the given size.
+    pub fn resize(&mut self, to: Size) {
+        self.width = to.width;
+        self.position_y = to.height.saturating_sub(1);
+        self.needs_redraw = true;
+    }
+
+    pub fn render(&mut self) {
+        if !self.needs_redraw || self.width == 0 {
+            return;
+        }
+        let text = format!("{}{}", self.prompt, self.value);
+        // Keep the caret visible when the input is wider than the terminal.
+        let skip = self
+            .caret_offset()
+            .saturating_sub(self.width.saturating_sub(1));
+        let visible: String = text.chars().skip(skip).collect();
+        let result = Terminal::print_row(self.position_y, &visible);
+        debug_assert!(result.is_ok(), "Failed to render command bar");
+        self.needs_redraw = false;
+    }
+}
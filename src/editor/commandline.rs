@@ -0,0 +1,274 @@
+use super::editorcommand::{Edit, EditorCommand, SearchDirection, SplitLayout};
+
+/// What a line typed on the command line asks for.
+pub enum LineCommand {
+    /// Runs an editor command as if its key was pressed.
+    Run(EditorCommand),
+    /// Saves the buffer, to the given file if there is one.
+    Write(Option<String>),
+    /// Saves the buffer, then quits if that worked.
+    WriteQuit,
+    /// Changes a setting as if it was in the config file, e.g. `wrap`.
+    Set {
+        key: String,
+        value: String,
+    },
+    Open(String),
+    /// Moves the caret to a line, counted from 1.
+    GoToLine(usize),
+}
+
+/// A command the command line knows by name.
+struct NamedCommand {
+    name: &'static str,
+    /// Whether the command needs an argument after its name.
+    takes_argument: bool,
+    /// Turns the text after the name, trimmed, into what to do.
+    build: fn(&str) -> Result<LineCommand, String>,
+}
+
+/// Every command the command line knows, in the order completion lists them.
+const COMMANDS: &[NamedCommand] = &[
+    NamedCommand {
+        name: "w",
+        takes_argument: false,
+        build: |argument| Ok(write(argument)),
+    },
+    NamedCommand {
+        name: "write",
+        takes_argument: false,
+        build: |argument| Ok(write(argument)),
+    },
+    NamedCommand {
+        name: "wq",
+        takes_argument: false,
+        build: |_| Ok(LineCommand::WriteQuit),
+    },
+    NamedCommand {
+        name: "q",
+        takes_argument: false,
+        build: |_| Ok(LineCommand::Run(EditorCommand::Quit)),
+    },
+    NamedCommand {
+        name: "quit",
+        takes_argument: false,
+        build: |_| Ok(LineCommand::Run(EditorCommand::Quit)),
+    },
+    NamedCommand {
+        name: "set",
+        takes_argument: true,
+        build: set,
+    },
+    NamedCommand {
+        name: "e",
+        takes_argument: true,
+        build: |argument| Ok(LineCommand::Open(argument.to_string())),
+    },
+    NamedCommand {
+        name: "open",
+        takes_argument: true,
+        build: |argument| Ok(LineCommand::Open(argument.to_string())),
+    },
+    NamedCommand {
+        name: "new",
+        takes_argument: false,
+        build: |_| Ok(LineCommand::Run(EditorCommand::NewBuffer)),
+    },
+    NamedCommand {
+        name: "next",
+        takes_argument: false,
+        build: |_| Ok(LineCommand::Run(EditorCommand::NextBuffer)),
+    },
+    NamedCommand {
+        name: "previous",
+        takes_argument: false,
+        build: |_| Ok(LineCommand::Run(EditorCommand::PreviousBuffer)),
+    },
+    NamedCommand {
+        name: "buffers",
+        takes_argument: false,
+        build: |_| Ok(LineCommand::Run(EditorCommand::SwitchBuffer)),
+    },
+    NamedCommand {
+        name: "files",
+        takes_argument: false,
+        build: |_| Ok(LineCommand::Run(EditorCommand::FindFile)),
+    },
+    NamedCommand {
+        name: "recent",
+        takes_argument: false,
+        build: |_| Ok(LineCommand::Run(EditorCommand::OpenRecentFile)),
+    },
+    NamedCommand {
+        name: "find",
+        takes_argument: false,
+        build: |_| {
+            Ok(LineCommand::Run(EditorCommand::Search(
+                SearchDirection::Forward,
+            )))
+        },
+    },
+    NamedCommand {
+        name: "replace",
+        takes_argument: false,
+        build: |_| Ok(LineCommand::Run(EditorCommand::Replace)),
+    },
+    NamedCommand {
+        name: "grep",
+        takes_argument: false,
+        build: |_| Ok(LineCommand::Run(EditorCommand::Grep)),
+    },
+    NamedCommand {
+        name: "sort",
+        takes_argument: false,
+        build: |_| Ok(LineCommand::Run(EditorCommand::SortLines)),
+    },
+    NamedCommand {
+        name: "undo",
+        takes_argument: false,
+        build: |_| Ok(LineCommand::Run(EditorCommand::Edit(Edit::Undo))),
+    },
+    NamedCommand {
+        name: "redo",
+        takes_argument: false,
+        build: |_| Ok(LineCommand::Run(EditorCommand::Edit(Edit::Redo))),
+    },
+    NamedCommand {
+        name: "split",
+        takes_argument: false,
+        build: |_| {
+            Ok(LineCommand::Run(EditorCommand::SplitWindow(
+                SplitLayout::Stacked,
+            )))
+        },
+    },
+    NamedCommand {
+        name: "vsplit",
+        takes_argument: false,
+        build: |_| {
+            Ok(LineCommand::Run(EditorCommand::SplitWindow(
+                SplitLayout::SideBySide,
+            )))
+        },
+    },
+    NamedCommand {
+        name: "close",
+        takes_argument: false,
+        build: |_| Ok(LineCommand::Run(EditorCommand::CloseWindow)),
+    },
+    NamedCommand {
+        name: "hex",
+        takes_argument: false,
+        build: |_| Ok(LineCommand::Run(EditorCommand::ToggleHexView)),
+    },
+];
+
+/// Parses a line typed on the command line: a command's name, followed by
+/// its argument if it takes one, or a line number to go to. Returns what
+/// to tell the user if the line doesn't make sense.
+pub fn parse(input: &str) -> Result<LineCommand, String> {
+    let input = input.trim();
+    if let Ok(line) = input.parse() {
+        return Ok(LineCommand::GoToLine(line));
+    }
+    let (name, argument) = input
+        .split_once(char::is_whitespace)
+        .map_or((input, ""), |(name, argument)| (name, argument.trim()));
+    let Some(command) = COMMANDS.iter().find(|command| command.name == name) else {
+        return Err(format!("Not a command: {name}"));
+    };
+    if command.takes_argument && argument.is_empty() {
+        return Err(format!("{name} needs an argument."));
+    }
+    (command.build)(argument)
+}
+
+/// The names of the commands starting with `input`, which the user is
+/// typing the name of, along with whether each takes an argument. Empty
+/// once the input goes past the name.
+pub fn complete(input: &str) -> Vec<(&'static str, bool)> {
+    if input.contains(char::is_whitespace) {
+        return Vec::new();
+    }
+    COMMANDS
+        .iter()
+        .filter(|command| command.name.starts_with(input))
+        .map(|command| (command.name, command.takes_argument))
+        .collect()
+}
+
+/// `w`, or `w <path>` to save to another file.
+fn write(argument: &str) -> LineCommand {
+    LineCommand::Write((!argument.is_empty()).then(|| argument.to_string()))
+}
+
+/// `set key = value`, or `set key value`.
+fn set(argument: &str) -> Result<LineCommand, String> {
+    let (key, value) = argument
+        .split_once('=')
+        .or_else(|| argument.split_once(char::is_whitespace))
+        .ok_or_else(|| format!("set needs a value for {argument}."))?;
+    Ok(LineCommand::Set {
+        key: key.trim().to_string(),
+        value: value.trim().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{complete, parse, LineCommand};
+    use crate::editor::editorcommand::EditorCommand;
+
+    #[test]
+    fn numbers_go_to_lines() {
+        assert!(matches!(parse(" 42 "), Ok(LineCommand::GoToLine(42))));
+    }
+
+    #[test]
+    fn commands_are_found_by_name() {
+        assert!(matches!(parse("w"), Ok(LineCommand::Write(None))));
+        assert!(matches!(
+            parse("write  out.txt "),
+            Ok(LineCommand::Write(Some(name))) if name == "out.txt"
+        ));
+        assert!(matches!(parse("wq"), Ok(LineCommand::WriteQuit)));
+        assert!(matches!(
+            parse("q"),
+            Ok(LineCommand::Run(EditorCommand::Quit))
+        ));
+        assert!(matches!(parse("e a b.txt"), Ok(LineCommand::Open(name)) if name == "a b.txt"));
+    }
+
+    #[test]
+    fn set_takes_a_key_and_a_value() {
+        for input in ["set wrap = on", "set wrap=on", "set wrap on"] {
+            assert!(
+                matches!(parse(input), Ok(LineCommand::Set { key, value }) if key == "wrap" && value == "on"),
+                "{input}"
+            );
+        }
+        assert_eq!(
+            parse("set wrap").err().unwrap(),
+            "set needs a value for wrap."
+        );
+    }
+
+    #[test]
+    fn reports_what_is_wrong() {
+        assert_eq!(
+            parse("frobnicate").err().unwrap(),
+            "Not a command: frobnicate"
+        );
+        assert_eq!(parse("open").err().unwrap(), "open needs an argument.");
+    }
+
+    #[test]
+    fn completes_command_names() {
+        assert_eq!(complete("wr"), [("write", false)]);
+        assert_eq!(
+            complete("s"),
+            [("set", true), ("sort", false), ("split", false)]
+        );
+        assert!(complete("set w").is_empty());
+    }
+}
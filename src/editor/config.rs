@@ -0,0 +1,223 @@
+use std::{collections::HashMap, env, fs::read_to_string, path::PathBuf, time::Duration};
+
+use super::snippet::Snippet;
+
+/// User settings, read from `$XDG_CONFIG_HOME/hecto/config`
+/// (or `~/.config/hecto/config`).
+///
+/// The file consists of `key = value` lines. Empty lines, lines starting
+/// with `#` and unknown keys are ignored, as are values which can't be parsed.
+// clippy::struct_excessive_bools: every flag is a separate user setting.
+#[allow(clippy::struct_excessive_bools)]
+pub struct Config {
+    /// Write modified buffers to disk after this long without input.
+    /// `None` (the default, or `autosave_interval = 0`) disables autosave.
+    pub autosave_interval: Option<Duration>,
+    /// Copy a file's previous contents to a backup before overwriting it.
+    pub backup: bool,
+    /// Where backups go. `None` puts `<name>~` next to the file itself.
+    pub backup_dir: Option<PathBuf>,
+    /// Files bigger than this many bytes are opened in large-file mode:
+    /// read-only, and loaded lazily while scrolling through them.
+    pub large_file_threshold: u64,
+    /// The command a buffer is piped through when saving fails for lack
+    /// of permissions, with the file name appended as its last argument.
+    pub privileged_save_command: String,
+    /// Remove trailing spaces and tabs from every line when saving.
+    pub strip_trailing_whitespace: bool,
+    /// Terminate the last line with a line ending when saving if it isn't
+    /// already, as POSIX tools expect. Otherwise a missing one stays missing.
+    pub ensure_final_newline: bool,
+    /// How many columns apart tab stops are.
+    pub tab_width: usize,
+    /// Insert spaces up to the next tab stop instead of a tab character
+    /// when Tab is pressed.
+    pub expand_tab: bool,
+    /// Keep each file's undo history in a cache file when saving, so it
+    /// can still be undone after reopening the file.
+    pub persistent_undo: bool,
+    /// Start a new line with the indentation of the line it was split from.
+    pub auto_indent: bool,
+    /// Indent a new line one level further when the text before the caret
+    /// ends with one of these characters, e.g. `{:`. Empty by default.
+    pub indent_after: String,
+    /// Insert the closing bracket or quote when typing an opening one, type
+    /// over closing ones, and delete both of an empty pair with Backspace.
+    pub auto_close: bool,
+    /// The snippets from `snippet.<file type>.<trigger>` keys, see `Snippet`.
+    pub snippets: Vec<Snippet>,
+    /// Replacements from `abbreviation.<word> = <replacement>` keys, applied
+    /// when the word is followed by a space, punctuation or a line break.
+    /// `\n` in a replacement stands for a line break.
+    pub abbreviations: HashMap<String, String>,
+    /// How many lines above and below the caret are kept on screen when
+    /// scrolling, as far as the text and the screen's height allow.
+    pub scrolloff: usize,
+    /// Show long lines across several rows instead of scrolling sideways.
+    pub wrap: bool,
+    /// Show each line's number left of the text.
+    pub line_numbers: bool,
+    /// Number the other lines by how far they are from the caret's line,
+    /// e.g. to see how many lines a move will cross.
+    pub relative_line_numbers: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            autosave_interval: None,
+            backup: true,
+            backup_dir: None,
+            large_file_threshold: 64 * 1024 * 1024,
+            privileged_save_command: "sudo tee".to_string(),
+            strip_trailing_whitespace: false,
+            ensure_final_newline: false,
+            tab_width: 4,
+            expand_tab: false,
+            persistent_undo: false,
+            auto_indent: true,
+            indent_after: String::new(),
+            auto_close: false,
+            snippets: Vec::new(),
+            abbreviations: HashMap::new(),
+            scrolloff: 0,
+            wrap: false,
+            line_numbers: true,
+            relative_line_numbers: false,
+        }
+    }
+}
+
+impl Config {
+    /// Loads the user's config file, falling back to defaults if it is
+    /// missing or unreadable.
+    pub fn load() -> Self {
+        config_file_path()
+            .and_then(|path| read_to_string(path).ok())
+            .map_or_else(Self::default, |contents| Self::parse(&contents))
+    }
+
+    fn parse(contents: &str) -> Self {
+        let mut config = Self::default();
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                config.set(key.trim(), value.trim());
+            }
+        }
+        config
+    }
+
+    /// Applies the line `key = value` of the config file. Returns whether
+    /// it did, i.e. `key` is a setting and `value` a valid value for it.
+    pub fn set(&mut self, key: &str, value: &str) -> bool {
+        if let Some(flag) = self.flag(key) {
+            let Some(on) = parse_bool(value) else {
+                return false;
+            };
+            *flag = on;
+            return true;
+        }
+        match key {
+            "autosave_interval" => {
+                let Ok(seconds) = value.parse::<u64>() else {
+                    return false;
+                };
+                self.autosave_interval = (seconds > 0).then(|| Duration::from_secs(seconds));
+            }
+            "tab_width" => {
+                let Some(width) = value.parse().ok().filter(|&width| width > 0) else {
+                    return false;
+                };
+                self.tab_width = width;
+            }
+            "indent_after" => self.indent_after = value.to_string(),
+            "scrolloff" => {
+                let Ok(lines) = value.parse() else {
+                    return false;
+                };
+                self.scrolloff = lines;
+            }
+            "large_file_threshold" => {
+                let Ok(bytes) = value.parse() else {
+                    return false;
+                };
+                self.large_file_threshold = bytes;
+            }
+            "privileged_save_command" if !value.is_empty() => {
+                self.privileged_save_command = value.to_string();
+            }
+            "backup_dir" => {
+                self.backup_dir = (!value.is_empty()).then(|| expand_home(value));
+            }
+            _ if key.starts_with("abbreviation.") => {
+                let Some(word) = key
+                    .strip_prefix("abbreviation.")
+                    .filter(|word| !word.is_empty())
+                else {
+                    return false;
+                };
+                self.abbreviations
+                    .insert(word.to_string(), value.replace("\\n", "\n"));
+            }
+            _ if key.starts_with("snippet.") => {
+                let Some(snippet) = key
+                    .strip_prefix("snippet.")
+                    .and_then(|key| Snippet::parse(key, value))
+                else {
+                    return false;
+                };
+                self.snippets.retain(|other| {
+                    other.trigger != snippet.trigger || other.file_type != snippet.file_type
+                });
+                self.snippets.push(snippet);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// The setting which `key` switches on or off, if it is one of those.
+    fn flag(&mut self, key: &str) -> Option<&mut bool> {
+        match key {
+            "backup" => Some(&mut self.backup),
+            "strip_trailing_whitespace" => Some(&mut self.strip_trailing_whitespace),
+            "ensure_final_newline" => Some(&mut self.ensure_final_newline),
+            "expand_tab" => Some(&mut self.expand_tab),
+            "persistent_undo" => Some(&mut self.persistent_undo),
+            "auto_indent" => Some(&mut self.auto_indent),
+            "auto_close" => Some(&mut self.auto_close),
+            "wrap" => Some(&mut self.wrap),
+            "line_numbers" => Some(&mut self.line_numbers),
+            "relative_line_numbers" => Some(&mut self.relative_line_numbers),
+            _ => None,
+        }
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" | "on" | "yes" => Some(true),
+        "false" | "off" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// Resolves a leading `~/` against the user's home directory.
+fn expand_home(value: &str) -> PathBuf {
+    match (value.strip_prefix("~/"), env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(value),
+    }
+}
+
+fn config_file_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("hecto").join("config"))
+}
@@ -0,0 +1,13 @@
+use std::{env, path::PathBuf};
+
+/// The directory hecto keeps its state in across sessions, like the recent
+/// files: `$XDG_DATA_HOME/hecto`, or `~/.local/share/hecto`.
+pub fn data_dir() -> Option<PathBuf> {
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| {
+            env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share"))
+        })?;
+    Some(data_home.join("hecto"))
+}
@@ -0,0 +1,26 @@
+use super::{encoding::Encoding, lineending::LineEnding};
+
+/// A snapshot of the facts about the current buffer which the status bar displays.
+#[derive(Default, PartialEq, Eq, Clone)]
+pub struct DocumentStatus {
+    pub file_name: Option<String>,
+    pub read_only: bool,
+    /// Whether the buffer was changed since it was loaded or saved.
+    pub modified: bool,
+    /// How many lines the buffer has, or has read so far in large-file mode.
+    pub line_count: usize,
+    pub encoding: Encoding,
+    pub line_ending: LineEnding,
+    /// For files in large-file mode, how much of the file has been read so far.
+    pub large_file_percent: Option<u64>,
+    /// The caret's byte offset while the hex view is shown.
+    pub hex_offset: Option<usize>,
+    /// Whether typed characters replace the character under the caret.
+    pub overwrite: bool,
+    /// How far through the buffer the caret's line is, in percent. Not
+    /// known in large-file mode or shown in the hex view.
+    pub percent: Option<usize>,
+    /// The caret's line and column, counted from 1. Not shown in the hex
+    /// view.
+    pub caret: Option<(usize, usize)>,
+}
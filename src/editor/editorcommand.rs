@@ -6,17 +6,239 @@ use super::terminal::Size;
 pub enum Direction {
     PageUp,
     PageDown,
+    /// To the first non-blank character of the line, or to its start if
+    /// the caret is already there.
     Home,
     End,
     Up,
     Left,
     Right,
     Down,
+    /// To the start of the previous word, possibly on a line above (Ctrl+Left).
+    WordLeft,
+    /// To the end of the next word, possibly on a line below (Ctrl+Right).
+    WordRight,
+    /// Half a screen up, scrolling the text along (Ctrl+Alt+U).
+    HalfPageUp,
+    /// Half a screen down, scrolling the text along (Ctrl+Alt+D). Ctrl+U
+    /// and Ctrl+D, where some editors have these, paste and add carets.
+    HalfPageDown,
+    /// To the blank line before the paragraph, or the one above (Ctrl+Up).
+    ParagraphUp,
+    /// To the blank line after the paragraph, or the one below (Ctrl+Down).
+    ParagraphDown,
+}
+
+#[derive(Copy, Clone)]
+pub enum Case {
+    Upper,
+    Lower,
+    /// Upper-cases the first letter of each word and lower-cases the rest.
+    Title,
+}
+
+#[derive(Copy, Clone)]
+pub enum SortOrder {
+    Lexicographic,
+    /// By the number each line starts with; lines without one come first.
+    Numeric,
+    /// Lexicographic, keeping only the first of several equal lines.
+    Unique,
+}
+
+/// How the screen is split between two windows.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum SplitLayout {
+    /// One above the other.
+    Stacked,
+    SideBySide,
+}
+
+/// Which way to search from the caret.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum SearchDirection {
+    Forward,
+    Backward,
+}
+
+impl SearchDirection {
+    pub const fn reversed(self) -> Self {
+        match self {
+            Self::Forward => Self::Backward,
+            Self::Backward => Self::Forward,
+        }
+    }
+}
+
+/// A change to the text at the caret.
+pub enum Edit {
+    Insert(char),
+    /// Inserts the character as it is, without closing brackets, expanding
+    /// abbreviations or overwriting, e.g. when it was typed after Ctrl+V.
+    InsertLiteral(char),
+    /// Inserts a tab or spaces up to the next tab stop, depending on the settings.
+    InsertTab,
+    /// Splits the line at the caret (Enter).
+    InsertNewline,
+    /// Removes the character before the caret (Backspace).
+    DeleteBackward,
+    /// Removes the character under the caret (Delete).
+    Delete,
+    /// Cuts the current line, or the selection, into the kill ring
+    /// (Ctrl+K). Cutting lines again right after adds them to the same entry.
+    KillLine,
+    /// Inserts the entry of the kill ring with the given index, 0 being the
+    /// newest one (Ctrl+U).
+    Paste(usize),
+    /// Comments out the current or selected lines, or uncomments them if
+    /// they all are comments already (Ctrl+/).
+    ToggleComment,
+    /// Changes the case of the selection or the word at the caret
+    /// (Alt+U, Alt+L and Alt+C).
+    ChangeCase(Case),
+    /// Sorts the selected lines, or all lines if fewer than two are selected.
+    SortLines(SortOrder),
+    /// Swaps the characters before and at the caret, or the last two at the
+    /// end of a line, and moves past them (Ctrl+T).
+    TransposeChars,
+    /// Swaps the words before and after the caret and moves past them (Alt+T).
+    TransposeWords,
+    /// Adds the given amount to the decimal or hexadecimal number at or
+    /// after the caret (Ctrl+A and Ctrl+X).
+    AddToNumber(i64),
+    /// Joins the next line onto the current one (Ctrl+J).
+    JoinLines,
+    /// Inserts a copy of the selection after it, or of the current line
+    /// below it (Alt+D).
+    Duplicate,
+    /// Swaps the current or selected lines with the line above (Alt+Up).
+    MoveLinesUp,
+    /// Swaps the current or selected lines with the line below (Alt+Down).
+    MoveLinesDown,
+    /// Removes one level of indentation from the selected lines, or the
+    /// lines with a caret (Shift+Tab).
+    Dedent,
+    Undo,
+    Redo,
+    /// Goes to the previous state of the undo tree in time, across branches.
+    UndoEarlier,
+    /// Goes to the next state of the undo tree in time.
+    UndoLater,
 }
 
 pub enum EditorCommand {
     Move(Direction),
+    /// Moves the caret while extending the selection.
+    Select(Direction),
+    /// Moves the caret while extending the rectangular selection: the
+    /// same screen columns on a range of lines.
+    SelectBlock(Direction),
+    /// Adds a caret on the line above the topmost caret.
+    AddCaretAbove,
+    /// Adds a caret on the line below the bottommost caret.
+    AddCaretBelow,
+    /// Adds a caret at the next occurrence of the word at the newest caret.
+    AddCaretAtNextMatch,
+    /// Moves the caret from a bracket at or before it to the bracket which
+    /// matches it (Ctrl+]).
+    JumpToMatchingBracket,
+    /// Scrolls the text down by a line, as far as the caret can stay
+    /// where it is (Alt+Page Up). Ctrl+Y, where some editors have this, is redo.
+    ScrollUp,
+    /// Scrolls the text up by a line (Alt+Page Down).
+    ScrollDown,
+    /// Goes back to where the caret was before the last jump, e.g. to another
+    /// line or the matching bracket (Ctrl+O).
+    JumpBack,
+    /// Goes forward again after `JumpBack`. Terminals send Ctrl+I as Tab,
+    /// so this is on Alt+I.
+    JumpForward,
+    /// Scrolls the caret's line to the middle of the screen, and on further
+    /// presses in a row to the top and the bottom (Ctrl+L).
+    Recenter,
+    /// Goes back to editing at a single caret.
+    RemoveExtraCarets,
+    /// Inserts the next key as it is, or the Unicode code point typed after
+    /// `u` (Ctrl+V).
+    QuotedInsert,
+    /// Copies the current line, or the selection, into the kill ring (Alt+W).
+    Copy,
+    /// Lists the kill ring to pick an entry to paste (Alt+K).
+    PasteFromKillRing,
+    /// Switches between inserting typed characters and having them replace
+    /// the character under the caret (Insert).
+    ToggleOverwrite,
+    Edit(Edit),
     Resize(Size),
+    Save,
+    SaveAs,
+    ConvertLineEndings,
+    /// Asks how to sort the lines, then sorts them.
+    SortLines,
+    /// Asks for a line, and optionally a column, to move the caret to.
+    GoToLine,
+    /// Asks for a letter to mark the caret's location with (Alt+M).
+    SetMark,
+    /// Asks for a mark to move the caret to (Alt+J).
+    JumpToMark,
+    /// Looks up the word at the caret in the `tags` file written by ctags
+    /// and goes to its definition (Alt+.).
+    GoToDefinition,
+    /// Opens the file whose path is at the caret, next to the buffer's file
+    /// or in the working directory (Alt+O).
+    OpenFileAtCaret,
+    /// Asks for text to search for, moving to the nearest match while
+    /// it is typed: towards the end with Ctrl+F, the start with Ctrl+B.
+    Search(SearchDirection),
+    /// Goes to the next match of the last search in the same direction
+    /// (Alt+N), or with `reverse` set, the opposite one (Alt+P).
+    RepeatSearch {
+        reverse: bool,
+    },
+    /// Goes to the next occurrence of the word at the caret as a whole
+    /// word, which Alt+N and Alt+P then repeat (Alt+F).
+    SearchWordAtCaret,
+    /// Says how many matches of the last search, or of the word at the
+    /// caret, there are, and which one the caret is on (Alt+A).
+    CountMatches,
+    /// Asks for text to search for in all files under the working directory,
+    /// then lets the user pick a line found to go to (Alt+G).
+    Grep,
+    /// Asks for text to replace and what to replace it with, then asks
+    /// about each match after the caret in turn (Alt+R).
+    Replace,
+    /// Asks for text to replace and what to replace it with, then replaces
+    /// all matches in the selection or the buffer at once (Ctrl+Alt+R).
+    ReplaceAll,
+    /// Stops highlighting the matches of the last search until it is
+    /// repeated (Alt+H).
+    ClearSearchHighlights,
+    ToggleHexView,
+    NewBuffer,
+    OpenRecentFile,
+    /// Lists the files under the working directory, narrowed down as the
+    /// user types, to open one (Ctrl+P).
+    FindFile,
+    /// Lists the open buffers, narrowed down as the user types, to switch
+    /// to one (Alt+B).
+    SwitchBuffer,
+    /// Shows the buffer of the next tab (Ctrl+PageDown, or Alt+>).
+    NextBuffer,
+    /// Shows the buffer of the previous tab (Ctrl+PageUp, or Alt+<).
+    PreviousBuffer,
+    /// Asks for a named command to run, e.g. `w`, `set wrap = on` or
+    /// `open <path>` (Ctrl+Shift+P, or Alt+:).
+    CommandLine,
+    /// Splits the screen into two windows showing the current buffer, one
+    /// above the other (Alt+2) or side by side (Alt+3).
+    SplitWindow(SplitLayout),
+    /// Moves the focus to the other window (F6).
+    FocusOtherWindow,
+    /// Moves the boundary between the windows to give the focused one a row
+    /// or column more (Alt+=) or less (Alt+-).
+    ResizeWindow(isize),
+    /// Closes the window which has the focus (Alt+0).
+    CloseWindow,
     Quit,
 }
 
@@ -27,18 +249,8 @@ impl TryFrom<Event> for EditorCommand {
         match event {
             Event::Key(KeyEvent {
                 code, modifiers, ..
-            }) => match (code, modifiers) {
-                (KeyCode::Char('c'), KeyModifiers::CONTROL) => Ok(Self::Quit),
-                (KeyCode::Up, _) => Ok(Self::Move(Direction::Up)),
-                (KeyCode::Down, _) => Ok(Self::Move(Direction::Down)),
-                (KeyCode::Left, _) => Ok(Self::Move(Direction::Left)),
-                (KeyCode::Right, _) => Ok(Self::Move(Direction::Right)),
-                (KeyCode::PageDown, _) => Ok(Self::Move(Direction::PageDown)),
-                (KeyCode::PageUp, _) => Ok(Self::Move(Direction::PageUp)),
-                (KeyCode::Home, _) => Ok(Self::Move(Direction::Home)),
-                (KeyCode::End, _) => Ok(Self::Move(Direction::End)),
-                _ => Err(format!("Key Code not supported: {code:?}")),
-            },
+            }) => Self::from_navigation_key(code, modifiers)
+                .map_or_else(|| Self::from_key(code, modifiers), Ok),
             Event::Resize(width_u16, height_u16) => {
                 // clippy::as_conversions: Will run into problems for rare edge case systems where usize < u16
                 #[allow(clippy::as_conversions)]
@@ -54,3 +266,183 @@ impl TryFrom<Event> for EditorCommand {
         }
     }
 }
+
+impl EditorCommand {
+    /// Whether the command may look through the whole buffer, e.g. for
+    /// the next match, and so needs all lines of a large file indexed.
+    pub const fn scans_buffer(&self) -> bool {
+        matches!(
+            self,
+            Self::Search(_)
+                | Self::RepeatSearch { .. }
+                | Self::SearchWordAtCaret
+                | Self::CountMatches
+                | Self::Replace
+                | Self::ReplaceAll
+                | Self::JumpToMatchingBracket
+                | Self::AddCaretAtNextMatch
+        )
+    }
+
+    /// The commands on the arrow keys, Page Up/Down, Home and End: Ctrl on
+    /// the arrows moves by words and paragraphs, Shift selects, Alt+Shift
+    /// selects a block, and Alt and Ctrl+Alt on Up and Down move lines and
+    /// add carets.
+    fn from_navigation_key(code: KeyCode, modifiers: KeyModifiers) -> Option<Self> {
+        let by_word = modifiers.contains(KeyModifiers::CONTROL);
+        let by_paragraph = by_word && !modifiers.contains(KeyModifiers::ALT);
+        let direction = match code {
+            KeyCode::Up if by_paragraph => Direction::ParagraphUp,
+            KeyCode::Down if by_paragraph => Direction::ParagraphDown,
+            KeyCode::Up => Direction::Up,
+            KeyCode::Down => Direction::Down,
+            KeyCode::Left if by_word => Direction::WordLeft,
+            KeyCode::Right if by_word => Direction::WordRight,
+            KeyCode::Left => Direction::Left,
+            KeyCode::Right => Direction::Right,
+            KeyCode::PageUp => Direction::PageUp,
+            KeyCode::PageDown => Direction::PageDown,
+            KeyCode::Home => Direction::Home,
+            KeyCode::End => Direction::End,
+            _ => return None,
+        };
+        let horizontal = matches!(
+            direction,
+            Direction::Left | Direction::Right | Direction::WordLeft | Direction::WordRight
+        );
+        let paragraph = matches!(direction, Direction::ParagraphUp | Direction::ParagraphDown);
+        let arrow = horizontal || matches!(direction, Direction::Up | Direction::Down);
+        let modifiers = if horizontal || paragraph {
+            modifiers.difference(KeyModifiers::CONTROL)
+        } else {
+            modifiers
+        };
+        let command = match (direction, modifiers) {
+            (Direction::PageDown, KeyModifiers::CONTROL) => Self::NextBuffer,
+            (Direction::PageUp, KeyModifiers::CONTROL) => Self::PreviousBuffer,
+            (Direction::PageDown, KeyModifiers::ALT) => Self::ScrollDown,
+            (Direction::PageUp, KeyModifiers::ALT) => Self::ScrollUp,
+            (Direction::Up, m) if m == KeyModifiers::CONTROL | KeyModifiers::ALT => {
+                Self::AddCaretAbove
+            }
+            (Direction::Down, m) if m == KeyModifiers::CONTROL | KeyModifiers::ALT => {
+                Self::AddCaretBelow
+            }
+            (direction, m) if arrow && m == KeyModifiers::ALT | KeyModifiers::SHIFT => {
+                Self::SelectBlock(direction)
+            }
+            (Direction::Up, KeyModifiers::ALT) => Self::Edit(Edit::MoveLinesUp),
+            (Direction::Down, KeyModifiers::ALT) => Self::Edit(Edit::MoveLinesDown),
+            (direction, KeyModifiers::SHIFT) => Self::Select(direction),
+            (direction, _) => Self::Move(direction),
+        };
+        Some(command)
+    }
+
+    fn from_key(code: KeyCode, modifiers: KeyModifiers) -> Result<Self, String> {
+        match (code, modifiers) {
+            (KeyCode::Char('c'), KeyModifiers::CONTROL) => Ok(Self::Quit),
+            (KeyCode::Char('s'), KeyModifiers::CONTROL) => Ok(Self::Save),
+            // Most terminals report Ctrl+Shift+S as an upper-case 'S'.
+            (KeyCode::Char('s' | 'S'), m) if m == KeyModifiers::CONTROL | KeyModifiers::SHIFT => {
+                Ok(Self::SaveAs)
+            }
+            (KeyCode::Char('e'), KeyModifiers::ALT) => Ok(Self::ConvertLineEndings),
+            (KeyCode::Char('x'), KeyModifiers::ALT) => Ok(Self::ToggleHexView),
+            (KeyCode::Char('s'), KeyModifiers::ALT) => Ok(Self::SortLines),
+            (KeyCode::Char('z'), KeyModifiers::CONTROL) => Ok(Self::Edit(Edit::Undo)),
+            (KeyCode::Char('y'), KeyModifiers::CONTROL) => Ok(Self::Edit(Edit::Redo)),
+            (KeyCode::Char('z'), KeyModifiers::ALT) => Ok(Self::Edit(Edit::UndoEarlier)),
+            (KeyCode::Char('y'), KeyModifiers::ALT) => Ok(Self::Edit(Edit::UndoLater)),
+            (KeyCode::Char('j'), KeyModifiers::CONTROL) => Ok(Self::Edit(Edit::JoinLines)),
+            (KeyCode::Char('d'), KeyModifiers::ALT) => Ok(Self::Edit(Edit::Duplicate)),
+            (KeyCode::Char('k'), KeyModifiers::CONTROL) => Ok(Self::Edit(Edit::KillLine)),
+            (KeyCode::Char('u'), KeyModifiers::CONTROL) => Ok(Self::Edit(Edit::Paste(0))),
+            // Most terminals send Ctrl+/ as Ctrl+_, which is reported as Ctrl+7.
+            (KeyCode::Char('/' | '7'), KeyModifiers::CONTROL) => {
+                Ok(Self::Edit(Edit::ToggleComment))
+            }
+            (KeyCode::Char('w'), KeyModifiers::ALT) => Ok(Self::Copy),
+            (KeyCode::Char('k'), KeyModifiers::ALT) => Ok(Self::PasteFromKillRing),
+            (KeyCode::Char('u'), KeyModifiers::ALT) => {
+                Ok(Self::Edit(Edit::ChangeCase(Case::Upper)))
+            }
+            (KeyCode::Char('l'), KeyModifiers::ALT) => {
+                Ok(Self::Edit(Edit::ChangeCase(Case::Lower)))
+            }
+            (KeyCode::Char('c'), KeyModifiers::ALT) => {
+                Ok(Self::Edit(Edit::ChangeCase(Case::Title)))
+            }
+            (KeyCode::Char('t'), KeyModifiers::CONTROL) => Ok(Self::Edit(Edit::TransposeChars)),
+            (KeyCode::Char('t'), KeyModifiers::ALT) => Ok(Self::Edit(Edit::TransposeWords)),
+            (KeyCode::Char('a'), KeyModifiers::CONTROL) => Ok(Self::Edit(Edit::AddToNumber(1))),
+            (KeyCode::Char('x'), KeyModifiers::CONTROL) => Ok(Self::Edit(Edit::AddToNumber(-1))),
+            (KeyCode::Char('v'), KeyModifiers::CONTROL) => Ok(Self::QuotedInsert),
+            (KeyCode::Char('g'), KeyModifiers::CONTROL) => Ok(Self::GoToLine),
+            (KeyCode::Char('m'), KeyModifiers::ALT) => Ok(Self::SetMark),
+            (KeyCode::Char('j'), KeyModifiers::ALT) => Ok(Self::JumpToMark),
+            (KeyCode::Char('.'), KeyModifiers::ALT) => Ok(Self::GoToDefinition),
+            (KeyCode::Char('o'), KeyModifiers::ALT) => Ok(Self::OpenFileAtCaret),
+            (KeyCode::Char('f'), KeyModifiers::CONTROL) => {
+                Ok(Self::Search(SearchDirection::Forward))
+            }
+            (KeyCode::Char('b'), KeyModifiers::CONTROL) => {
+                Ok(Self::Search(SearchDirection::Backward))
+            }
+            (KeyCode::Char('n'), KeyModifiers::ALT) => Ok(Self::RepeatSearch { reverse: false }),
+            (KeyCode::Char('p'), KeyModifiers::ALT) => Ok(Self::RepeatSearch { reverse: true }),
+            (KeyCode::Char('h'), KeyModifiers::ALT) => Ok(Self::ClearSearchHighlights),
+            (KeyCode::Char('f'), KeyModifiers::ALT) => Ok(Self::SearchWordAtCaret),
+            (KeyCode::Char('g'), KeyModifiers::ALT) => Ok(Self::Grep),
+            (KeyCode::Char('a'), KeyModifiers::ALT) => Ok(Self::CountMatches),
+            (KeyCode::Char('r'), KeyModifiers::ALT) => Ok(Self::Replace),
+            (KeyCode::Char('r'), m) if m == KeyModifiers::CONTROL | KeyModifiers::ALT => {
+                Ok(Self::ReplaceAll)
+            }
+            (KeyCode::Char('u'), m) if m == KeyModifiers::CONTROL | KeyModifiers::ALT => {
+                Ok(Self::Move(Direction::HalfPageUp))
+            }
+            (KeyCode::Char('d'), m) if m == KeyModifiers::CONTROL | KeyModifiers::ALT => {
+                Ok(Self::Move(Direction::HalfPageDown))
+            }
+            (KeyCode::Char('o'), KeyModifiers::CONTROL) => Ok(Self::JumpBack),
+            (KeyCode::Char('i'), KeyModifiers::ALT) => Ok(Self::JumpForward),
+            (KeyCode::Char('l'), KeyModifiers::CONTROL) => Ok(Self::Recenter),
+            (KeyCode::Char('n'), KeyModifiers::CONTROL) => Ok(Self::NewBuffer),
+            (KeyCode::Char('r'), KeyModifiers::CONTROL) => Ok(Self::OpenRecentFile),
+            (KeyCode::Char('p'), KeyModifiers::CONTROL) => Ok(Self::FindFile),
+            // Terminals which can't tell Ctrl+Shift+P from Ctrl+P have Alt+: instead.
+            (KeyCode::Char('p' | 'P'), m) if m == KeyModifiers::CONTROL | KeyModifiers::SHIFT => {
+                Ok(Self::CommandLine)
+            }
+            (KeyCode::Char(':'), m) if m.contains(KeyModifiers::ALT) => Ok(Self::CommandLine),
+            (KeyCode::Char('2'), KeyModifiers::ALT) => Ok(Self::SplitWindow(SplitLayout::Stacked)),
+            (KeyCode::Char('3'), KeyModifiers::ALT) => {
+                Ok(Self::SplitWindow(SplitLayout::SideBySide))
+            }
+            (KeyCode::Char('=' | '+'), m) if m.contains(KeyModifiers::ALT) => {
+                Ok(Self::ResizeWindow(1))
+            }
+            (KeyCode::Char('-'), KeyModifiers::ALT) => Ok(Self::ResizeWindow(-1)),
+            (KeyCode::F(6), _) => Ok(Self::FocusOtherWindow),
+            (KeyCode::Char('0'), KeyModifiers::ALT) => Ok(Self::CloseWindow),
+            (KeyCode::Char('b'), KeyModifiers::ALT) => Ok(Self::SwitchBuffer),
+            (KeyCode::Char('>'), m) if m.contains(KeyModifiers::ALT) => Ok(Self::NextBuffer),
+            (KeyCode::Char('<'), m) if m.contains(KeyModifiers::ALT) => Ok(Self::PreviousBuffer),
+            (KeyCode::Char('d'), KeyModifiers::CONTROL) => Ok(Self::AddCaretAtNextMatch),
+            // Most terminals send Ctrl+] as a control character which is reported as Ctrl+5.
+            (KeyCode::Char(']' | '5'), KeyModifiers::CONTROL) => Ok(Self::JumpToMatchingBracket),
+            (KeyCode::Esc, _) => Ok(Self::RemoveExtraCarets),
+            (KeyCode::Insert, KeyModifiers::NONE) => Ok(Self::ToggleOverwrite),
+            (KeyCode::Tab, _) => Ok(Self::Edit(Edit::InsertTab)),
+            (KeyCode::BackTab, _) => Ok(Self::Edit(Edit::Dedent)),
+            (KeyCode::Enter, _) => Ok(Self::Edit(Edit::InsertNewline)),
+            (KeyCode::Backspace, _) => Ok(Self::Edit(Edit::DeleteBackward)),
+            (KeyCode::Delete, _) => Ok(Self::Edit(Edit::Delete)),
+            (KeyCode::Char(character), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                Ok(Self::Edit(Edit::Insert(character)))
+            }
+            _ => Err(format!("Key Code not supported: {code:?}")),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use std::{
+    fmt,
+    io::{Error, ErrorKind},
+};
+
+const UTF8_BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: [u8; 2] = [0xFF, 0xFE];
+const UTF16BE_BOM: [u8; 2] = [0xFE, 0xFF];
+
+/// The encoding of a file on disk. Buffers always hold UTF-8 internally;
+/// the encoding determines how the file is decoded on load and encoded on save.
+#[derive(Default, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// UTF-8 preceded by a byte order mark.
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    /// ISO 8859-1, assumed for anything which isn't valid UTF-8.
+    Latin1,
+}
+
+impl Encoding {
+    /// Detects the encoding of `bytes` and decodes them.
+    /// UTF-16 is only recognised by its byte order mark.
+    pub fn decode(bytes: &[u8]) -> (Self, String) {
+        if let Some(rest) = bytes.strip_prefix(&UTF8_BOM) {
+            return (Self::Utf8Bom, String::from_utf8_lossy(rest).into_owned());
+        }
+        if let Some(rest) = bytes.strip_prefix(&UTF16LE_BOM) {
+            return (Self::Utf16Le, decode_utf16(rest, u16::from_le_bytes));
+        }
+        if let Some(rest) = bytes.strip_prefix(&UTF16BE_BOM) {
+            return (Self::Utf16Be, decode_utf16(rest, u16::from_be_bytes));
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(text) => (Self::Utf8, text.to_string()),
+            Err(_) => (
+                Self::Latin1,
+                bytes.iter().copied().map(char::from).collect(),
+            ),
+        }
+    }
+
+    pub const fn is_utf16(self) -> bool {
+        matches!(self, Self::Utf16Le | Self::Utf16Be)
+    }
+
+    /// Encodes `text` for writing to disk, including the byte order mark
+    /// if the encoding has one. Fails if a character can't be represented.
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::Utf8 => Ok(text.as_bytes().to_vec()),
+            Self::Utf8Bom => Ok(UTF8_BOM.iter().chain(text.as_bytes()).copied().collect()),
+            Self::Utf16Le => Ok(UTF16LE_BOM
+                .into_iter()
+                .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+                .collect()),
+            Self::Utf16Be => Ok(UTF16BE_BOM
+                .into_iter()
+                .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+                .collect()),
+            Self::Latin1 => text
+                .chars()
+                .map(|character| {
+                    u8::try_from(character).map_err(|_| {
+                        Error::new(
+                            ErrorKind::InvalidData,
+                            format!("'{character}' can't be represented in {self}"),
+                        )
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf8Bom => "UTF-8 BOM",
+            Self::Utf16Le => "UTF-16LE",
+            Self::Utf16Be => "UTF-16BE",
+            Self::Latin1 => "Latin-1",
+        };
+        write!(formatter, "{name}")
+    }
+}
+
+/// Decodes UTF-16 code units, replacing unpaired surrogates and a
+/// trailing odd byte with U+FFFD.
+fn decode_utf16(bytes: &[u8], to_unit: fn([u8; 2]) -> u16) -> String {
+    let chunks = bytes.chunks_exact(2);
+    let has_odd_byte = !chunks.remainder().is_empty();
+    let units = chunks.map(|pair| to_unit([pair[0], pair[1]]));
+    let mut text: String = char::decode_utf16(units)
+        .map(|unit| unit.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+    if has_odd_byte {
+        text.push(char::REPLACEMENT_CHARACTER);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Encoding;
+
+    #[test]
+    fn round_trips_each_encoding() {
+        for encoding in [
+            Encoding::Utf8,
+            Encoding::Utf8Bom,
+            Encoding::Utf16Le,
+            Encoding::Utf16Be,
+            Encoding::Latin1,
+        ] {
+            let text = "caf\u{e9}\nna\u{ef}ve";
+            let bytes = encoding.encode(text).unwrap();
+            let (detected, decoded) = Encoding::decode(&bytes);
+            // Latin-1 text which happens to be valid UTF-8 isn't told apart.
+            if encoding != Encoding::Latin1 {
+                assert_eq!(detected, encoding);
+            }
+            assert_eq!(decoded, text, "{encoding}");
+        }
+    }
+
+    #[test]
+    fn detects_by_byte_order_mark() {
+        assert_eq!(
+            Encoding::decode(b"\xef\xbb\xbfhi"),
+            (Encoding::Utf8Bom, "hi".to_string())
+        );
+        assert_eq!(
+            Encoding::decode(b"\xff\xfeh\0i\0"),
+            (Encoding::Utf16Le, "hi".to_string())
+        );
+        assert_eq!(
+            Encoding::decode(b"\xfe\xff\0h\0i"),
+            (Encoding::Utf16Be, "hi".to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_utf8_is_latin1() {
+        assert_eq!(
+            Encoding::decode(b"caf\xe9"),
+            (Encoding::Latin1, "caf\u{e9}".to_string())
+        );
+    }
+
+    #[test]
+    fn broken_utf16_is_replaced() {
+        let (_, text) = Encoding::decode(b"\xff\xfe\x00\xd8h\0!");
+        assert_eq!(text, "\u{fffd}h\u{fffd}");
+    }
+
+    #[test]
+    fn latin1_rejects_other_characters() {
+        assert!(Encoding::Latin1.encode("\u{20ac}").is_err());
+        assert_eq!(Encoding::Latin1.encode("\u{ff}").unwrap(), [0xff]);
+    }
+}
@@ -0,0 +1,80 @@
+use std::path::Path;
+
+/// The kind of text in a buffer, going by its file name.
+#[derive(Default, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FileType {
+    #[default]
+    Text,
+    Rust,
+    C,
+    JavaScript,
+    Go,
+    Python,
+    Toml,
+    Yaml,
+    Shell,
+    Makefile,
+    Lua,
+    Sql,
+}
+
+impl FileType {
+    pub fn detect(file_name: &str) -> Self {
+        let path = Path::new(file_name);
+        if path
+            .file_name()
+            .is_some_and(|name| name == "Makefile" || name == "makefile")
+        {
+            return Self::Makefile;
+        }
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+        match extension {
+            "rs" => Self::Rust,
+            "c" | "h" | "cc" | "cpp" | "hpp" | "java" | "cs" => Self::C,
+            "js" | "jsx" | "ts" | "tsx" => Self::JavaScript,
+            "go" => Self::Go,
+            "py" => Self::Python,
+            "toml" => Self::Toml,
+            "yml" | "yaml" => Self::Yaml,
+            "sh" | "bash" | "zsh" => Self::Shell,
+            "mk" => Self::Makefile,
+            "lua" => Self::Lua,
+            "sql" => Self::Sql,
+            _ => Self::Text,
+        }
+    }
+
+    /// The file type called `name` in the config file, e.g. `rust`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let file_type = match name {
+            "text" => Self::Text,
+            "rust" => Self::Rust,
+            "c" => Self::C,
+            "javascript" => Self::JavaScript,
+            "go" => Self::Go,
+            "python" => Self::Python,
+            "toml" => Self::Toml,
+            "yaml" => Self::Yaml,
+            "shell" => Self::Shell,
+            "makefile" => Self::Makefile,
+            "lua" => Self::Lua,
+            "sql" => Self::Sql,
+            _ => return None,
+        };
+        Some(file_type)
+    }
+
+    /// What starts a comment reaching to the end of the line, if the
+    /// language has such comments.
+    pub const fn comment_leader(self) -> Option<&'static str> {
+        match self {
+            Self::Rust | Self::C | Self::JavaScript | Self::Go => Some("//"),
+            Self::Python | Self::Toml | Self::Yaml | Self::Shell | Self::Makefile => Some("#"),
+            Self::Lua | Self::Sql => Some("--"),
+            Self::Text => None,
+        }
+    }
+}
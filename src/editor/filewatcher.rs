@@ -0,0 +1,68 @@
+use std::{
+    collections::HashMap,
+    fs::metadata,
+    path::PathBuf,
+    sync::{
+        mpsc::{channel, Receiver},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Watches files for changes made by other programs.
+///
+/// A background thread periodically compares each watched file's
+/// modification time with the one it saw last and reports the paths
+/// which changed. Changes made by hecto itself are reported as well,
+/// so receivers need to check whether a change is actually external.
+pub struct FileWatcher {
+    paths: Arc<Mutex<Vec<PathBuf>>>,
+    changes: Receiver<PathBuf>,
+}
+
+impl FileWatcher {
+    pub fn new() -> Self {
+        let paths: Arc<Mutex<Vec<PathBuf>>> = Arc::default();
+        let (sender, changes) = channel();
+        let watched = Arc::clone(&paths);
+        thread::spawn(move || {
+            let mut last_seen: HashMap<PathBuf, SystemTime> = HashMap::new();
+            loop {
+                thread::sleep(POLL_INTERVAL);
+                let current = watched
+                    .lock()
+                    .map(|paths| paths.clone())
+                    .unwrap_or_default();
+                last_seen.retain(|path, _| current.contains(path));
+                for path in current {
+                    let Ok(mtime) = metadata(&path).and_then(|meta| meta.modified()) else {
+                        continue;
+                    };
+                    let previous = last_seen.insert(path.clone(), mtime);
+                    if previous.is_some_and(|previous| previous != mtime)
+                        && sender.send(path).is_err()
+                    {
+                        // The editor is gone, so nobody is listening anymore.
+                        return;
+                    }
+                }
+            }
+        });
+        Self { paths, changes }
+    }
+
+    /// Replaces the set of watched files.
+    pub fn watch(&self, paths: Vec<PathBuf>) {
+        if let Ok(mut watched) = self.paths.lock() {
+            *watched = paths;
+        }
+    }
+
+    /// Returns the next changed path, if any, without blocking.
+    pub fn try_recv(&self) -> Option<PathBuf> {
+        self.changes.try_recv().ok()
+    }
+}
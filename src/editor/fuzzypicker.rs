@@ -0,0 +1,162 @@
+use std::cmp::Reverse;
+
+use super::terminal::{Position, Size, Terminal};
+
+/// How many entries the list shows at once.
+const MAX_ROWS: usize = 10;
+
+/// Something to pick from a `FuzzyPicker`.
+pub struct PickerEntry {
+    /// What the user's input is matched against.
+    pub name: String,
+    /// Shows a `*` before the name, e.g. for a modified buffer.
+    pub marked: bool,
+}
+
+/// A list over the bottom of the view, narrowed down to the entries whose
+/// names fuzzily match what the user typed, to pick one, e.g. a file to open.
+pub struct FuzzyPicker {
+    entries: Vec<PickerEntry>,
+    /// The indices into `entries` of the matching ones, best match first.
+    matches: Vec<usize>,
+    /// The entry of `matches` which is picked on Enter.
+    selection: usize,
+    /// The screen row the view starts in.
+    origin_row: usize,
+    /// The size of the view, whose bottom rows the list covers.
+    size: Size,
+}
+
+impl FuzzyPicker {
+    pub fn new(entries: Vec<PickerEntry>, origin_row: usize, size: Size) -> Self {
+        let matches = (0..entries.len()).collect();
+        Self {
+            entries,
+            matches,
+            selection: 0,
+            origin_row,
+            size,
+        }
+    }
+
+    /// Keeps the entries which `query` fuzzily matches, best match first,
+    /// and selects the first one.
+    pub fn filter(&mut self, query: &str) {
+        let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+        let mut scored: Vec<(usize, usize)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| Some((index, fuzzy_score(&entry.name, &query)?)))
+            .collect();
+        // Shorter names first among equally good matches; sorting is stable,
+        // so the order of the entries decides after that.
+        scored.sort_by_key(|&(index, score)| (Reverse(score), self.entries[index].name.len()));
+        self.matches = scored.into_iter().map(|(index, _)| index).collect();
+        self.selection = 0;
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selection = self
+            .selection
+            .checked_sub(1)
+            .unwrap_or_else(|| self.matches.len().saturating_sub(1));
+    }
+
+    pub fn select_next(&mut self) {
+        let next = self.selection.saturating_add(1);
+        self.selection = if next < self.matches.len() { next } else { 0 };
+    }
+
+    /// The index of the selected entry, in the order they were passed in.
+    pub fn selected(&self) -> Option<usize> {
+        self.matches.get(self.selection).copied()
+    }
+
+    pub fn name(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(|entry| entry.name.as_str())
+    }
+
+    pub fn entry_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    pub fn resize(&mut self, origin_row: usize, size: Size) {
+        self.origin_row = origin_row;
+        self.size = size;
+    }
+
+    /// Draws the list into the bottom rows of the view, the selected entry
+    /// inverted. It is drawn on every refresh, as the view may have drawn
+    /// over it.
+    pub fn render(&self) {
+        let rows = MAX_ROWS.min(self.size.height);
+        let top = self
+            .origin_row
+            .saturating_add(self.size.height.saturating_sub(rows));
+        // Scroll the list so the selected entry stays visible.
+        let first = self.selection.saturating_sub(rows.saturating_sub(1));
+        let mut result = Ok(());
+        for row in 0..rows {
+            let index = first.saturating_add(row);
+            let entry = self
+                .matches
+                .get(index)
+                .and_then(|&entry| self.entries.get(entry));
+            let text: String = entry
+                .map(|entry| {
+                    let mark = if entry.marked { '*' } else { ' ' };
+                    format!("{mark} {}", entry.name)
+                })
+                .unwrap_or_default()
+                .chars()
+                .take(self.size.width)
+                .collect();
+            let at = top.saturating_add(row);
+            result = result.and_then(|()| {
+                if index == self.selection && entry.is_some() {
+                    Terminal::move_caret_to(Position { row: at, col: 0 })
+                        .and_then(|()| Terminal::clear_line())
+                        .and_then(|()| Terminal::print_inverted(&text))
+                } else {
+                    Terminal::print_row(at, &text)
+                }
+            });
+        }
+        debug_assert!(result.is_ok(), "Failed to render fuzzy picker");
+    }
+}
+
+/// How well `query`, lower-cased, matches `name` if its characters appear
+/// in `name` in order, ignoring case: characters right after the previous
+/// match and at the start of a path component or word count more. `None`
+/// if some character doesn't appear.
+fn fuzzy_score(name: &str, query: &[char]) -> Option<usize> {
+    let mut remaining = query.iter().peekable();
+    let mut score: usize = 0;
+    let mut previous: Option<char> = None;
+    let mut previous_matched = false;
+    for character in name.chars() {
+        let Some(&&wanted) = remaining.peek() else {
+            break;
+        };
+        let matched = character.to_lowercase().eq(std::iter::once(wanted));
+        if matched {
+            remaining.next();
+            score = score.saturating_add(1);
+            if previous_matched {
+                score = score.saturating_add(4);
+            }
+            if previous.is_none_or(|before| "/_-. ".contains(before)) {
+                score = score.saturating_add(6);
+            }
+        }
+        previous_matched = matched;
+        previous = Some(character);
+    }
+    remaining.peek().is_none().then_some(score)
+}
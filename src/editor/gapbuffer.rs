@@ -0,0 +1,102 @@
+/// A sequence which is cheap to edit around one position, the gap:
+/// inserting or removing there doesn't move any of the other elements.
+/// Editing elsewhere first moves the gap, which costs as many steps as
+/// the gap moves.
+///
+/// The elements before the gap are kept in order in `before`, the ones
+/// after it in reverse order in `after`, so both sides grow and shrink at
+/// the end of their vector.
+#[derive(Clone, Default)]
+pub struct GapBuffer<T> {
+    before: Vec<T>,
+    after: Vec<T>,
+}
+
+impl<T: Copy> GapBuffer<T> {
+    pub fn len(&self) -> usize {
+        self.before.len().saturating_add(self.after.len())
+    }
+
+    /// Inserts `value` at `index`, or at the end if `index` is beyond it.
+    pub fn insert(&mut self, index: usize, value: T) {
+        self.move_gap(index);
+        self.before.push(value);
+    }
+
+    /// Removes and returns the element at `index`, if there is one.
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        self.move_gap(index);
+        self.after.pop()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        self.before.iter().chain(self.after.iter().rev()).copied()
+    }
+
+    /// Moves the gap to just before the element at `index`, or to the end.
+    fn move_gap(&mut self, index: usize) {
+        while self.before.len() > index {
+            if let Some(value) = self.before.pop() {
+                self.after.push(value);
+            }
+        }
+        while self.before.len() < index {
+            let Some(value) = self.after.pop() else {
+                break;
+            };
+            self.before.push(value);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for GapBuffer<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(values: I) -> Self {
+        Self {
+            before: values.into_iter().collect(),
+            after: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GapBuffer;
+
+    fn contents(buffer: &GapBuffer<char>) -> String {
+        buffer.iter().collect()
+    }
+
+    #[test]
+    fn inserts_at_the_gap_and_elsewhere() {
+        let mut buffer: GapBuffer<char> = "ac".chars().collect();
+        buffer.insert(1, 'b');
+        assert_eq!(contents(&buffer), "abc");
+        buffer.insert(0, '>');
+        buffer.insert(4, '<');
+        assert_eq!(contents(&buffer), ">abc<");
+        buffer.insert(99, '!');
+        assert_eq!(contents(&buffer), ">abc<!");
+        assert_eq!(buffer.len(), 6);
+    }
+
+    #[test]
+    fn removes_around_the_gap() {
+        let mut buffer: GapBuffer<char> = "hello".chars().collect();
+        assert_eq!(buffer.remove(4), Some('o'));
+        assert_eq!(buffer.remove(0), Some('h'));
+        assert_eq!(buffer.remove(1), Some('l'));
+        assert_eq!(contents(&buffer), "el");
+        assert_eq!(buffer.remove(2), None);
+        assert_eq!(buffer.len(), 2);
+    }
+
+    #[test]
+    fn moving_the_gap_keeps_the_order() {
+        let mut buffer: GapBuffer<char> = "abcdef".chars().collect();
+        for index in [0, 6, 3, 1, 5, 2, 99] {
+            buffer.move_gap(index);
+            assert_eq!(buffer.before.len(), index.min(6));
+            assert_eq!(contents(&buffer), "abcdef");
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    fs::{metadata, read},
+    path::{Path, PathBuf},
+};
+
+use super::{
+    projectfiles::project_files,
+    search::{self, Pattern},
+};
+
+/// How many matches a grep collects at most, so searching a huge tree
+/// doesn't go on for long.
+const MAX_MATCHES: usize = 1000;
+/// Files larger than this are skipped, as they are unlikely to be source.
+const MAX_FILE_SIZE: u64 = 4 * 1024 * 1024;
+
+/// A line matching the pattern in a file under the directory searched.
+pub struct GrepMatch {
+    pub file: PathBuf,
+    /// The line and the character within it where the match starts,
+    /// counted from 0.
+    pub line: usize,
+    pub column: usize,
+    pub text: String,
+}
+
+impl GrepMatch {
+    /// The match as `file:line: text` for the picker, with the line
+    /// counted from 1 and without a leading `./`.
+    pub fn description(&self) -> String {
+        let file = self.file.strip_prefix(".").unwrap_or(&self.file);
+        format!(
+            "{}:{}: {}",
+            file.display(),
+            self.line.saturating_add(1),
+            self.text.trim()
+        )
+    }
+}
+
+/// The first match of `pattern` on each line of the project's files under
+/// `root`, in order of their paths, which start with `root`. Binary files,
+/// files which aren't UTF-8 and very large files are skipped. `cancelled`
+/// is asked before each file whether to stop early; the matches found so
+/// far are returned then.
+pub fn grep(root: &Path, pattern: &Pattern, mut cancelled: impl FnMut() -> bool) -> Vec<GrepMatch> {
+    let mut matches = Vec::new();
+    for file in project_files(root) {
+        if matches.len() >= MAX_MATCHES || cancelled() {
+            break;
+        }
+        grep_file(&file, pattern, &mut matches);
+    }
+    matches
+}
+
+fn grep_file(file: &Path, pattern: &Pattern, matches: &mut Vec<GrepMatch>) {
+    if metadata(file).map_or(true, |metadata| metadata.len() > MAX_FILE_SIZE) {
+        return;
+    }
+    let Ok(bytes) = read(file) else {
+        return;
+    };
+    if bytes.contains(&0) {
+        return;
+    }
+    let Ok(contents) = String::from_utf8(bytes) else {
+        return;
+    };
+    for (line, text) in contents.lines().enumerate() {
+        if matches.len() >= MAX_MATCHES {
+            return;
+        }
+        if let Some(found) = search::find_all(text, pattern).first() {
+            matches.push(GrepMatch {
+                file: file.to_path_buf(),
+                line,
+                column: found.start,
+                text: text.to_string(),
+            });
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::{cmp::min, fmt::Write};
+
+use super::{
+    editorcommand::Direction,
+    terminal::{Position, Size, Terminal},
+};
+
+/// Columns taken up by the offset at the start of each row, including
+/// the two spaces which follow it.
+const OFFSET_WIDTH: usize = 10;
+const MAX_BYTES_PER_ROW: usize = 16;
+
+/// Shows raw bytes as a hex dump: the offset of each row, the bytes in
+/// hex and the same bytes as ASCII, with non-printable ones shown as `.`.
+///
+/// The caret points at a single byte and moves by byte offset.
+#[derive(Default)]
+pub struct HexView {
+    needs_redraw: bool,
+    /// The screen row the view's first row is drawn in.
+    origin_row: usize,
+    size: Size,
+    /// The offset of the byte the caret is on.
+    offset: usize,
+    /// The first row shown at the top of the view.
+    scroll_row: usize,
+}
+
+impl HexView {
+    pub fn render(&mut self, bytes: &[u8]) {
+        if !self.needs_redraw {
+            return;
+        }
+        let Size { height, width } = self.size;
+        if height == 0 || width == 0 {
+            return;
+        }
+        let per_row = self.bytes_per_row();
+        for current_row in 0..height {
+            let row_start = current_row
+                .saturating_add(self.scroll_row)
+                .saturating_mul(per_row);
+            let text = bytes
+                .get(row_start..)
+                .filter(|rest| !rest.is_empty())
+                .map_or_else(
+                    || "~".to_string(),
+                    |rest| {
+                        Self::format_row(row_start, rest.get(..per_row).unwrap_or(rest), per_row)
+                    },
+                );
+            let visible: String = text.chars().take(width).collect();
+            let result = Terminal::print_row(self.origin_row.saturating_add(current_row), &visible);
+            debug_assert!(result.is_ok(), "Failed to render hex row");
+        }
+        self.needs_redraw = false;
+    }
+
+    /// Moves the caret by bytes (`Left`/`Right`), rows (`Up`/`Down`) or
+    /// pages, staying within `len` bytes.
+    pub fn move_offset(&mut self, direction: &Direction, len: usize) {
+        let per_row = self.bytes_per_row();
+        let page = per_row.saturating_mul(self.size.height.max(1));
+        let half_page = per_row.saturating_mul(self.size.height.checked_div(2).unwrap_or(0).max(1));
+        let column = self.offset.checked_rem(per_row).unwrap_or(0);
+        let offset = self.offset;
+        let offset = match direction {
+            Direction::Left | Direction::WordLeft => offset.saturating_sub(1),
+            Direction::Right | Direction::WordRight => offset.saturating_add(1),
+            Direction::Up | Direction::ParagraphUp => offset.checked_sub(per_row).unwrap_or(offset),
+            Direction::Down | Direction::ParagraphDown => offset.saturating_add(per_row),
+            Direction::PageUp => offset.saturating_sub(page),
+            Direction::PageDown => offset.saturating_add(page),
+            Direction::HalfPageUp => offset.saturating_sub(half_page),
+            Direction::HalfPageDown => offset.saturating_add(half_page),
+            Direction::Home => offset.saturating_sub(column),
+            Direction::End => offset
+                .saturating_sub(column)
+                .saturating_add(per_row.saturating_sub(1)),
+        };
+        self.offset = min(offset, len.saturating_sub(1));
+        self.scroll_offset_into_view();
+    }
+
+    /// Puts the caret back on the first byte, e.g. for a newly shown buffer.
+    pub fn reset(&mut self) {
+        self.offset = 0;
+        self.scroll_row = 0;
+        self.needs_redraw = true;
+    }
+
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Places the view at screen row `origin_row`, `to.height` rows high.
+    pub fn resize(&mut self, origin_row: usize, to: Size) {
+        self.origin_row = origin_row;
+        self.size = to;
+        self.scroll_offset_into_view();
+        self.needs_redraw = true;
+    }
+
+    pub fn mark_redraw(&mut self) {
+        self.needs_redraw = true;
+    }
+
+    /// Where the caret goes: on the first hex digit of the current byte.
+    pub fn get_position(&self) -> Position {
+        let per_row = self.bytes_per_row();
+        let row = self.offset.checked_div(per_row).unwrap_or(0);
+        let column = self.offset.checked_rem(per_row).unwrap_or(0);
+        Position {
+            col: OFFSET_WIDTH.saturating_add(Self::hex_column(column)),
+            row: row
+                .saturating_sub(self.scroll_row)
+                .saturating_add(self.origin_row),
+        }
+    }
+
+    /// Fits as many bytes into a row as the width allows, up to 16, and
+    /// rounds down to a multiple of four so columns stay easy to count.
+    fn bytes_per_row(&self) -> usize {
+        // Each byte takes three columns in hex and one as ASCII; the two
+        // halves of the hex part and the ASCII column need four more.
+        let available = self
+            .size
+            .width
+            .saturating_sub(OFFSET_WIDTH.saturating_add(4));
+        let fitting = min(available.checked_div(4).unwrap_or(0), MAX_BYTES_PER_ROW);
+        if fitting >= 4 {
+            fitting.saturating_sub(fitting.checked_rem(4).unwrap_or(0))
+        } else {
+            fitting.max(1)
+        }
+    }
+
+    /// The column of the `column`th byte within the hex part of a row,
+    /// which has an extra space between its two halves.
+    fn hex_column(column: usize) -> usize {
+        let gap = usize::from(column >= MAX_BYTES_PER_ROW.checked_div(2).unwrap_or(0));
+        column.saturating_mul(3).saturating_add(gap)
+    }
+
+    fn format_row(row_start: usize, bytes: &[u8], per_row: usize) -> String {
+        let mut row = format!("{row_start:08x}  ");
+        let mut hex = String::new();
+        for column in 0..per_row {
+            while hex.len() < Self::hex_column(column) {
+                hex.push(' ');
+            }
+            match bytes.get(column) {
+                Some(byte) => {
+                    let _ = write!(hex, "{byte:02x} ");
+                }
+                None => hex.push_str("   "),
+            }
+        }
+        row.push_str(&hex);
+        row.push_str(" |");
+        row.extend(bytes.iter().map(|&byte| {
+            if byte.is_ascii_graphic() || byte == b' ' {
+                char::from(byte)
+            } else {
+                '.'
+            }
+        }));
+        row.push('|');
+        row
+    }
+
+    fn scroll_offset_into_view(&mut self) {
+        let per_row = self.bytes_per_row();
+        let row = self.offset.checked_div(per_row).unwrap_or(0);
+        let height = self.size.height;
+        if row < self.scroll_row {
+            self.scroll_row = row;
+            self.needs_redraw = true;
+        } else if row >= self.scroll_row.saturating_add(height) {
+            self.scroll_row = row.saturating_sub(height).saturating_add(1);
+            self.needs_redraw = true;
+        }
+    }
+}
@@ -0,0 +1,563 @@
+use std::num::ParseIntError;
+
+use super::location::Location;
+
+/// A single change to a buffer's text. `text` may span several lines,
+/// separated by `\n`.
+#[derive(Clone)]
+pub enum Change {
+    Insert { at: Location, text: String },
+    Delete { at: Location, text: String },
+}
+
+impl Change {
+    /// The change which reverts this one.
+    pub fn inverse(&self) -> Self {
+        match self {
+            Self::Insert { at, text } => Self::Delete {
+                at: *at,
+                text: text.clone(),
+            },
+            Self::Delete { at, text } => Self::Insert {
+                at: *at,
+                text: text.clone(),
+            },
+        }
+    }
+
+    /// Where the caret belongs after this change was applied.
+    pub fn caret_after(&self) -> Location {
+        match self {
+            Self::Insert { at, text } => end_of(*at, text),
+            Self::Delete { at, .. } => *at,
+        }
+    }
+
+    /// Where the text of this change ends if it is inserted at its location,
+    /// i.e. the end of the inserted or deleted text.
+    pub fn text_end(&self) -> Location {
+        match self {
+            Self::Insert { at, text } | Self::Delete { at, text } => end_of(*at, text),
+        }
+    }
+
+    /// Whether `next` continues this change while typing or deleting, so
+    /// both belong to the same undo step. A typed word and the whitespace
+    /// after it form one step; a new word starts a new one.
+    fn continues_with(&self, next: &Self) -> bool {
+        match (self, next) {
+            (
+                Self::Insert { at, text },
+                Self::Insert {
+                    at: next_at,
+                    text: next_text,
+                },
+            ) => {
+                let starts_word = !next_text.starts_with(char::is_whitespace)
+                    && text.ends_with(char::is_whitespace);
+                !text.contains('\n') && end_of(*at, text) == *next_at && !starts_word
+            }
+            (
+                Self::Delete { at, text },
+                Self::Delete {
+                    at: next_at,
+                    text: next_text,
+                },
+            ) => {
+                // Delete removes at the same location, Backspace right before it.
+                let before = next_at.y == at.y
+                    && next_at.x.saturating_add(next_text.chars().count()) == at.x;
+                !text.contains('\n') && (next_at == at || before)
+            }
+            _ => false,
+        }
+    }
+
+    /// Merges `next` into this change if it continues it.
+    fn merge(&mut self, next: &Self) -> bool {
+        if !self.continues_with(next) {
+            return false;
+        }
+        match (self, next) {
+            (
+                Self::Insert { text, .. },
+                Self::Insert {
+                    text: next_text, ..
+                },
+            ) => {
+                text.push_str(next_text);
+            }
+            (
+                Self::Delete { at, text },
+                Self::Delete {
+                    at: next_at,
+                    text: next_text,
+                },
+            ) => {
+                if next_at == at {
+                    text.push_str(next_text);
+                } else {
+                    text.insert_str(0, next_text);
+                    *at = *next_at;
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// The location just past `text` when it is inserted at `at`.
+fn end_of(at: Location, text: &str) -> Location {
+    let mut lines = text.split('\n');
+    let first_len = lines.next().map_or(0, |line| line.chars().count());
+    let mut end = Location {
+        x: at.x.saturating_add(first_len),
+        y: at.y,
+    };
+    for line in lines {
+        end = Location {
+            x: line.chars().count(),
+            y: end.y.saturating_add(1),
+        };
+    }
+    end
+}
+
+/// One state of the buffer in the undo tree, reached from its parent by
+/// applying `changes`.
+#[derive(Default)]
+struct Node {
+    parent: usize,
+    changes: Vec<Change>,
+    /// The child redo goes to: the one most recently created or visited.
+    redo_child: Option<usize>,
+}
+
+/// The changes made to a buffer, for undo and redo, kept as a tree: making
+/// a change after undoing starts a new branch instead of discarding the
+/// undone steps. Nodes are numbered in the order they were created, which
+/// lets `earlier` and `later` walk through every state the buffer was ever
+/// in. Consecutive typing or deleting is merged into one step until the
+/// step is ended, e.g. by moving the caret.
+pub struct History {
+    /// Node 0 is the root: the buffer as it was loaded.
+    nodes: Vec<Node>,
+    current: usize,
+    /// Whether the next change may be merged into the current step.
+    open: bool,
+    /// How many groups are started: while any is, changes are collected
+    /// into the current step wherever they are, e.g. while one edit is made
+    /// at several carets.
+    group_depth: usize,
+    /// Whether the current step stays open after the outermost group ends,
+    /// as it was started by `start_continuing_group`.
+    group_continues: bool,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            nodes: vec![Node::default()],
+            current: 0,
+            open: false,
+            group_depth: 0,
+            group_continues: false,
+        }
+    }
+}
+
+impl History {
+    pub fn record(&mut self, change: Change) {
+        let index = self.nodes.len();
+        let current = &mut self.nodes[self.current];
+        if self.open && self.current != 0 && current.redo_child.is_none() {
+            if self.group_depth > 0 {
+                current.changes.push(change);
+                return;
+            }
+            if let Some(last) = current.changes.last_mut() {
+                if last.merge(&change) {
+                    return;
+                }
+            }
+        }
+        current.redo_child = Some(index);
+        self.nodes.push(Node {
+            parent: self.current,
+            changes: vec![change],
+            redo_child: None,
+        });
+        self.current = index;
+        self.open = true;
+    }
+
+    /// Makes the next change start a new undo step.
+    pub fn end_step(&mut self) {
+        self.open = false;
+    }
+
+    /// Starts a step which takes all changes up to the matching `end_group`,
+    /// so they are undone together. Groups may be nested.
+    pub fn start_group(&mut self) {
+        if self.group_depth == 0 {
+            self.open = false;
+        }
+        self.group_depth = self.group_depth.saturating_add(1);
+    }
+
+    /// Starts a group like `start_group`, but one which joins the current
+    /// step if that ends with text inserted right up to `at`, and which
+    /// leaves its step open, so e.g. consecutive overwrites are undone
+    /// together like consecutive typing.
+    pub fn start_continuing_group(&mut self, at: Location) {
+        if self.group_depth == 0 {
+            let current = &self.nodes[self.current];
+            let joins = current.redo_child.is_none()
+                && current.changes.last().is_some_and(|last| {
+                    matches!(last, Change::Insert { text, .. } if !text.contains('\n'))
+                        && last.text_end() == at
+                });
+            self.open = self.open && joins;
+            self.group_continues = true;
+        }
+        self.group_depth = self.group_depth.saturating_add(1);
+    }
+
+    pub fn end_group(&mut self) {
+        self.group_depth = self.group_depth.saturating_sub(1);
+        if self.group_depth == 0 {
+            self.open = self.open && std::mem::take(&mut self.group_continues);
+        }
+    }
+
+    /// The changes to apply, in order, to revert the current step.
+    pub fn undo(&mut self) -> Option<Vec<Change>> {
+        self.open = false;
+        if self.current == 0 {
+            return None;
+        }
+        let node = &self.nodes[self.current];
+        let changes = node.changes.iter().rev().map(Change::inverse).collect();
+        let parent = node.parent;
+        self.nodes[parent].redo_child = Some(self.current);
+        self.current = parent;
+        Some(changes)
+    }
+
+    /// The changes to apply, in order, to redo the most recently visited
+    /// branch below the current step.
+    pub fn redo(&mut self) -> Option<Vec<Change>> {
+        self.open = false;
+        let child = self.nodes[self.current].redo_child?;
+        self.current = child;
+        Some(self.nodes[child].changes.clone())
+    }
+
+    /// The changes to apply to go back to the state created just before
+    /// the current one, which may lie on another branch.
+    pub fn earlier(&mut self) -> Option<Vec<Change>> {
+        let target = self.current.checked_sub(1)?;
+        Some(self.travel_to(target))
+    }
+
+    /// The changes to apply to go to the state created just after the
+    /// current one.
+    pub fn later(&mut self) -> Option<Vec<Change>> {
+        let target = self.current.saturating_add(1);
+        (target < self.nodes.len()).then(|| self.travel_to(target))
+    }
+
+    /// The current state's number and the number of states, for messages.
+    pub const fn position(&self) -> (usize, usize) {
+        (self.current, self.nodes.len().saturating_sub(1))
+    }
+
+    /// Undoes up to the common ancestor of the current node and `target`,
+    /// then redoes down to `target`.
+    fn travel_to(&mut self, target: usize) -> Vec<Change> {
+        let mut changes = Vec::new();
+        let mut path_down = Vec::new();
+        let mut ancestor = target;
+        // Parents are always older than their children, so repeatedly
+        // stepping up from the newer of both nodes meets at the common ancestor.
+        while ancestor != self.current {
+            if ancestor > self.current {
+                path_down.push(ancestor);
+                ancestor = self.nodes[ancestor].parent;
+            } else if let Some(mut undone) = self.undo() {
+                changes.append(&mut undone);
+            } else {
+                break;
+            }
+        }
+        for node in path_down.into_iter().rev() {
+            self.nodes[self.current].redo_child = Some(node);
+            if let Some(mut redone) = self.redo() {
+                changes.append(&mut redone);
+            }
+        }
+        self.open = false;
+        changes
+    }
+
+    /// Writes the tree as text, one node or change per line, for the undo file.
+    pub fn serialize(&self) -> String {
+        let mut lines = vec![format!("current {}", self.current)];
+        for (index, node) in self.nodes.iter().enumerate() {
+            let redo_child = node
+                .redo_child
+                .map_or_else(|| "-".to_string(), |child| child.to_string());
+            if index == 0 {
+                lines.push(format!("root {redo_child}"));
+                continue;
+            }
+            lines.push(format!("node {} {redo_child}", node.parent));
+            for change in &node.changes {
+                let (kind, at, text) = match change {
+                    Change::Insert { at, text } => ('i', at, text),
+                    Change::Delete { at, text } => ('d', at, text),
+                };
+                lines.push(format!("{kind} {} {} {}", at.y, at.x, escape(text)));
+            }
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    /// Reads a tree written by `serialize`. Returns `None` if `text` is malformed.
+    pub fn deserialize(text: &str) -> Option<Self> {
+        let mut history = Self::default();
+        for line in text.lines() {
+            let mut parts = line.splitn(4, ' ');
+            match parts.next()? {
+                "current" => history.current = parts.next()?.parse().ok()?,
+                "root" => history.nodes[0].redo_child = parse_child(parts.next()?).ok()?,
+                "node" => {
+                    let parent = parts.next()?.parse().ok()?;
+                    let redo_child = parse_child(parts.next()?).ok()?;
+                    history.nodes.push(Node {
+                        parent,
+                        changes: Vec::new(),
+                        redo_child,
+                    });
+                }
+                kind => {
+                    let y = parts.next()?.parse().ok()?;
+                    let x = parts.next()?.parse().ok()?;
+                    let text = unescape(parts.next()?)?;
+                    let at = Location { x, y };
+                    let change = match kind {
+                        "i" => Change::Insert { at, text },
+                        "d" => Change::Delete { at, text },
+                        _ => return None,
+                    };
+                    history.nodes.last_mut()?.changes.push(change);
+                }
+            }
+        }
+        let count = history.nodes.len();
+        let valid = history.current < count
+            && history.nodes.iter().enumerate().all(|(index, node)| {
+                (index == 0 || node.parent < index)
+                    && node
+                        .redo_child
+                        .is_none_or(|child| child > index && child < count)
+            });
+        valid.then_some(history)
+    }
+}
+
+/// Parses a node's redo child, written as `-` if there is none.
+fn parse_child(text: &str) -> Result<Option<usize>, ParseIntError> {
+    match text {
+        "-" => Ok(None),
+        child => child.parse().map(Some),
+    }
+}
+
+/// Escapes backslashes and line breaks so `text` fits on one line.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(text: &str) -> Option<String> {
+    let mut result = String::new();
+    let mut chars = text.chars();
+    while let Some(character) = chars.next() {
+        if character == '\\' {
+            match chars.next()? {
+                'n' => result.push('\n'),
+                'r' => result.push('\r'),
+                '\\' => result.push('\\'),
+                _ => return None,
+            }
+        } else {
+            result.push(character);
+        }
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Change, History, Location};
+
+    fn insert(x: usize, text: &str) -> Change {
+        Change::Insert {
+            at: Location { x, y: 0 },
+            text: text.to_string(),
+        }
+    }
+
+    fn delete(x: usize, text: &str) -> Change {
+        Change::Delete {
+            at: Location { x, y: 0 },
+            text: text.to_string(),
+        }
+    }
+
+    /// The changes as `+x:text` or `-x:text`, for comparing them.
+    fn describe(changes: &[Change]) -> Vec<String> {
+        changes
+            .iter()
+            .map(|change| match change {
+                Change::Insert { at, text } => format!("+{}:{text}", at.x),
+                Change::Delete { at, text } => format!("-{}:{text}", at.x),
+            })
+            .collect()
+    }
+
+    fn undo(history: &mut History) -> Vec<String> {
+        describe(&history.undo().unwrap_or_default())
+    }
+
+    fn redo(history: &mut History) -> Vec<String> {
+        describe(&history.redo().unwrap_or_default())
+    }
+
+    #[test]
+    fn typing_merges_until_a_new_word_starts() {
+        let mut history = History::default();
+        for (x, text) in [(0, "a"), (1, "b"), (2, " "), (3, "c")] {
+            history.record(insert(x, text));
+        }
+        assert_eq!(undo(&mut history), ["-3:c"]);
+        assert_eq!(undo(&mut history), ["-0:ab "]);
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn line_breaks_and_ended_steps_stop_merging() {
+        let mut history = History::default();
+        history.record(insert(0, "a\n"));
+        history.record(Change::Insert {
+            at: Location { x: 0, y: 1 },
+            text: "b".to_string(),
+        });
+        history.end_step();
+        history.record(Change::Insert {
+            at: Location { x: 1, y: 1 },
+            text: "c".to_string(),
+        });
+        assert_eq!(history.position(), (3, 3));
+    }
+
+    #[test]
+    fn deleting_merges_in_both_directions() {
+        let mut history = History::default();
+        // Backspace twice from 3, then Delete twice at 1.
+        for change in [
+            delete(2, "c"),
+            delete(1, "b"),
+            delete(1, "d"),
+            delete(1, "e"),
+        ] {
+            history.record(change);
+        }
+        assert_eq!(undo(&mut history), ["+1:bcde"]);
+    }
+
+    #[test]
+    fn groups_collect_changes_anywhere() {
+        let mut history = History::default();
+        history.start_group();
+        history.record(insert(0, "x"));
+        history.record(insert(5, "y"));
+        history.end_group();
+        history.record(insert(6, "z"));
+        assert_eq!(undo(&mut history), ["-6:z"]);
+        assert_eq!(undo(&mut history), ["-5:y", "-0:x"]);
+    }
+
+    #[test]
+    fn redo_follows_the_newest_branch() {
+        let mut history = History::default();
+        history.record(insert(0, "a"));
+        history.end_step();
+        history.record(insert(1, "b"));
+        assert_eq!(undo(&mut history), ["-1:b"]);
+        history.record(insert(1, "c"));
+        assert!(history.redo().is_none());
+        assert_eq!(undo(&mut history), ["-1:c"]);
+        assert_eq!(redo(&mut history), ["+1:c"]);
+        // Visiting b again makes it the branch redo follows.
+        history.earlier();
+        assert_eq!(undo(&mut history), ["-1:b"]);
+        assert_eq!(redo(&mut history), ["+1:b"]);
+    }
+
+    #[test]
+    fn time_travel_crosses_branches() {
+        let mut history = History::default();
+        history.record(insert(0, "a"));
+        history.end_step();
+        history.record(insert(1, "b"));
+        history.undo();
+        history.record(insert(1, "c"));
+        assert_eq!(history.position(), (3, 3));
+        let earlier = history.earlier().unwrap();
+        assert_eq!(describe(&earlier), ["-1:c", "+1:b"]);
+        assert_eq!(history.position(), (2, 3));
+        let later = history.later().unwrap();
+        assert_eq!(describe(&later), ["-1:b", "+1:c"]);
+        assert!(history.later().is_none());
+        assert_eq!(describe(&history.earlier().unwrap()), ["-1:c", "+1:b"]);
+        assert_eq!(describe(&history.earlier().unwrap()), ["-1:b"]);
+        assert_eq!(describe(&history.earlier().unwrap()), ["-0:a"]);
+        assert!(history.earlier().is_none());
+    }
+
+    #[test]
+    fn serialized_history_reads_back_the_same() {
+        let mut history = History::default();
+        history.record(insert(0, "line\\one\nline two\r"));
+        history.end_step();
+        history.record(delete(2, "ne"));
+        history.undo();
+        history.record(insert(4, "c"));
+        history.undo();
+        let text = history.serialize();
+        let mut read = History::deserialize(&text).unwrap();
+        assert_eq!(read.serialize(), text);
+        assert_eq!(read.position(), history.position());
+        assert_eq!(redo(&mut read), ["+4:c"]);
+        assert_eq!(undo(&mut read), ["-4:c"]);
+        assert_eq!(undo(&mut read), ["-0:line\\one\nline two\r"]);
+    }
+
+    #[test]
+    fn malformed_history_is_rejected() {
+        for text in [
+            "current 5\nroot -\n",
+            "current 0\nroot 1\n",
+            "current 0\nroot -\nnode 1 -\n",
+            "current 1\nroot 1\nnode 0 -\ni 0 0 bad\\escape\n",
+            "current 1\nroot 1\nnode 0 -\nx 0 0 text\n",
+        ] {
+            assert!(History::deserialize(text).is_none(), "{text}");
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use std::{
+    fs::File,
+    io::{Error, ErrorKind, Read, Seek, SeekFrom},
+    str::from_utf8,
+};
+
+use super::{line::Line, lineending::LineEnding, rope::Rope};
+
+/// How many bytes are scanned for line breaks at once.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Lines read on demand from a file too big to load up front.
+///
+/// The file is scanned sequentially for line breaks, only as far as the
+/// user has scrolled so far, keeping just the offsets of the lines. A line
+/// is read from the file when it is accessed. Lines are decoded as UTF-8
+/// without encoding detection, which would need to scan the whole file.
+pub struct LargeFile {
+    file: File,
+    file_len: u64,
+    /// Byte offsets of the starts of the lines indexed so far.
+    line_starts: Vec<u64>,
+    /// How many bytes have been scanned for line breaks.
+    scanned: u64,
+}
+
+impl LargeFile {
+    pub fn open(file_name: &str) -> Result<Self, Error> {
+        let file = File::open(file_name)?;
+        let file_len = file.metadata()?.len();
+        let line_starts = if file_len == 0 { Vec::new() } else { vec![0] };
+        Ok(Self {
+            file,
+            file_len,
+            line_starts,
+            scanned: 0,
+        })
+    }
+
+    /// Scans for line breaks until at least `line_count` lines are indexed
+    /// or the end of the file is reached.
+    pub fn index_lines(&mut self, line_count: usize) {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        while self.len() < line_count && !self.is_complete() {
+            let mut file = &self.file;
+            let read = file
+                .seek(SeekFrom::Start(self.scanned))
+                .and_then(|_| file.read(&mut chunk));
+            let read = match read {
+                Ok(read) if read > 0 => read,
+                // The file was truncated meanwhile, or can't be read any further.
+                _ => {
+                    self.file_len = self.scanned;
+                    break;
+                }
+            };
+            for (offset, _) in chunk
+                .iter()
+                .take(read)
+                .enumerate()
+                .filter(|&(_, &byte)| byte == b'\n')
+            {
+                let next_start = self
+                    .scanned
+                    .saturating_add(to_u64(offset))
+                    .saturating_add(1);
+                if next_start < self.file_len {
+                    self.line_starts.push(next_start);
+                }
+            }
+            self.scanned = self.scanned.saturating_add(to_u64(read));
+        }
+    }
+
+    /// The number of lines indexed so far: those whose end has been
+    /// found, or all of them once the whole file was scanned.
+    pub fn len(&self) -> usize {
+        if self.is_complete() {
+            self.line_starts.len()
+        } else {
+            self.line_starts.len().saturating_sub(1)
+        }
+    }
+
+    /// Reads the line at `index` from the file, without its line ending.
+    pub fn line(&self, index: usize) -> Option<Line> {
+        if index >= self.len() {
+            return None;
+        }
+        let start = *self.line_starts.get(index)?;
+        let end = self
+            .line_starts
+            .get(index.saturating_add(1))
+            .copied()
+            .unwrap_or(self.file_len);
+        let mut bytes = vec![0; usize::try_from(end.saturating_sub(start)).ok()?];
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(start)).ok()?;
+        file.read_exact(&mut bytes).ok()?;
+        let mut bytes = bytes.as_slice();
+        if let Some(stripped) = bytes.strip_suffix(b"\n") {
+            bytes = stripped;
+        }
+        if let Some(stripped) = bytes.strip_suffix(b"\r") {
+            bytes = stripped;
+        }
+        Some(Line::from(&String::from_utf8_lossy(bytes)))
+    }
+
+    /// Reads the whole file into lines, e.g. to edit it after all. It is
+    /// read a chunk at a time, and fails with `ErrorKind::InvalidData` if
+    /// it isn't valid UTF-8, since saving the decoded lines would then
+    /// change the bytes which aren't.
+    pub fn read_all(&self) -> Result<Rope<Line>, Error> {
+        let mut lines = Rope::default();
+        let mut chunk = vec![0; CHUNK_SIZE];
+        // The bytes of a character which was cut off by the end of a chunk.
+        let mut pending = Vec::new();
+        let mut partial_line = String::new();
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(0))?;
+        loop {
+            let read = file.read(&mut chunk)?;
+            if read == 0 {
+                break;
+            }
+            pending.extend_from_slice(chunk.get(..read).unwrap_or_default());
+            let valid_len = match from_utf8(&pending) {
+                Ok(text) => text.len(),
+                Err(err) if err.error_len().is_none() => err.valid_up_to(),
+                Err(_) => return Err(not_utf8()),
+            };
+            let text =
+                from_utf8(pending.get(..valid_len).unwrap_or_default()).map_err(|_| not_utf8())?;
+            let mut rest = text;
+            while let Some((line, after)) = rest.split_once('\n') {
+                partial_line.push_str(line);
+                let line = partial_line.strip_suffix('\r').unwrap_or(&partial_line);
+                lines.push(Line::from(line));
+                partial_line.clear();
+                rest = after;
+            }
+            partial_line.push_str(rest);
+            pending.drain(..valid_len);
+        }
+        if !pending.is_empty() {
+            return Err(not_utf8());
+        }
+        if !partial_line.is_empty() {
+            lines.push(Line::from(&partial_line));
+        }
+        Ok(lines)
+    }
+
+    /// How the file's lines are terminated, going by its first chunk.
+    pub fn line_ending(&self) -> LineEnding {
+        let mut chunk = Vec::new();
+        let mut file = &self.file;
+        let read = file
+            .seek(SeekFrom::Start(0))
+            .and_then(|_| file.take(to_u64(CHUNK_SIZE)).read_to_end(&mut chunk));
+        if read.is_err() {
+            return LineEnding::default();
+        }
+        LineEnding::detect(&String::from_utf8_lossy(&chunk))
+    }
+
+    /// Whether the file's last line isn't terminated.
+    pub fn missing_final_newline(&self) -> bool {
+        let mut last = [0];
+        let mut file = &self.file;
+        self.file_len > 0
+            && file
+                .seek(SeekFrom::End(-1))
+                .and_then(|_| file.read_exact(&mut last))
+                .is_ok_and(|()| last != *b"\n")
+    }
+
+    const fn is_complete(&self) -> bool {
+        self.scanned >= self.file_len
+    }
+
+    /// How much of the file has been indexed so far, in percent.
+    pub fn percent_read(&self) -> u64 {
+        if self.file_len == 0 {
+            return 100;
+        }
+        self.scanned
+            .saturating_mul(100)
+            .checked_div(self.file_len)
+            .unwrap_or(100)
+    }
+}
+
+fn not_utf8() -> Error {
+    Error::new(ErrorKind::InvalidData, "not valid UTF-8")
+}
+
+fn to_u64(value: usize) -> u64 {
+    u64::try_from(value).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs::write, io::ErrorKind};
+
+    use super::{LargeFile, CHUNK_SIZE};
+    use crate::editor::lineending::LineEnding;
+
+    /// Writes `bytes` to a file in the temporary directory named after the test.
+    fn file_with(name: &str, bytes: &[u8]) -> String {
+        let path = temp_dir().join(format!("hecto-largefile-{name}"));
+        write(&path, bytes).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn all_lines(path: &str) -> Vec<String> {
+        let lines = LargeFile::open(path).unwrap().read_all().unwrap();
+        (0..lines.len())
+            .map(|index| lines[index].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn indexes_lines_only_as_far_as_asked() {
+        let mut bytes = Vec::new();
+        for index in 0..20_000 {
+            bytes.extend_from_slice(format!("line {index}\r\n").as_bytes());
+        }
+        let path = file_with("index", &bytes);
+        let mut file = LargeFile::open(&path).unwrap();
+        assert_eq!(file.len(), 0);
+        file.index_lines(10);
+        // Whole chunks are scanned, but no further than needed.
+        assert!(file.len() >= 10 && file.len() < 20_000);
+        assert!(file.percent_read() < 100);
+        assert_eq!(file.line(9).unwrap().to_string(), "line 9");
+        assert!(file.line(file.len()).is_none());
+        file.index_lines(usize::MAX);
+        assert_eq!(file.len(), 20_000);
+        assert_eq!(file.percent_read(), 100);
+        assert_eq!(file.line(19_999).unwrap().to_string(), "line 19999");
+        assert_eq!(file.line_ending(), LineEnding::CrLf);
+        assert!(!file.missing_final_newline());
+    }
+
+    #[test]
+    fn last_line_may_be_unterminated() {
+        let path = file_with("unterminated", b"a\nb");
+        let mut file = LargeFile::open(&path).unwrap();
+        file.index_lines(usize::MAX);
+        assert_eq!(file.len(), 2);
+        assert_eq!(file.line(1).unwrap().to_string(), "b");
+        assert!(file.missing_final_newline());
+        assert_eq!(file.line_ending(), LineEnding::Lf);
+    }
+
+    #[test]
+    fn empty_file_has_no_lines() {
+        let path = file_with("empty", b"");
+        let mut file = LargeFile::open(&path).unwrap();
+        file.index_lines(usize::MAX);
+        assert_eq!(file.len(), 0);
+        assert_eq!(file.percent_read(), 100);
+        assert!(!file.missing_final_newline());
+    }
+
+    #[test]
+    fn read_all_splits_lines_like_loading() {
+        let path = file_with("lines", b"one\r\ntwo\n\nthree");
+        assert_eq!(all_lines(&path), ["one", "two", "", "three"]);
+    }
+
+    #[test]
+    fn read_all_joins_characters_cut_by_a_chunk() {
+        let mut bytes = vec![b'a'; CHUNK_SIZE.saturating_sub(1)];
+        bytes.extend_from_slice("é\nü".as_bytes());
+        let path = file_with("chunks", &bytes);
+        let lines = all_lines(&path);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("aé"));
+        assert_eq!(lines[1], "ü");
+    }
+
+    #[test]
+    fn read_all_rejects_invalid_utf8() {
+        for (name, bytes) in [("invalid", &b"ok\n\xff\n"[..]), ("truncated", b"ok\n\xc3")] {
+            let path = file_with(name, bytes);
+            let file = LargeFile::open(&path).unwrap();
+            let err = file.read_all().err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::{
+    fs::{create_dir_all, read_to_string, write},
+    io::Error,
+    path::{Path, PathBuf},
+};
+
+use super::{datadir::data_dir, location::Location};
+
+/// How many files the caret locations are remembered for.
+const MAX_FILES: usize = 500;
+
+/// Where the caret was in each file when hecto was last quit, most recent
+/// first, kept in `$XDG_DATA_HOME/hecto/locations` (or
+/// `~/.local/share/hecto/locations`). Each line holds the line and the
+/// character index, both counted from 0, and the absolute path, separated by tabs.
+pub fn load(file_name: &str) -> Option<Location> {
+    let path = absolute(file_name)?;
+    read_entries()
+        .into_iter()
+        .find(|(entry_path, _)| *entry_path == path)
+        .map(|(_, location)| location)
+}
+
+/// Remembers the caret `locations` of the files, keeping the entries of
+/// the others. Failing to write them isn't worth bothering the user about,
+/// so callers may ignore the error.
+pub fn store(locations: &[(&str, Location)]) -> Result<(), Error> {
+    let Some(path) = locations_file_path() else {
+        return Ok(());
+    };
+    let mut entries: Vec<(String, Location)> = locations
+        .iter()
+        .filter_map(|&(file_name, location)| Some((absolute(file_name)?, location)))
+        .collect();
+    if entries.is_empty() {
+        return Ok(());
+    }
+    // Other instances may have added entries since this one started.
+    let others: Vec<_> = read_entries()
+        .into_iter()
+        .filter(|(other, _)| entries.iter().all(|(path, _)| path != other))
+        .collect();
+    entries.extend(others);
+    entries.truncate(MAX_FILES);
+    if let Some(directory) = path.parent() {
+        create_dir_all(directory)?;
+    }
+    let mut contents = entries
+        .iter()
+        .map(|(path, location)| format!("{}\t{}\t{path}", location.y, location.x))
+        .collect::<Vec<_>>()
+        .join("\n");
+    contents.push('\n');
+    write(path, contents)
+}
+
+fn read_entries() -> Vec<(String, Location)> {
+    let Some(contents) = locations_file_path().and_then(|path| read_to_string(path).ok()) else {
+        return Vec::new();
+    };
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let y = fields.next()?.parse().ok()?;
+            let x = fields.next()?.parse().ok()?;
+            Some((fields.next()?.to_string(), Location { x, y }))
+        })
+        .collect()
+}
+
+fn absolute(file_name: &str) -> Option<String> {
+    Path::new(file_name)
+        .canonicalize()
+        .ok()?
+        .to_str()
+        .map(ToString::to_string)
+}
+
+fn locations_file_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("locations"))
+}
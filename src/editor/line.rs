@@ -0,0 +1,391 @@
+use std::{cell::OnceCell, cmp, fmt, iter::once_with, ops::Range};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use super::gapbuffer::GapBuffer;
+
+/// How a line keeps its text.
+#[derive(Clone)]
+enum Text {
+    /// Most lines: compact, but each insertion or removal shifts the rest
+    /// of the string and may reallocate it.
+    Compact(String),
+    /// The line being edited, so typing at the caret is cheap. Its text is
+    /// copied out of the gap buffer once after each edit, when it is read.
+    Editing {
+        gap_buffer: GapBuffer<char>,
+        string: OnceCell<String>,
+    },
+}
+
+#[derive(Clone)]
+pub struct Line {
+    text: Text,
+}
+
+impl Line {
+    pub fn from(line_str: &str) -> Self {
+        Self {
+            text: Text::Compact(String::from(line_str)),
+        }
+    }
+
+    /// Turns the gap buffer of a line which was edited back into a string.
+    /// Lines switch to a gap buffer by themselves when they are edited.
+    pub fn compact(&mut self) {
+        if let Text::Editing { gap_buffer, string } = &mut self.text {
+            let string = string.take().unwrap_or_else(|| gap_buffer.iter().collect());
+            self.text = Text::Compact(string);
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        match &self.text {
+            Text::Compact(string) => string,
+            Text::Editing { gap_buffer, string } => {
+                string.get_or_init(|| gap_buffer.iter().collect())
+            }
+        }
+    }
+
+    /// The text as a string, switching back from a gap buffer if needed.
+    fn string_mut(&mut self) -> &mut String {
+        self.compact();
+        match &mut self.text {
+            Text::Compact(string) => string,
+            Text::Editing { .. } => unreachable!("line was just compacted"),
+        }
+    }
+
+    /// The text as a gap buffer, for editing at a character index.
+    fn gap_buffer_mut(&mut self) -> &mut GapBuffer<char> {
+        if let Text::Compact(string) = &self.text {
+            self.text = Text::Editing {
+                gap_buffer: string.chars().collect(),
+                string: OnceCell::new(),
+            };
+        }
+        match &mut self.text {
+            Text::Editing { gap_buffer, string } => {
+                // The copy is out of date once the gap buffer is edited.
+                string.take();
+                gap_buffer
+            }
+            Text::Compact(_) => unreachable!("line was just turned into a gap buffer"),
+        }
+    }
+
+    /// The part of the line shown in the screen columns `range`, with tabs
+    /// expanded to the next multiple of `tab_width`. Wide characters which
+    /// are cut off at either edge are replaced by spaces.
+    pub fn get_visible(&self, range: Range<usize>, tab_width: usize) -> String {
+        let mut result = String::new();
+        let mut col = 0;
+        for grapheme in self.as_str().graphemes(true) {
+            if col >= range.end {
+                break;
+            }
+            let next_col = col.saturating_add(grapheme_width(grapheme, col, tab_width));
+            if grapheme == "\t" || col < range.start || next_col > range.end {
+                let visible =
+                    cmp::min(next_col, range.end).saturating_sub(cmp::max(col, range.start));
+                result.push_str(&" ".repeat(visible));
+            } else {
+                result.push_str(grapheme);
+            }
+            col = next_col;
+        }
+        result
+    }
+
+    /// The screen column at which the character at index `at` starts.
+    pub fn column_of(&self, at: usize, tab_width: usize) -> usize {
+        let mut index: usize = 0;
+        let mut col: usize = 0;
+        for grapheme in self.as_str().graphemes(true) {
+            if index >= at {
+                break;
+            }
+            index = index.saturating_add(grapheme.chars().count());
+            col = col.saturating_add(grapheme_width(grapheme, col, tab_width));
+        }
+        col
+    }
+
+    /// The screen columns at which the rows start when the line is wrapped
+    /// into rows `width` columns wide. A grapheme cluster which would cross
+    /// the edge of a row, like a wide character in its last column, starts
+    /// the next row instead.
+    pub fn row_starts(&self, width: usize, tab_width: usize) -> Vec<usize> {
+        let mut starts = vec![0];
+        let mut row_start: usize = 0;
+        let mut col: usize = 0;
+        for grapheme in self.as_str().graphemes(true) {
+            let next_col = col.saturating_add(grapheme_width(grapheme, col, tab_width));
+            if next_col > row_start.saturating_add(width) && col > row_start {
+                starts.push(col);
+                row_start = col;
+            }
+            col = next_col;
+        }
+        starts
+    }
+
+    /// The character index of the first grapheme cluster which starts at
+    /// or after the screen column `col`, or the length of the line if none does.
+    pub fn index_at_column(&self, col: usize, tab_width: usize) -> usize {
+        let mut index: usize = 0;
+        let mut current_col: usize = 0;
+        for grapheme in self.as_str().graphemes(true) {
+            if current_col >= col {
+                break;
+            }
+            index = index.saturating_add(grapheme.chars().count());
+            current_col =
+                current_col.saturating_add(grapheme_width(grapheme, current_col, tab_width));
+        }
+        index
+    }
+
+    /// The character index of the start of the grapheme cluster before
+    /// index `at`, i.e. where the caret goes when moving left from `at`.
+    pub fn previous_grapheme_boundary(&self, at: usize) -> usize {
+        self.grapheme_boundaries()
+            .take_while(|&boundary| boundary < at)
+            .last()
+            .unwrap_or(0)
+    }
+
+    /// The character index just past the grapheme cluster at index `at`.
+    pub fn next_grapheme_boundary(&self, at: usize) -> usize {
+        self.grapheme_boundaries()
+            .find(|&boundary| boundary > at)
+            .unwrap_or_else(|| self.len())
+    }
+
+    /// The character index of the start of the grapheme cluster which
+    /// contains the character at index `at`, so the caret never ends up
+    /// within a cluster.
+    pub fn grapheme_start(&self, at: usize) -> usize {
+        self.grapheme_boundaries()
+            .take_while(|&boundary| boundary <= at)
+            .last()
+            .unwrap_or(0)
+    }
+
+    /// The character indices at which grapheme clusters start, followed
+    /// by the length of the line.
+    fn grapheme_boundaries(&self) -> impl Iterator<Item = usize> + '_ {
+        let mut index: usize = 0;
+        self.as_str()
+            .graphemes(true)
+            .map(move |grapheme| {
+                let start = index;
+                index = index.saturating_add(grapheme.chars().count());
+                start
+            })
+            .chain(once_with(|| self.len()))
+    }
+
+    /// The start and end character index of the word which contains index
+    /// `at` or ends right before it. Words consist of letters, digits and `_`.
+    pub fn word_around(&self, at: usize) -> Option<Range<usize>> {
+        self.run_around(at, is_word_char)
+    }
+
+    /// Like `word_around`, but for a run of the characters `belongs` accepts.
+    pub fn run_around(&self, at: usize, belongs: impl Fn(char) -> bool) -> Option<Range<usize>> {
+        let chars: Vec<char> = self.as_str().chars().collect();
+        let is_word = |index: usize| chars.get(index).is_some_and(|&c| belongs(c));
+        let mut start = if is_word(at) {
+            at
+        } else {
+            at.checked_sub(1).filter(|&before| is_word(before))?
+        };
+        while start > 0 && is_word(start.saturating_sub(1)) {
+            start = start.saturating_sub(1);
+        }
+        let mut end = start;
+        while is_word(end) {
+            end = end.saturating_add(1);
+        }
+        Some(start..end)
+    }
+
+    /// The character index ranges of the words in the line, in order.
+    pub fn words(&self) -> Vec<Range<usize>> {
+        let mut words = Vec::new();
+        let mut start = None;
+        let mut index: usize = 0;
+        for character in self.as_str().chars() {
+            match (is_word_char(character), start) {
+                (true, None) => start = Some(index),
+                (false, Some(word_start)) => {
+                    words.push(word_start..index);
+                    start = None;
+                }
+                _ => {}
+            }
+            index = index.saturating_add(1);
+        }
+        if let Some(word_start) = start {
+            words.push(word_start..index);
+        }
+        words
+    }
+
+    /// The character index ranges of the words in the line according to
+    /// Unicode word segmentation, in order. Unlike `words`, a word may
+    /// contain apostrophes or periods, as in `can't` or `3.14`.
+    pub fn word_segments(&self) -> Vec<Range<usize>> {
+        let mut words = Vec::new();
+        let mut index: usize = 0;
+        for segment in self.as_str().split_word_bounds() {
+            let end = index.saturating_add(segment.chars().count());
+            if segment.chars().any(char::is_alphanumeric) {
+                words.push(index..end);
+            }
+            index = end;
+        }
+        words
+    }
+
+    /// The character index of the first occurrence of `word` as a whole
+    /// word at or after index `from`.
+    pub fn find_word(&self, word: &str, from: usize) -> Option<usize> {
+        let chars: Vec<char> = self.as_str().chars().collect();
+        let needle: Vec<char> = word.chars().collect();
+        let is_word = |index: Option<usize>| {
+            index
+                .and_then(|index| chars.get(index))
+                .is_some_and(|&c| is_word_char(c))
+        };
+        (from..chars.len()).find(|&start| {
+            let end = start.saturating_add(needle.len());
+            chars.get(start..end) == Some(&needle[..])
+                && !is_word(start.checked_sub(1))
+                && !is_word(Some(end))
+        })
+    }
+
+    /// Whether the line consists of whitespace only.
+    pub fn is_blank(&self) -> bool {
+        self.as_str().chars().all(char::is_whitespace)
+    }
+
+    /// The number of spaces and tabs the line starts with.
+    pub fn indentation_len(&self) -> usize {
+        self.as_str()
+            .chars()
+            .take_while(|&character| character == ' ' || character == '\t')
+            .count()
+    }
+
+    /// The characters from index `at` up to, but not including, index `end`.
+    pub fn chars_between(&self, at: usize, end: usize) -> String {
+        self.as_str()
+            .chars()
+            .skip(at)
+            .take(end.saturating_sub(at))
+            .collect()
+    }
+
+    /// Inserts `text` before the character at index `at`, or appends it
+    /// if `at` is at or beyond the end of the line.
+    pub fn insert_str(&mut self, text: &str, at: usize) {
+        let gap_buffer = self.gap_buffer_mut();
+        for (offset, character) in text.chars().enumerate() {
+            gap_buffer.insert(at.saturating_add(offset), character);
+        }
+    }
+
+    /// Removes the character at index `at`, if there is one.
+    pub fn remove(&mut self, at: usize) {
+        self.gap_buffer_mut().remove(at);
+    }
+
+    /// Cuts the line before the character at index `at` and returns the
+    /// part after it.
+    pub fn split_off(&mut self, at: usize) -> Self {
+        let string = self.string_mut();
+        let byte_index = string
+            .char_indices()
+            .nth(at)
+            .map_or(string.len(), |(index, _)| index);
+        Self {
+            text: Text::Compact(string.split_off(byte_index)),
+        }
+    }
+
+    /// Appends the contents of `other`, e.g. when joining two lines.
+    pub fn append(&mut self, other: &Self) {
+        self.string_mut().push_str(other.as_str());
+    }
+
+    /// Where the trailing spaces and tabs start, or the length if there are none.
+    pub fn trailing_whitespace_start(&self) -> usize {
+        self.as_str().trim_end_matches([' ', '\t']).chars().count()
+    }
+
+    /// The number of characters in the line.
+    pub fn len(&self) -> usize {
+        match &self.text {
+            Text::Compact(string) => string.chars().count(),
+            Text::Editing { gap_buffer, .. } => gap_buffer.len(),
+        }
+    }
+}
+
+/// Whether `character` belongs to a word: letters, digits and `_`.
+pub fn is_word_char(character: char) -> bool {
+    character.is_alphanumeric() || character == '_'
+}
+
+/// How many screen columns `grapheme` takes up when it starts at `col`.
+/// Tabs reach up to the next tab stop, CJK characters and most emoji take
+/// up two columns.
+fn grapheme_width(grapheme: &str, col: usize, tab_width: usize) -> usize {
+    if grapheme == "\t" {
+        tab_width.saturating_sub(col.checked_rem(tab_width).unwrap_or(0))
+    } else {
+        grapheme.width()
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Line;
+
+    #[test]
+    fn reads_see_each_edit() {
+        let mut line = Line::from("hllo wrld");
+        assert_eq!(line.next_grapheme_boundary(0), 1);
+        line.insert_str("e", 1);
+        assert_eq!(line.to_string(), "hello wrld");
+        line.insert_str("o", 7);
+        assert_eq!(line.to_string(), "hello world");
+        line.remove(0);
+        assert_eq!(line.chars_between(0, 4), "ello");
+        assert_eq!(line.len(), 10);
+        line.compact();
+        assert_eq!(line.to_string(), "ello world");
+    }
+
+    #[test]
+    fn grapheme_boundaries_skip_combining_marks() {
+        let mut line = Line::from("ae\u{301}b");
+        assert_eq!(line.next_grapheme_boundary(1), 3);
+        assert_eq!(line.previous_grapheme_boundary(3), 1);
+        assert_eq!(line.grapheme_start(2), 1);
+        line.insert_str("x", 0);
+        assert_eq!(line.next_grapheme_boundary(2), 4);
+        assert_eq!(line.next_grapheme_boundary(4), 5);
+        assert_eq!(line.previous_grapheme_boundary(0), 0);
+    }
+}
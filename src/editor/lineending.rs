@@ -0,0 +1,61 @@
+use std::fmt;
+
+/// The sequence which terminates lines in a file.
+#[derive(Default, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    /// Picks the ending used by the majority of lines in `text`,
+    /// preferring `\n` on a tie or if there are no line breaks at all.
+    pub fn detect(text: &str) -> Self {
+        let line_feeds = text.matches('\n').count();
+        let crlfs = text.matches("\r\n").count();
+        if crlfs > line_feeds.saturating_sub(crlfs) {
+            Self::CrLf
+        } else {
+            Self::Lf
+        }
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+        }
+    }
+}
+
+impl fmt::Display for LineEnding {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Lf => "LF",
+            Self::CrLf => "CRLF",
+        };
+        write!(formatter, "{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LineEnding;
+
+    #[test]
+    fn detects_the_majority_ending() {
+        assert_eq!(LineEnding::detect("a\r\nb\r\nc\n"), LineEnding::CrLf);
+        assert_eq!(LineEnding::detect("a\nb\nc\r\n"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("a\r\nb\n"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("no breaks"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("lone\rreturn"), LineEnding::Lf);
+    }
+
+    #[test]
+    fn names_and_sequences() {
+        assert_eq!(LineEnding::CrLf.as_str(), "\r\n");
+        assert_eq!(LineEnding::Lf.to_string(), "LF");
+        assert_eq!(LineEnding::CrLf.to_string(), "CRLF");
+    }
+}
@@ -0,0 +1,57 @@
+use super::{history::Change, terminal::Position};
+
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub struct Location {
+    pub x: usize,
+    pub y: usize,
+}
+
+impl From<Location> for Position {
+    fn from(loc: Location) -> Self {
+        Self {
+            col: loc.x,
+            row: loc.y,
+        }
+    }
+}
+
+impl Location {
+    pub const fn subtract(&self, other: &Self) -> Self {
+        Self {
+            x: self.x.saturating_sub(other.x),
+            y: self.y.saturating_sub(other.y),
+        }
+    }
+
+    /// Where this location, which lies at or after `old_end`, ends up after
+    /// the text up to `old_end` was changed so that it now ends at `new_end`.
+    pub const fn shifted(self, old_end: Self, new_end: Self) -> Self {
+        if self.y == old_end.y {
+            Self {
+                x: new_end.x.saturating_add(self.x.saturating_sub(old_end.x)),
+                y: new_end.y,
+            }
+        } else {
+            Self {
+                x: self.x,
+                y: self.y.saturating_add(new_end.y).saturating_sub(old_end.y),
+            }
+        }
+    }
+
+    /// Where this location ends up after `change` was applied to the text.
+    /// Locations within deleted text move to where it was.
+    pub fn adjusted_to(self, change: &Change) -> Self {
+        let key = |location: Self| (location.y, location.x);
+        match change {
+            Change::Insert { at, .. } if key(self) >= key(*at) => {
+                self.shifted(*at, change.text_end())
+            }
+            Change::Delete { at, .. } if key(self) >= key(change.text_end()) => {
+                self.shifted(change.text_end(), *at)
+            }
+            Change::Delete { at, .. } if key(self) > key(*at) => *at,
+            Change::Insert { .. } | Change::Delete { .. } => self,
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::{fs::File, io::Error};
+
+use memmap2::Mmap;
+
+use super::{line::Line, lineending::LineEnding};
+
+/// How many bytes at the start of the file its line ending is detected from.
+const DETECTION_LEN: usize = 64 * 1024;
+
+/// A read-only file mapped into memory.
+///
+/// Nothing is copied up front: line starts are indexed lazily as far as
+/// the user has scrolled, and a line is decoded from the map only when
+/// it is accessed.
+pub struct MappedFile {
+    /// Kept open to notice when another program truncates the file.
+    file: File,
+    map: Mmap,
+    /// Byte offsets of the starts of the lines indexed so far.
+    line_starts: Vec<usize>,
+    /// How many bytes have been scanned for line breaks.
+    scanned: usize,
+}
+
+impl MappedFile {
+    pub fn open(file_name: &str) -> Result<Self, Error> {
+        let file = File::open(file_name)?;
+        // SAFETY: The map is only ever read. hecto can't prevent other
+        // programs from truncating the file while it is mapped, so the map
+        // isn't read once the file got shorter, and the buffer is mapped
+        // again when the file watcher reports the change.
+        let map = unsafe { Mmap::map(&file)? };
+        let line_starts = if map.is_empty() { Vec::new() } else { vec![0] };
+        Ok(Self {
+            file,
+            map,
+            line_starts,
+            scanned: 0,
+        })
+    }
+
+    /// Scans for line breaks until at least `line_count` lines are indexed
+    /// or the end of the file is reached.
+    pub fn index_lines(&mut self, line_count: usize) {
+        if self.is_truncated() {
+            return;
+        }
+        let len = self.map.len();
+        while self.line_starts.len() < line_count && self.scanned < len {
+            let rest = self.map.get(self.scanned..).unwrap_or_default();
+            self.scanned = match rest.iter().position(|&byte| byte == b'\n') {
+                Some(offset) => {
+                    let next_start = self.scanned.saturating_add(offset).saturating_add(1);
+                    if next_start < len {
+                        self.line_starts.push(next_start);
+                    }
+                    next_start
+                }
+                None => len,
+            };
+        }
+    }
+
+    /// The number of lines indexed so far.
+    pub fn len(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    pub fn line(&self, index: usize) -> Option<Line> {
+        if self.is_truncated() {
+            return None;
+        }
+        let start = *self.line_starts.get(index)?;
+        let rest = self.map.get(start..)?;
+        let mut bytes = rest
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .and_then(|end| rest.get(..end))
+            .unwrap_or(rest);
+        if let Some(stripped) = bytes.strip_suffix(b"\r") {
+            bytes = stripped;
+        }
+        Some(Line::from(&String::from_utf8_lossy(bytes)))
+    }
+
+    /// How the file's lines are terminated, going by its start.
+    pub fn line_ending(&self) -> LineEnding {
+        let start = self.map.get(..DETECTION_LEN).unwrap_or(&self.map);
+        LineEnding::detect(&String::from_utf8_lossy(start))
+    }
+
+    /// Whether the file's last line isn't terminated.
+    pub fn missing_final_newline(&self) -> bool {
+        self.map.last().is_some_and(|&last| last != b'\n')
+    }
+
+    /// Whether the file is shorter than the map now, so reading the end of
+    /// the map would crash.
+    fn is_truncated(&self) -> bool {
+        self.file
+            .metadata()
+            .map_or(true, |metadata| metadata.len() < to_u64(self.map.len()))
+    }
+
+    /// How much of the file has been indexed so far, in percent.
+    pub fn percent_read(&self) -> u64 {
+        let len = self.map.len();
+        if len == 0 {
+            return 100;
+        }
+        self.scanned
+            .saturating_mul(100)
+            .checked_div(len)
+            .and_then(|percent| u64::try_from(percent).ok())
+            .unwrap_or(100)
+    }
+}
+
+fn to_u64(value: usize) -> u64 {
+    u64::try_from(value).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env::temp_dir,
+        fs::{write, OpenOptions},
+    };
+
+    use super::MappedFile;
+    use crate::editor::lineending::LineEnding;
+
+    fn file_with(name: &str, bytes: &[u8]) -> String {
+        let path = temp_dir().join(format!("hecto-mappedfile-{name}"));
+        write(&path, bytes).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn detects_line_ending_and_final_newline() {
+        let path = file_with("crlf", b"one\r\ntwo");
+        let mut mapped = MappedFile::open(&path).unwrap();
+        assert_eq!(mapped.line_ending(), LineEnding::CrLf);
+        assert!(mapped.missing_final_newline());
+        mapped.index_lines(usize::MAX);
+        assert_eq!(mapped.len(), 2);
+        assert_eq!(mapped.line(0).unwrap().to_string(), "one");
+        let path = file_with("lf", b"one\n");
+        let mapped = MappedFile::open(&path).unwrap();
+        assert_eq!(mapped.line_ending(), LineEnding::Lf);
+        assert!(!mapped.missing_final_newline());
+    }
+
+    #[test]
+    fn stops_reading_once_truncated() {
+        let path = file_with("truncated", &b"line\n".repeat(5000));
+        let mut mapped = MappedFile::open(&path).unwrap();
+        mapped.index_lines(10);
+        assert!(mapped.line(9).is_some());
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(0)
+            .unwrap();
+        assert!(mapped.line(9).is_none());
+        mapped.index_lines(usize::MAX);
+        assert!(mapped.line(0).is_none());
+    }
+}
@@ -0,0 +1,63 @@
+use std::time::{Duration, Instant};
+
+use super::terminal::{Size, Terminal};
+
+/// How long a message is shown if no key is pressed.
+const MESSAGE_DURATION: Duration = Duration::from_secs(5);
+
+/// The bottom row of the screen, used to tell the user what just happened.
+#[derive(Default)]
+pub struct MessageBar {
+    current_message: String,
+    /// When the current message was shown, to let it expire.
+    shown_at: Option<Instant>,
+    needs_redraw: bool,
+    width: usize,
+    position_y: usize,
+}
+
+impl MessageBar {
+    pub fn update_message(&mut self, new_message: &str) {
+        self.shown_at = Some(Instant::now());
+        if new_message != self.current_message {
+            self.current_message = new_message.to_string();
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Removes the current message, if any.
+    pub fn clear(&mut self) {
+        self.shown_at = None;
+        if !self.current_message.is_empty() {
+            self.current_message.clear();
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Removes the current message if it has been shown long enough.
+    pub fn expire(&mut self) {
+        if self
+            .shown_at
+            .is_some_and(|shown_at| shown_at.elapsed() >= MESSAGE_DURATION)
+        {
+            self.clear();
+        }
+    }
+
+    /// Places the bar on the last row of a terminal of the given size.
+    pub fn resize(&mut self, to: Size) {
+        self.width = to.width;
+        self.position_y = to.height.saturating_sub(1);
+        self.needs_redraw = true;
+    }
+
+    pub fn render(&mut self) {
+        if !self.needs_redraw || self.width == 0 {
+            return;
+        }
+        let message: String = self.current_message.chars().take(self.width).collect();
+        let result = Terminal::print_row(self.position_y, &message);
+        debug_assert!(result.is_ok(), "Failed to render message bar");
+        self.needs_redraw = false;
+    }
+}
@@ -0,0 +1,400 @@
+use std::{
+    fs::{read_dir, read_to_string},
+    path::{Path, PathBuf},
+};
+
+/// How many files are listed at most, so a huge tree doesn't take long.
+const MAX_FILES: usize = 50_000;
+const GITIGNORE_FILE_NAME: &str = ".gitignore";
+const GIT_DIR_NAME: &str = ".git";
+
+/// A line of a `.gitignore` file.
+struct Rule {
+    /// The directory of the `.gitignore` file, relative to the root, for
+    /// files in the root or below.
+    base: PathBuf,
+    /// The path from the directory of the file with the rule to the root,
+    /// for files above the root. Empty for those in the root or below.
+    root_offset: PathBuf,
+    pattern: Vec<char>,
+    /// `!pattern`: the path isn't ignored after all.
+    negated: bool,
+    /// `pattern/`: only directories are ignored.
+    dir_only: bool,
+    /// Patterns with a `/` other than at the end match the path from
+    /// `base`, others match the name alone.
+    anchored: bool,
+}
+
+impl Rule {
+    /// Parses a line of the `.gitignore` file in `base`. Returns `None` for
+    /// blank lines and comments.
+    fn parse(line: &str, base: &Path) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        if line.is_empty() {
+            return None;
+        }
+        Some(Self {
+            base: base.to_path_buf(),
+            root_offset: PathBuf::new(),
+            pattern: line.chars().collect(),
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+
+    /// Whether the rule applies to `path`, relative to the root.
+    fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let Ok(relative) = path.strip_prefix(&self.base) else {
+            return false;
+        };
+        let relative = self.root_offset.join(relative);
+        let text = if self.anchored {
+            relative.to_string_lossy()
+        } else {
+            relative
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_default()
+        };
+        let text: Vec<char> = text.chars().collect();
+        glob_matches(&self.pattern, &text)
+    }
+}
+
+/// The files under `root`, in order of their paths, which start with
+/// `root`. Hidden files and directories are left out, and so are those
+/// ignored by the `.gitignore` files in `root` and below, or in the
+/// directories above it within its Git repository, or by the repository's
+/// `.git/info/exclude`.
+pub fn project_files(root: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut rules = repository_rules(root);
+    list_directory(root, Path::new(""), &mut rules, &mut files);
+    files
+}
+
+/// The rules which apply to `root` from outside it, weakest first: those
+/// of the repository's `.git/info/exclude`, then those of the `.gitignore`
+/// files in the directories above `root`, from the repository's top down.
+/// None if `root` isn't within a Git repository.
+fn repository_rules(root: &Path) -> Vec<Rule> {
+    let Ok(root) = root.canonicalize() else {
+        return Vec::new();
+    };
+    let Some(top) = root
+        .ancestors()
+        .find(|directory| directory.join(GIT_DIR_NAME).exists())
+    else {
+        return Vec::new();
+    };
+    let mut rules = read_rules(
+        &top.join(GIT_DIR_NAME).join("info").join("exclude"),
+        top,
+        &root,
+    );
+    let above: Vec<&Path> = root
+        .ancestors()
+        .skip(1)
+        .take_while(|directory| directory.starts_with(top))
+        .collect();
+    for directory in above.into_iter().rev() {
+        rules.extend(read_rules(
+            &directory.join(GITIGNORE_FILE_NAME),
+            directory,
+            &root,
+        ));
+    }
+    rules
+}
+
+/// The rules in `file`, which apply to the paths relative to `directory`,
+/// a directory above `root` or `root` itself.
+fn read_rules(file: &Path, directory: &Path, root: &Path) -> Vec<Rule> {
+    let root_offset = root.strip_prefix(directory).unwrap_or(root);
+    read_to_string(file)
+        .map(|contents| {
+            contents
+                .lines()
+                .filter_map(|line| Rule::parse(line, Path::new("")))
+                .map(|rule| Rule {
+                    root_offset: root_offset.to_path_buf(),
+                    ..rule
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Adds the files in `root.join(relative)` and below to `files`, with
+/// `rules` holding those of the `.gitignore` files above it.
+fn list_directory(root: &Path, relative: &Path, rules: &mut Vec<Rule>, files: &mut Vec<PathBuf>) {
+    let directory = root.join(relative);
+    let Ok(entries) = read_dir(&directory) else {
+        return;
+    };
+    let rule_count = rules.len();
+    if let Ok(contents) = read_to_string(directory.join(GITIGNORE_FILE_NAME)) {
+        rules.extend(
+            contents
+                .lines()
+                .filter_map(|line| Rule::parse(line, relative)),
+        );
+    }
+    // Symbolic links to directories aren't followed, as they may form loops.
+    let mut entries: Vec<(PathBuf, bool)> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .map(|entry| {
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            (relative.join(entry.file_name()), is_dir)
+        })
+        .collect();
+    entries.sort();
+    for (path, is_dir) in entries {
+        if files.len() >= MAX_FILES {
+            break;
+        }
+        // The last rule which matches decides.
+        let ignored = rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(&path, is_dir))
+            .is_some_and(|rule| !rule.negated);
+        if ignored {
+            continue;
+        }
+        if is_dir {
+            list_directory(root, &path, rules, files);
+        } else {
+            files.push(root.join(path));
+        }
+    }
+    rules.truncate(rule_count);
+}
+
+/// A part of a glob pattern which matches one or more characters.
+enum Token {
+    Literal(char),
+    /// `?`: any character but `/`.
+    Any,
+    /// `*`: any characters but `/`.
+    Star,
+    /// `**`: any characters.
+    DoubleStar,
+    /// `[...]`: one of the characters or ranges listed, or with `[!...]`
+    /// one not listed.
+    Class {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+}
+
+impl Token {
+    /// Whether the token matches `character` on its own, for the tokens
+    /// which match a single character.
+    fn matches(&self, character: char) -> bool {
+        match self {
+            Self::Literal(literal) => *literal == character,
+            Self::Any => character != '/',
+            Self::Class { ranges, negated } => {
+                ranges
+                    .iter()
+                    .any(|(first, last)| (*first..=*last).contains(&character))
+                    != *negated
+            }
+            Self::Star | Self::DoubleStar => false,
+        }
+    }
+}
+
+/// Splits a glob pattern into its tokens. A `[` without a closing `]`
+/// stands for itself.
+fn tokenize(pattern: &[char]) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while let Some(&character) = pattern.get(index) {
+        index = index.saturating_add(1);
+        let token = match character {
+            '*' if pattern.get(index) == Some(&'*') => {
+                index = index.saturating_add(1);
+                Token::DoubleStar
+            }
+            '*' => Token::Star,
+            '?' => Token::Any,
+            '[' => {
+                let rest = pattern.get(index..).unwrap_or_default();
+                // A `]` right after the `[` is listed rather than closing it.
+                let Some(close) = rest
+                    .iter()
+                    .skip(1)
+                    .position(|&c| c == ']')
+                    .map(|close| close.saturating_add(1))
+                else {
+                    tokens.push(Token::Literal('['));
+                    continue;
+                };
+                let class = rest.get(..close).unwrap_or_default();
+                index = index.saturating_add(close).saturating_add(1);
+                let (negated, class) = match class.first() {
+                    Some('!' | '^') => (true, class.get(1..).unwrap_or_default()),
+                    _ => (false, class),
+                };
+                let mut ranges = Vec::new();
+                let mut at = 0;
+                while let Some(&first) = class.get(at) {
+                    if let (Some('-'), Some(&last)) = (
+                        class.get(at.saturating_add(1)),
+                        class.get(at.saturating_add(2)),
+                    ) {
+                        ranges.push((first, last));
+                        at = at.saturating_add(3);
+                    } else {
+                        ranges.push((first, first));
+                        at = at.saturating_add(1);
+                    }
+                }
+                Token::Class { ranges, negated }
+            }
+            literal => Token::Literal(literal),
+        };
+        tokens.push(token);
+    }
+    tokens
+}
+
+/// Whether `text` matches the glob `pattern`: `*` stands for any characters
+/// but `/`, `**` for any characters, `?` for one character but `/`, and
+/// `[...]` for one of the characters or ranges listed, or with `[!...]`
+/// for one not listed. `**/` also matches no directory at all.
+///
+/// Works out for each token and position in `text` whether the rest of
+/// the pattern matches the rest of the text, from the ends backwards, so
+/// it takes time proportional to the product of their lengths however
+/// many stars the pattern has.
+fn glob_matches(pattern: &[char], text: &[char]) -> bool {
+    let tokens = tokenize(pattern);
+    let width = text.len().saturating_add(1);
+    // `matched[i * width + j]`: whether `tokens[i..]` matches `text[j..]`.
+    let mut matched = vec![false; tokens.len().saturating_add(1).saturating_mul(width)];
+    let at = |token: usize, position: usize| token.saturating_mul(width).saturating_add(position);
+    matched[at(tokens.len(), text.len())] = true;
+    for (index, token) in tokens.iter().enumerate().rev() {
+        let next = index.saturating_add(1);
+        for position in (0..=text.len()).rev() {
+            let character = text.get(position).copied();
+            let after = position.saturating_add(1);
+            matched[at(index, position)] = match token {
+                Token::Star => {
+                    matched[at(next, position)]
+                        || character.is_some_and(|c| c != '/') && matched[at(index, after)]
+                }
+                Token::DoubleStar => {
+                    let skips_directory = matches!(tokens.get(next), Some(Token::Literal('/')))
+                        && matched[at(next.saturating_add(1), position)];
+                    skips_directory
+                        || matched[at(next, position)]
+                        || character.is_some() && matched[at(index, after)]
+                }
+                _ => character.is_some_and(|c| token.matches(c)) && matched[at(next, after)],
+            };
+        }
+    }
+    matched[at(0, 0)]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{glob_matches, Rule};
+
+    fn glob(pattern: &str, text: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        glob_matches(&pattern, &text)
+    }
+
+    /// Whether the last of `lines` which matches `path` ignores it, as in
+    /// a `.gitignore` file in the root.
+    fn ignored(lines: &[&str], path: &str, is_dir: bool) -> bool {
+        lines
+            .iter()
+            .filter_map(|line| Rule::parse(line, Path::new("")))
+            .rev()
+            .find(|rule| rule.matches(Path::new(path), is_dir))
+            .is_some_and(|rule| !rule.negated)
+    }
+
+    #[test]
+    fn star_stays_within_a_directory() {
+        assert!(glob("*.rs", "main.rs"));
+        assert!(!glob("*.rs", "src/main.rs"));
+        assert!(glob("src/*/mod.rs", "src/editor/mod.rs"));
+    }
+
+    #[test]
+    fn double_star_crosses_directories() {
+        assert!(glob("**/mod.rs", "mod.rs"));
+        assert!(glob("**/mod.rs", "src/editor/mod.rs"));
+        assert!(glob("src/**/mod.rs", "src/mod.rs"));
+        assert!(glob("src/**", "src/editor/view.rs"));
+        assert!(!glob("src/**/mod.rs", "lib/mod.rs"));
+    }
+
+    #[test]
+    fn question_marks_and_classes_match_one_character() {
+        assert!(glob("?.txt", "a.txt"));
+        assert!(!glob("?.txt", "ab.txt"));
+        assert!(glob("[a-c]x", "bx"));
+        assert!(!glob("[!a-c]x", "bx"));
+        assert!(glob("[x", "[x"));
+    }
+
+    #[test]
+    fn many_stars_dont_backtrack_exponentially() {
+        let pattern = "*a".repeat(30);
+        let text = "a".repeat(60) + "b";
+        assert!(!glob(&pattern, &text));
+        assert!(!glob(&"**a".repeat(30), &text));
+    }
+
+    #[test]
+    fn negation_unignores() {
+        let lines = ["*.log", "!keep.log"];
+        assert!(ignored(&lines, "debug.log", false));
+        assert!(!ignored(&lines, "keep.log", false));
+    }
+
+    #[test]
+    fn anchored_patterns_match_from_their_directory() {
+        assert!(ignored(&["/build"], "build", true));
+        assert!(!ignored(&["/build"], "src/build", true));
+        assert!(ignored(&["doc/*.html"], "doc/index.html", false));
+        assert!(!ignored(&["doc/*.html"], "src/doc/index.html", false));
+        assert!(ignored(&["build"], "src/build", true));
+    }
+
+    #[test]
+    fn directory_patterns_skip_files() {
+        assert!(ignored(&["target/"], "target", true));
+        assert!(!ignored(&["target/"], "target", false));
+    }
+}
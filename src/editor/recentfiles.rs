@@ -0,0 +1,72 @@
+use std::{
+    fs::{create_dir_all, read_to_string, write},
+    io::Error,
+    path::{Path, PathBuf},
+};
+
+use super::datadir::data_dir;
+
+/// How many files the list remembers.
+const MAX_RECENT_FILES: usize = 50;
+
+/// The files most recently opened or saved, newest first, kept in
+/// `$XDG_DATA_HOME/hecto/recent` (or `~/.local/share/hecto/recent`)
+/// with one absolute path per line.
+#[derive(Default)]
+pub struct RecentFiles {
+    paths: Vec<String>,
+}
+
+impl RecentFiles {
+    /// Loads the list, starting out empty if it is missing or unreadable.
+    pub fn load() -> Self {
+        let paths = recent_files_path()
+            .and_then(|path| read_to_string(path).ok())
+            .map(|contents| {
+                contents
+                    .lines()
+                    .filter(|line| !line.is_empty())
+                    .map(ToString::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { paths }
+    }
+
+    /// Moves `file_name` to the front of the list and writes the list back
+    /// to disk. Failing to write it isn't worth bothering the user about.
+    pub fn add(&mut self, file_name: &str) {
+        let Ok(path) = Path::new(file_name).canonicalize() else {
+            return;
+        };
+        let Some(path) = path.to_str().map(ToString::to_string) else {
+            return;
+        };
+        // Pick up files added by other instances before writing the list back.
+        *self = Self::load();
+        self.paths.retain(|existing| existing != &path);
+        self.paths.insert(0, path);
+        self.paths.truncate(MAX_RECENT_FILES);
+        let _ = self.store();
+    }
+
+    pub fn paths(&self) -> &[String] {
+        &self.paths
+    }
+
+    fn store(&self) -> Result<(), Error> {
+        let Some(path) = recent_files_path() else {
+            return Ok(());
+        };
+        if let Some(directory) = path.parent() {
+            create_dir_all(directory)?;
+        }
+        let mut contents = self.paths.join("\n");
+        contents.push('\n');
+        write(path, contents)
+    }
+}
+
+fn recent_files_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("recent"))
+}
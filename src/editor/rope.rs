@@ -0,0 +1,256 @@
+use std::{
+    cmp::Ordering,
+    ops::{Index, IndexMut},
+};
+
+type Link<T> = Option<Box<Node<T>>>;
+
+struct Node<T> {
+    value: T,
+    /// Nodes with a higher priority sit above those with a lower one,
+    /// which keeps the tree balanced on average.
+    priority: u32,
+    /// The number of values in this subtree.
+    size: usize,
+    left: Link<T>,
+    right: Link<T>,
+}
+
+/// A sequence of values, such as the lines of a buffer, stored as a
+/// balanced tree instead of one contiguous vector. Getting, inserting and
+/// removing at any index takes O(log n), so editing in the middle of a huge
+/// file doesn't shift all the lines after it.
+///
+/// The tree is a treap ordered by index: each node also carries a random
+/// priority, and the tree is a heap with respect to the priorities.
+pub struct Rope<T> {
+    root: Link<T>,
+    /// The state of the generator for the nodes' priorities.
+    seed: u32,
+}
+
+impl<T> Default for Rope<T> {
+    fn default() -> Self {
+        Self {
+            root: None,
+            seed: 0x9E37_79B9,
+        }
+    }
+}
+
+impl<T> Rope<T> {
+    pub fn len(&self) -> usize {
+        size(self.root.as_deref())
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        let mut node = self.root.as_deref()?;
+        let mut index = index;
+        loop {
+            let left_size = size(node.left.as_deref());
+            match index.cmp(&left_size) {
+                Ordering::Less => node = node.left.as_deref()?,
+                Ordering::Equal => return Some(&node.value),
+                Ordering::Greater => {
+                    index = index.saturating_sub(left_size).saturating_sub(1);
+                    node = node.right.as_deref()?;
+                }
+            }
+        }
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        let mut node = self.root.as_deref_mut()?;
+        let mut index = index;
+        loop {
+            let left_size = size(node.left.as_deref());
+            match index.cmp(&left_size) {
+                Ordering::Less => node = node.left.as_deref_mut()?,
+                Ordering::Equal => return Some(&mut node.value),
+                Ordering::Greater => {
+                    index = index.saturating_sub(left_size).saturating_sub(1);
+                    node = node.right.as_deref_mut()?;
+                }
+            }
+        }
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.get(self.len().checked_sub(1)?)
+    }
+
+    /// Inserts `value` at `index`, moving all values after it up by one.
+    /// Values inserted beyond the end are appended.
+    pub fn insert(&mut self, index: usize, value: T) {
+        let node = Box::new(Node {
+            value,
+            priority: self.next_priority(),
+            size: 1,
+            left: None,
+            right: None,
+        });
+        let (left, right) = split(self.root.take(), index);
+        self.root = merge(merge(left, Some(node)), right);
+    }
+
+    pub fn push(&mut self, value: T) {
+        self.insert(self.len(), value);
+    }
+
+    /// Removes and returns the value at `index`, if there is one.
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        let (left, rest) = split(self.root.take(), index);
+        let (removed, right) = split(rest, 1);
+        self.root = merge(left, right);
+        removed.map(|node| node.value)
+    }
+
+    /// A xorshift generator is plenty for keeping the tree balanced.
+    fn next_priority(&mut self) -> u32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed
+    }
+}
+
+impl<T> FromIterator<T> for Rope<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(values: I) -> Self {
+        let mut rope = Self::default();
+        for value in values {
+            rope.push(value);
+        }
+        rope
+    }
+}
+
+impl<T> Index<usize> for Rope<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        self.get(index).expect("Rope index out of bounds")
+    }
+}
+
+impl<T> IndexMut<usize> for Rope<T> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        self.get_mut(index).expect("Rope index out of bounds")
+    }
+}
+
+fn size<T>(node: Option<&Node<T>>) -> usize {
+    node.map_or(0, |node| node.size)
+}
+
+fn update_size<T>(node: &mut Node<T>) {
+    node.size = size(node.left.as_deref())
+        .saturating_add(size(node.right.as_deref()))
+        .saturating_add(1);
+}
+
+/// Splits the tree into the first `index` values and the rest.
+fn split<T>(link: Link<T>, index: usize) -> (Link<T>, Link<T>) {
+    let Some(mut node) = link else {
+        return (None, None);
+    };
+    let left_size = size(node.left.as_deref());
+    if index <= left_size {
+        let (left, right) = split(node.left.take(), index);
+        node.left = right;
+        update_size(&mut node);
+        (left, Some(node))
+    } else {
+        let (left, right) = split(
+            node.right.take(),
+            index.saturating_sub(left_size).saturating_sub(1),
+        );
+        node.right = left;
+        update_size(&mut node);
+        (Some(node), right)
+    }
+}
+
+/// Joins two trees, with all values of `left` coming before those of `right`.
+fn merge<T>(left: Link<T>, right: Link<T>) -> Link<T> {
+    match (left, right) {
+        (None, right) => right,
+        (left, None) => left,
+        (Some(mut left), Some(mut right)) => {
+            if left.priority > right.priority {
+                left.right = merge(left.right.take(), Some(right));
+                update_size(&mut left);
+                Some(left)
+            } else {
+                right.left = merge(Some(left), right.left.take());
+                update_size(&mut right);
+                Some(right)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rope;
+
+    fn contents(rope: &Rope<usize>) -> Vec<usize> {
+        (0..rope.len()).map(|index| rope[index]).collect()
+    }
+
+    #[test]
+    fn matches_a_vec_through_random_edits() {
+        let mut rope = Rope::default();
+        let mut expected = Vec::new();
+        let mut seed: usize = 12345;
+        for value in 0..2000 {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            let index = seed.checked_rem(expected.len().saturating_add(1)).unwrap();
+            if seed.checked_rem(3) == Some(0) && index < expected.len() {
+                assert_eq!(rope.remove(index), Some(expected.remove(index)));
+            } else {
+                rope.insert(index, value);
+                expected.insert(index, value);
+            }
+            assert_eq!(rope.len(), expected.len());
+        }
+        assert_eq!(contents(&rope), expected);
+        for (index, value) in expected.iter_mut().enumerate() {
+            *rope.get_mut(index).unwrap() = index;
+            *value = index;
+        }
+        assert_eq!(contents(&rope), expected);
+    }
+
+    #[test]
+    fn handles_indices_at_the_ends() {
+        let mut rope: Rope<usize> = (0..5).collect();
+        assert_eq!(rope.get(4), Some(&4));
+        assert_eq!(rope.get(5), None);
+        assert_eq!(rope.last(), Some(&4));
+        assert_eq!(rope.remove(5), None);
+        rope.insert(99, 5);
+        rope.insert(0, 9);
+        assert_eq!(contents(&rope), [9, 0, 1, 2, 3, 4, 5]);
+        assert_eq!(rope.remove(0), Some(9));
+        assert_eq!(rope.remove(5), Some(5));
+        assert_eq!(contents(&rope), [0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn empty_rope_has_nothing_to_get_or_remove() {
+        let mut rope: Rope<usize> = Rope::default();
+        assert_eq!(rope.len(), 0);
+        assert_eq!(rope.get(0), None);
+        assert_eq!(rope.last(), None);
+        assert_eq!(rope.remove(0), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Rope index out of bounds")]
+    fn indexing_past_the_end_panics() {
+        let rope: Rope<usize> = (0..3).collect();
+        let _ = rope[3];
+    }
+}
@@ -0,0 +1,34 @@
+use std::fmt;
+
+/// What the save-time transforms changed in a buffer, reported to the
+/// user after saving.
+#[derive(Default, Clone, Copy)]
+pub struct SaveReport {
+    /// How many lines had trailing whitespace stripped.
+    pub stripped_lines: usize,
+    /// Whether a line ending was added after the last line.
+    pub added_final_newline: bool,
+}
+
+impl SaveReport {
+    /// Whether any line was changed, so the view has to be redrawn.
+    pub const fn changed_lines(self) -> bool {
+        self.stripped_lines > 0
+    }
+}
+
+impl fmt::Display for SaveReport {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        if self.stripped_lines > 0 {
+            write!(
+                formatter,
+                " Stripped trailing whitespace from {} lines.",
+                self.stripped_lines
+            )?;
+        }
+        if self.added_final_newline {
+            write!(formatter, " Added final newline.")?;
+        }
+        Ok(())
+    }
+}
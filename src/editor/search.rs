@@ -0,0 +1,512 @@
+use std::{cmp::min, ops::Range};
+
+use regex::{Regex, RegexBuilder};
+
+use super::{
+    buffer::Buffer, editorcommand::SearchDirection, line::is_word_char, location::Location,
+};
+
+/// How the text searched for is matched.
+#[derive(Clone, Copy, Default)]
+pub struct SearchOptions {
+    /// Whether the text is a regular expression rather than literal text.
+    pub regex: bool,
+    pub case: CaseMode,
+    /// Whether matches must start and end at word boundaries.
+    pub whole_word: bool,
+}
+
+/// Whether upper and lower case letters match each other.
+#[derive(Clone, Copy, Default)]
+pub enum CaseMode {
+    #[default]
+    Sensitive,
+    Insensitive,
+    /// Insensitive unless the text searched for has an upper-case letter.
+    Smart,
+}
+
+impl CaseMode {
+    /// The mode after this one when switching through them.
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Sensitive => Self::Insensitive,
+            Self::Insensitive => Self::Smart,
+            Self::Smart => Self::Sensitive,
+        }
+    }
+}
+
+/// The text searched for, prepared for matching.
+#[derive(Clone)]
+pub struct Pattern {
+    text: String,
+    /// Set for regular expressions, and for literal text matched regardless
+    /// of case or as whole words, which is escaped into one.
+    regex: Option<Regex>,
+    /// Whether `text` is a regular expression, so replacements may refer
+    /// to its groups.
+    is_regex: bool,
+}
+
+impl Pattern {
+    /// Fails if `options` ask for a regular expression and `text` isn't one.
+    pub fn new(text: &str, options: SearchOptions) -> Result<Self, regex::Error> {
+        let ignore_case = match options.case {
+            CaseMode::Sensitive => false,
+            CaseMode::Insensitive => true,
+            CaseMode::Smart => !text.chars().any(char::is_uppercase),
+        };
+        let regex = if options.regex || ignore_case || options.whole_word {
+            let mut source = if options.regex {
+                text.to_string()
+            } else {
+                regex::escape(text)
+            };
+            if options.whole_word {
+                // Literal text only needs a boundary where it starts or ends
+                // with a word character, e.g. `foo(` matches before any text.
+                let at_word =
+                    |character: Option<char>| options.regex || character.is_some_and(is_word_char);
+                let before = if at_word(text.chars().next()) {
+                    r"\b"
+                } else {
+                    ""
+                };
+                let after = if at_word(text.chars().last()) {
+                    r"\b"
+                } else {
+                    ""
+                };
+                source = format!("{before}(?:{source}){after}");
+            }
+            Some(
+                RegexBuilder::new(&source)
+                    .case_insensitive(ignore_case)
+                    .build()?,
+            )
+        } else {
+            None
+        };
+        Ok(Self {
+            text: text.to_string(),
+            regex,
+            is_regex: options.regex,
+        })
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The first match in `haystack` starting at or after byte `start`, as
+    /// the byte where it starts and the byte range to highlight: for a
+    /// regular expression with groups, that of the first group taking part
+    /// in the match, otherwise the whole match. Regular expressions still
+    /// see the text before `start`, e.g. for word boundaries.
+    fn find_at(&self, haystack: &str, start: usize) -> Option<(usize, Range<usize>)> {
+        match &self.regex {
+            Some(regex) if regex.captures_len() > 1 => {
+                let captures = regex.captures_at(haystack, start)?;
+                let whole = captures.get(0)?;
+                let extent = captures.iter().skip(1).flatten().next().unwrap_or(whole);
+                Some((whole.start(), extent.range()))
+            }
+            Some(regex) => regex
+                .find_at(haystack, start)
+                .map(|found| (found.start(), found.range())),
+            None => haystack[start..].find(&self.text).map(|offset| {
+                let match_start = start.saturating_add(offset);
+                (
+                    match_start,
+                    match_start..match_start.saturating_add(self.text.len()),
+                )
+            }),
+        }
+    }
+}
+
+/// A match of a pattern in a buffer.
+#[derive(Clone, Copy)]
+pub struct Match {
+    /// Where the whole match starts, which is where the caret goes.
+    pub start: Location,
+    /// The start and end of the part of the match which is highlighted.
+    pub extent: (Location, Location),
+}
+
+/// The nearest match of `pattern` in `direction`: towards the end, the
+/// first one starting at or after `from`; towards the start, the last one
+/// starting before it. With a `region`, only matches whose extent lies
+/// within it count. Matches don't span lines. In large-file mode, only the
+/// lines read so far are searched.
+pub fn find(
+    buffer: &Buffer,
+    pattern: &Pattern,
+    from: Location,
+    direction: SearchDirection,
+    region: Option<(Location, Location)>,
+) -> Option<Match> {
+    if pattern.text.is_empty() {
+        return None;
+    }
+    let in_line = |y: usize| {
+        let line = buffer.line(y)?.to_string();
+        let inside = |(_, extent): &(usize, Range<usize>)| {
+            region.is_none_or(|(start, end)| {
+                (y > start.y || extent.start >= start.x) && (y < end.y || extent.end <= end.x)
+            })
+        };
+        let mut matches = matches(&line, pattern).filter(inside);
+        let (start, extent) = match direction {
+            SearchDirection::Forward => {
+                let skip = if y == from.y { from.x } else { 0 };
+                matches.find(|&(start, _)| start >= skip)
+            }
+            SearchDirection::Backward => {
+                let before = if y == from.y { from.x } else { usize::MAX };
+                matches.take_while(|&(start, _)| start < before).last()
+            }
+        }?;
+        Some(Match {
+            start: Location { x: start, y },
+            extent: (
+                Location { x: extent.start, y },
+                Location { x: extent.end, y },
+            ),
+        })
+    };
+    let (first, last) = region.map_or((0, buffer.height()), |(start, end)| {
+        (start.y, min(end.y.saturating_add(1), buffer.height()))
+    });
+    match direction {
+        SearchDirection::Forward => (from.y.max(first)..last).find_map(in_line),
+        SearchDirection::Backward => (first..=from.y.min(last.saturating_sub(1)))
+            .rev()
+            .find_map(in_line),
+    }
+}
+
+/// Like `find`, but when there is no match before the end of the buffer, or
+/// of the `region`, in `direction`, goes on from the other end. Returns the
+/// match and whether the search wrapped around to find it.
+pub fn find_wrapping(
+    buffer: &Buffer,
+    pattern: &Pattern,
+    from: Location,
+    direction: SearchDirection,
+    region: Option<(Location, Location)>,
+) -> Option<(Match, bool)> {
+    if let Some(found) = find(buffer, pattern, from, direction, region) {
+        return Some((found, false));
+    }
+    let other_end = match (direction, region) {
+        (SearchDirection::Forward, Some((start, _))) => start,
+        (SearchDirection::Forward, None) => Location::default(),
+        (SearchDirection::Backward, Some((_, end))) => end,
+        (SearchDirection::Backward, None) => Location {
+            x: usize::MAX,
+            y: buffer.height().saturating_sub(1),
+        },
+    };
+    find(buffer, pattern, other_end, direction, region).map(|found| (found, true))
+}
+
+/// How many matches of `pattern` there are in `buffer`, counted as `find`
+/// goes through them, and the number of the one starting at `at`, counted
+/// from 1, if there is one. In large-file mode, only the lines read so far
+/// are counted.
+pub fn count(buffer: &Buffer, pattern: &Pattern, at: Location) -> (usize, Option<usize>) {
+    let mut total: usize = 0;
+    let mut current = None;
+    if pattern.text.is_empty() {
+        return (total, current);
+    }
+    for y in 0..buffer.height() {
+        let Some(line) = buffer.line(y) else {
+            break;
+        };
+        for (start, _) in matches(&line.to_string(), pattern) {
+            total = total.saturating_add(1);
+            if y == at.y && start == at.x {
+                current = Some(total);
+            }
+        }
+    }
+    (total, current)
+}
+
+/// Replaces the matches of `pattern` in `text`, which doesn't span lines,
+/// with `replacement`. For a regular expression, `$1` or `${name}` in
+/// `replacement` stand for what a group matched, and `$$` for a `$`.
+/// Returns the new text and how many matches were replaced.
+pub fn replace_all(text: &str, pattern: &Pattern, replacement: &str) -> (String, usize) {
+    if pattern.text.is_empty() {
+        return (text.to_string(), 0);
+    }
+    let Some(regex) = &pattern.regex else {
+        let count = text.matches(&pattern.text).count();
+        return (text.replace(&pattern.text, replacement), count);
+    };
+    let mut replaced = String::new();
+    let mut count: usize = 0;
+    let mut after_last = 0;
+    for captures in regex.captures_iter(text) {
+        let Some(whole) = captures.get(0) else {
+            continue;
+        };
+        replaced.push_str(&text[after_last..whole.start()]);
+        if pattern.is_regex {
+            captures.expand(replacement, &mut replaced);
+        } else {
+            replaced.push_str(replacement);
+        }
+        after_last = whole.end();
+        count = count.saturating_add(1);
+    }
+    replaced.push_str(&text[after_last..]);
+    (replaced, count)
+}
+
+/// The whole match of `pattern` in `text` which starts at character index
+/// `start`, as a character range, and what `replacement` expands to for
+/// it, the same way as in `replace_all`.
+pub fn replacement_at(
+    text: &str,
+    pattern: &Pattern,
+    start: usize,
+    replacement: &str,
+) -> Option<(Range<usize>, String)> {
+    let byte_start = text
+        .char_indices()
+        .nth(start)
+        .map_or(text.len(), |(index, _)| index);
+    let Some(regex) = &pattern.regex else {
+        if !text[byte_start..].starts_with(&pattern.text) {
+            return None;
+        }
+        let end = start.saturating_add(pattern.text.chars().count());
+        return Some((start..end, replacement.to_string()));
+    };
+    let captures = regex.captures_at(text, byte_start)?;
+    let whole = captures
+        .get(0)
+        .filter(|whole| whole.start() == byte_start)?;
+    let mut expanded = String::new();
+    if pattern.is_regex {
+        captures.expand(replacement, &mut expanded);
+    } else {
+        expanded.push_str(replacement);
+    }
+    Some((start..char_index(text, whole.end()), expanded))
+}
+
+/// The extents of the matches of `pattern` in `text` as character ranges,
+/// leaving out empty ones and those overlapping an earlier one, e.g. to
+/// highlight them all.
+pub fn find_all(text: &str, pattern: &Pattern) -> Vec<Range<usize>> {
+    let mut found: Vec<Range<usize>> = Vec::new();
+    if pattern.text.is_empty() {
+        return found;
+    }
+    for (_, extent) in matches(text, pattern) {
+        let after_last = found.last().map_or(0, |last| last.end);
+        if !extent.is_empty() && extent.start >= after_last {
+            found.push(extent);
+        }
+    }
+    found
+}
+
+/// The matches of `pattern` in `text` as the character index where each
+/// starts and the character range of its extent, in order of where the
+/// matches start, including overlapping ones.
+fn matches<'a>(
+    text: &'a str,
+    pattern: &'a Pattern,
+) -> impl Iterator<Item = (usize, Range<usize>)> + 'a {
+    let mut start = 0;
+    std::iter::from_fn(move || {
+        if start > text.len() {
+            return None;
+        }
+        let (match_start, extent) = pattern.find_at(text, start)?;
+        // The next match may start right after this one's first character.
+        start = text[match_start..]
+            .chars()
+            .next()
+            .map_or(text.len().saturating_add(1), |first| {
+                match_start.saturating_add(first.len_utf8())
+            });
+        Some((
+            char_index(text, match_start),
+            char_index(text, extent.start)..char_index(text, extent.end),
+        ))
+    })
+}
+
+/// The character index of byte `byte_index` in `text`.
+fn char_index(text: &str, byte_index: usize) -> usize {
+    text[..byte_index].chars().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        count, find, find_all, find_wrapping, replace_all, replacement_at, CaseMode, Pattern,
+        SearchOptions,
+    };
+    use crate::editor::{buffer::Buffer, editorcommand::SearchDirection, location::Location};
+
+    fn literal(text: &str) -> Pattern {
+        Pattern::new(text, SearchOptions::default()).unwrap()
+    }
+
+    fn regex(text: &str) -> Pattern {
+        let options = SearchOptions {
+            regex: true,
+            ..SearchOptions::default()
+        };
+        Pattern::new(text, options).unwrap()
+    }
+
+    fn buffer(text: &str) -> Buffer {
+        Buffer::from_bytes(text.as_bytes().to_vec())
+    }
+
+    /// Where the match found from `(x, y)` in `direction` starts.
+    fn found_at(
+        buffer: &Buffer,
+        pattern: &Pattern,
+        (x, y): (usize, usize),
+        direction: SearchDirection,
+    ) -> Option<(usize, usize)> {
+        find(buffer, pattern, Location { x, y }, direction, None)
+            .map(|found| (found.start.x, found.start.y))
+    }
+
+    /// How many matches of `pattern` there are in `text` with the options.
+    fn count_in(text: &str, pattern: &str, case: CaseMode, whole_word: bool) -> usize {
+        let options = SearchOptions {
+            case,
+            whole_word,
+            ..SearchOptions::default()
+        };
+        find_all(text, &Pattern::new(pattern, options).unwrap()).len()
+    }
+
+    #[test]
+    fn case_modes_and_whole_words() {
+        let text = "Foo foo food";
+        assert_eq!(count_in(text, "foo", CaseMode::Sensitive, false), 2);
+        assert_eq!(count_in(text, "foo", CaseMode::Insensitive, false), 3);
+        assert_eq!(count_in(text, "foo", CaseMode::Smart, false), 3);
+        assert_eq!(count_in(text, "Foo", CaseMode::Smart, false), 1);
+        assert_eq!(count_in(text, "foo", CaseMode::Insensitive, true), 2);
+        assert_eq!(count_in("f(x) f(", "f(", CaseMode::Sensitive, true), 2);
+        let options = SearchOptions {
+            regex: true,
+            ..SearchOptions::default()
+        };
+        assert!(Pattern::new("(", options).is_err());
+    }
+
+    #[test]
+    fn finds_forward_and_backward_across_lines() {
+        let buffer = buffer("one two\ntwo one\n");
+        let pattern = literal("two");
+        assert_eq!(
+            found_at(&buffer, &pattern, (0, 0), SearchDirection::Forward),
+            Some((4, 0))
+        );
+        assert_eq!(
+            found_at(&buffer, &pattern, (5, 0), SearchDirection::Forward),
+            Some((0, 1))
+        );
+        assert_eq!(
+            found_at(&buffer, &pattern, (0, 1), SearchDirection::Backward),
+            Some((4, 0))
+        );
+        assert_eq!(
+            found_at(&buffer, &pattern, (1, 1), SearchDirection::Forward),
+            None
+        );
+        let (found, wrapped) = find_wrapping(
+            &buffer,
+            &pattern,
+            Location { x: 1, y: 1 },
+            SearchDirection::Forward,
+            None,
+        )
+        .unwrap();
+        assert_eq!((found.start.x, found.start.y, wrapped), (4, 0, true));
+    }
+
+    #[test]
+    fn regex_group_is_highlighted_but_the_caret_goes_to_the_match() {
+        let buffer = buffer("let x = 1;\n");
+        let found = find(
+            &buffer,
+            &regex(r"= (\d)"),
+            Location::default(),
+            SearchDirection::Forward,
+            None,
+        )
+        .unwrap();
+        assert_eq!(found.start.x, 6);
+        assert_eq!((found.extent.0.x, found.extent.1.x), (8, 9));
+    }
+
+    #[test]
+    fn counts_overlapping_matches_and_the_current_one() {
+        let buffer = buffer("aaaa\naa\n");
+        let pattern = literal("aa");
+        assert_eq!(
+            count(&buffer, &pattern, Location { x: 2, y: 0 }),
+            (4, Some(3))
+        );
+        assert_eq!(count(&buffer, &pattern, Location { x: 1, y: 1 }), (4, None));
+        assert_eq!(count(&buffer, &literal(""), Location::default()), (0, None));
+    }
+
+    #[test]
+    fn replaces_all_matches() {
+        assert_eq!(
+            replace_all("a.b.c", &literal("."), "-"),
+            ("a-b-c".to_string(), 2)
+        );
+        assert_eq!(
+            replace_all("x=1, y=22", &regex(r"(\w)=(\d+)"), "$2=$1"),
+            ("1=x, 22=y".to_string(), 2)
+        );
+        // Literal text searched regardless of case is escaped into a regex,
+        // but `$` in its replacement still stands for itself.
+        let options = SearchOptions {
+            case: CaseMode::Insensitive,
+            ..SearchOptions::default()
+        };
+        let pattern = Pattern::new("A", options).unwrap();
+        assert_eq!(replace_all("a A", &pattern, "$1"), ("$1 $1".to_string(), 2));
+    }
+
+    #[test]
+    fn replacement_at_expands_the_match_starting_there() {
+        let pattern = regex(r"f(o+)");
+        assert_eq!(
+            replacement_at("é foo fooo", &pattern, 6, "b$1"),
+            Some((6..10, "booo".to_string()))
+        );
+        assert_eq!(replacement_at("é foo fooo", &pattern, 3, "b$1"), None);
+        assert_eq!(
+            replacement_at("é foo", &literal("foo"), 2, "$1"),
+            Some((2..5, "$1".to_string()))
+        );
+    }
+
+    #[test]
+    fn find_all_skips_overlapping_and_empty_matches() {
+        assert_eq!(find_all("aaaaa", &literal("aa")), [0..2, 2..4]);
+        assert!(find_all("abc", &regex("x*")).is_empty());
+        assert_eq!(find_all("héllo héllo", &literal("llo")), [2..5, 8..11]);
+    }
+}
@@ -0,0 +1,69 @@
+use std::{
+    fs::{create_dir_all, read_to_string, write},
+    io::Error,
+    path::PathBuf,
+};
+
+use super::datadir::data_dir;
+
+/// How many queries the history remembers.
+const MAX_QUERIES: usize = 100;
+
+/// The texts most recently searched for, newest first, kept in
+/// `$XDG_DATA_HOME/hecto/searches` (or `~/.local/share/hecto/searches`)
+/// with one query per line.
+#[derive(Default)]
+pub struct SearchHistory {
+    queries: Vec<String>,
+}
+
+impl SearchHistory {
+    /// Loads the history, starting out empty if it is missing or unreadable.
+    pub fn load() -> Self {
+        let queries = search_history_path()
+            .and_then(|path| read_to_string(path).ok())
+            .map(|contents| {
+                contents
+                    .lines()
+                    .filter(|line| !line.is_empty())
+                    .map(ToString::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { queries }
+    }
+
+    /// Moves `query` to the front of the history and writes it back to
+    /// disk. Failing to write it isn't worth bothering the user about.
+    pub fn add(&mut self, query: &str) {
+        if query.is_empty() || query.contains('\n') {
+            return;
+        }
+        // Pick up queries added by other instances before writing the history back.
+        *self = Self::load();
+        self.queries.retain(|existing| existing != query);
+        self.queries.insert(0, query.to_string());
+        self.queries.truncate(MAX_QUERIES);
+        let _ = self.store();
+    }
+
+    pub fn queries(&self) -> &[String] {
+        &self.queries
+    }
+
+    fn store(&self) -> Result<(), Error> {
+        let Some(path) = search_history_path() else {
+            return Ok(());
+        };
+        if let Some(directory) = path.parent() {
+            create_dir_all(directory)?;
+        }
+        let mut contents = self.queries.join("\n");
+        contents.push('\n');
+        write(path, contents)
+    }
+}
+
+fn search_history_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("searches"))
+}
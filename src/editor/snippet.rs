@@ -0,0 +1,91 @@
+use super::{filetype::FileType, location::Location};
+
+/// A piece of text which replaces its trigger word when Tab is pressed
+/// after it, defined in the config file as
+/// `snippet.<file type>.<trigger> = <body>`, with `*` as the file type
+/// for snippets which apply to all files.
+///
+/// In the body, `\n` and `\t` stand for a line break and one level of
+/// indentation. `$1` to `$9` mark the tab stops Tab and Shift+Tab move
+/// between after the expansion, in order, and `$0` where the caret goes
+/// last; without it, that's the end of the snippet. `$$` is a literal `$`.
+#[derive(Clone)]
+pub struct Snippet {
+    /// `None` for snippets which apply to all files.
+    pub file_type: Option<FileType>,
+    pub trigger: String,
+    body: String,
+}
+
+impl Snippet {
+    /// Parses a config entry, returning `None` if the key doesn't name a
+    /// known file type and a trigger.
+    pub fn parse(key: &str, body: &str) -> Option<Self> {
+        let (file_type, trigger) = key.split_once('.')?;
+        let file_type = match file_type {
+            "*" => None,
+            name => Some(FileType::from_name(name)?),
+        };
+        if trigger.is_empty() {
+            return None;
+        }
+        Some(Self {
+            file_type,
+            trigger: trigger.to_string(),
+            body: body.to_string(),
+        })
+    }
+
+    /// The text to insert, with `indentation` after each line break and
+    /// `indent_unit` for each `\t`, and the tab stops within it in the
+    /// order they are visited. The tab stops are relative to where the text
+    /// starts: their `x` is an offset on the first line, and a column on
+    /// the following ones.
+    pub fn expand(&self, indentation: &str, indent_unit: &str) -> (String, Vec<Location>) {
+        let mut text = String::new();
+        let mut stops: Vec<(u32, Location)> = Vec::new();
+        let mut at = Location::default();
+        let mut chars = self.body.chars().peekable();
+        while let Some(character) = chars.next() {
+            let inserted = match (character, chars.peek().copied()) {
+                ('\\', Some('n')) => {
+                    chars.next();
+                    text.push('\n');
+                    at = Location {
+                        x: 0,
+                        y: at.y.saturating_add(1),
+                    };
+                    indentation.to_string()
+                }
+                ('\\', Some('t')) => {
+                    chars.next();
+                    indent_unit.to_string()
+                }
+                ('\\', Some('\\')) => {
+                    chars.next();
+                    "\\".to_string()
+                }
+                ('$', Some('$')) => {
+                    chars.next();
+                    "$".to_string()
+                }
+                ('$', Some(digit)) if digit.is_ascii_digit() => {
+                    chars.next();
+                    let number = digit.to_digit(10).unwrap_or(0);
+                    // $0 is visited last.
+                    let order = if number == 0 { u32::MAX } else { number };
+                    stops.push((order, at));
+                    String::new()
+                }
+                (character, _) => character.to_string(),
+            };
+            at.x = at.x.saturating_add(inserted.chars().count());
+            text.push_str(&inserted);
+        }
+        if !stops.iter().any(|&(order, _)| order == u32::MAX) {
+            stops.push((u32::MAX, at));
+        }
+        stops.sort_by_key(|&(order, _)| order);
+        (text, stops.into_iter().map(|(_, stop)| stop).collect())
+    }
+}
@@ -0,0 +1,79 @@
+use super::{
+    documentstatus::DocumentStatus,
+    terminal::{Size, Terminal},
+};
+
+/// The row above the message bar, describing the file being edited.
+#[derive(Default)]
+pub struct StatusBar {
+    current_status: DocumentStatus,
+    needs_redraw: bool,
+    width: usize,
+    position_y: usize,
+}
+
+impl StatusBar {
+    pub fn update_status(&mut self, new_status: DocumentStatus) {
+        if new_status != self.current_status {
+            self.current_status = new_status;
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Places the bar on the second-to-last row of a terminal of the given size.
+    pub fn resize(&mut self, to: Size) {
+        self.width = to.width;
+        self.position_y = to.height.saturating_sub(2);
+        self.needs_redraw = true;
+    }
+
+    pub fn render(&mut self) {
+        if !self.needs_redraw || self.width == 0 {
+            return;
+        }
+        let DocumentStatus {
+            file_name,
+            read_only,
+            modified,
+            line_count,
+            encoding,
+            line_ending,
+            large_file_percent,
+            hex_offset,
+            overwrite,
+            percent,
+            caret,
+        } = &self.current_status;
+        let name = file_name.as_deref().unwrap_or("[No Name]");
+        let modified = if *modified { " [modified]" } else { "" };
+        let read_only = if *read_only { " [read-only]" } else { "" };
+        let large_file = large_file_percent
+            .map(|percent| format!(" [large file, {percent}% read]"))
+            .unwrap_or_default();
+        let hex = hex_offset
+            .map(|offset| format!(" [hex, offset {offset:#x}]"))
+            .unwrap_or_default();
+        let overwrite = if *overwrite { " [overwrite]" } else { "" };
+        let percent = percent
+            .map(|percent| format!(" {percent}%"))
+            .unwrap_or_default();
+        let lines = if *line_count == 1 { "line" } else { "lines" };
+        let caret = caret
+            .map(|(line, column)| format!(" {line}:{column}"))
+            .unwrap_or_default();
+        let left = format!(
+            "{name}{modified}{read_only}{large_file}{hex}{overwrite} - {line_count} {lines}"
+        );
+        let right = format!("{encoding} {line_ending}{caret}{percent}");
+        let gap = self
+            .width
+            .saturating_sub(left.chars().count())
+            .saturating_sub(right.chars().count())
+            .max(1);
+        let status = format!("{left}{:gap$}{right}", "");
+        let status: String = status.chars().take(self.width).collect();
+        let result = Terminal::print_inverted_row(self.position_y, &status);
+        debug_assert!(result.is_ok(), "Failed to render status bar");
+        self.needs_redraw = false;
+    }
+}
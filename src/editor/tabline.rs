@@ -0,0 +1,95 @@
+use std::{iter::once, path::Path};
+
+use super::{
+    fuzzypicker::PickerEntry,
+    terminal::{Position, Size, Terminal},
+};
+
+/// How many characters of a file name a tab shows at most.
+const MAX_NAME_WIDTH: usize = 20;
+
+/// The top row of the screen, listing the open buffers as tabs in the
+/// order the buffer switcher lists them, with the active one inverted.
+#[derive(Default)]
+pub struct TabLine {
+    labels: Vec<String>,
+    active: usize,
+    needs_redraw: bool,
+    width: usize,
+}
+
+impl TabLine {
+    /// Shows a tab for each of `buffers`, highlighting the one at `active`.
+    pub fn update_tabs(&mut self, buffers: &[PickerEntry], active: usize) {
+        let labels: Vec<String> = buffers.iter().map(label).collect();
+        if labels != self.labels || active != self.active {
+            self.labels = labels;
+            self.active = active;
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Places the line on the first row of a terminal of the given size.
+    pub fn resize(&mut self, to: Size) {
+        self.width = to.width;
+        self.needs_redraw = true;
+    }
+
+    /// Draws as many tabs as fit, starting far enough to the right for the
+    /// active one to be among them.
+    pub fn render(&mut self) {
+        if !self.needs_redraw || self.width == 0 {
+            return;
+        }
+        let mut used: usize = 0;
+        let first = self
+            .labels
+            .get(..=self.active)
+            .unwrap_or_default()
+            .iter()
+            .rposition(|label| {
+                used = used.saturating_add(label.chars().count());
+                used > self.width
+            })
+            .map_or(0, |index| index.saturating_add(1))
+            .min(self.active);
+        let mut result =
+            Terminal::move_caret_to(Position::default()).and_then(|()| Terminal::clear_line());
+        let mut remaining = self.width;
+        for (index, label) in self.labels.iter().enumerate().skip(first) {
+            if remaining == 0 {
+                break;
+            }
+            let text: String = label.chars().take(remaining).collect();
+            remaining = remaining.saturating_sub(text.chars().count());
+            result = result.and_then(|()| {
+                if index == self.active {
+                    Terminal::print_inverted(&text)
+                } else {
+                    Terminal::print(&text)
+                }
+            });
+        }
+        debug_assert!(result.is_ok(), "Failed to render tab line");
+        self.needs_redraw = false;
+    }
+}
+
+/// A tab's text: the file name without its directory, cut short with `…`
+/// if it is too long, and followed by a `*` if the buffer is modified.
+fn label(buffer: &PickerEntry) -> String {
+    let name = Path::new(&buffer.name).file_name().map_or_else(
+        || buffer.name.clone(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+    let name: String = if name.chars().count() > MAX_NAME_WIDTH {
+        name.chars()
+            .take(MAX_NAME_WIDTH.saturating_sub(1))
+            .chain(once('…'))
+            .collect()
+    } else {
+        name
+    };
+    let mark = if buffer.marked { "*" } else { "" };
+    format!(" {name}{mark} ")
+}
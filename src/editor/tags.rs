@@ -0,0 +1,122 @@
+use std::{
+    fs::read_to_string,
+    io::Error,
+    path::{Path, PathBuf},
+};
+
+/// The name of the file ctags writes its index to.
+const TAGS_FILE_NAME: &str = "tags";
+
+/// Where a definition is within its file.
+#[derive(Clone)]
+pub enum Address {
+    /// The line number, counted from 0.
+    Line(usize),
+    /// The text of the line. Unless `whole_line` is set, ctags cut the line
+    /// short, so the line only has to start with `text`.
+    Pattern { text: String, whole_line: bool },
+}
+
+/// The definition of a symbol, read from a `tags` file written by ctags.
+#[derive(Clone)]
+pub struct Tag {
+    pub name: String,
+    /// The file the definition is in, resolved against the directory of
+    /// the tags file.
+    pub file: PathBuf,
+    pub address: Address,
+}
+
+impl Tag {
+    /// The definitions of `name` listed in `tags_file`, in the order they
+    /// appear in it.
+    pub fn find(name: &str, tags_file: &Path) -> Result<Vec<Self>, Error> {
+        let directory = tags_file.parent().unwrap_or_else(|| Path::new(""));
+        let tags = read_to_string(tags_file)?
+            .lines()
+            .filter_map(|line| Self::parse(line, directory))
+            .filter(|tag| tag.name == name)
+            .collect();
+        Ok(tags)
+    }
+
+    /// Parses a `name<Tab>file<Tab>address` line, where the address may be
+    /// followed by `;"` and extension fields. Returns `None` for the
+    /// `!_TAG_` lines describing the file, and for malformed lines.
+    fn parse(line: &str, directory: &Path) -> Option<Self> {
+        if line.starts_with("!_TAG_") {
+            return None;
+        }
+        let mut fields = line.splitn(3, '\t');
+        let name = fields.next()?;
+        let file = fields.next()?;
+        let rest = fields.next()?;
+        let address = split_address(rest)?;
+        let address = if let Ok(line_number) = address.parse::<usize>() {
+            Address::Line(line_number.saturating_sub(1))
+        } else {
+            parse_pattern(address)?
+        };
+        Some(Self {
+            name: name.to_string(),
+            file: directory.join(file),
+            address,
+        })
+    }
+
+    /// Where the definition is, for the picker: the file and either the
+    /// line number or the text of the line.
+    pub fn description(&self) -> String {
+        match &self.address {
+            Address::Line(line) => format!("{}:{}", self.file.display(), line.saturating_add(1)),
+            Address::Pattern { text, .. } => format!("{}: {}", self.file.display(), text.trim()),
+        }
+    }
+}
+
+/// The address at the start of `rest`, without the extension fields after
+/// it. A search pattern ends at its first unescaped delimiter, as it may
+/// contain `;"` itself.
+fn split_address(rest: &str) -> Option<&str> {
+    let Some(delimiter) = rest.chars().next().filter(|&c| c == '/' || c == '?') else {
+        return Some(rest.split_once(";\"").map_or(rest, |(address, _)| address));
+    };
+    let mut escaped = false;
+    let (end, _) = rest.char_indices().skip(1).find(|&(_, character)| {
+        let ends = !escaped && character == delimiter;
+        escaped = !escaped && character == '\\';
+        ends
+    })?;
+    let (address, fields) = rest.split_at(end.saturating_add(delimiter.len_utf8()));
+    (fields.is_empty() || fields.starts_with(";\"")).then_some(address)
+}
+
+/// Parses a `/^text$/` or `?^text$?` search pattern. Only the slash or
+/// question mark and backslashes are escaped within it.
+fn parse_pattern(address: &str) -> Option<Address> {
+    let delimiter = address.chars().next().filter(|&c| c == '/' || c == '?')?;
+    let pattern = address.strip_prefix(delimiter)?.strip_suffix(delimiter)?;
+    let pattern = pattern.strip_prefix('^').unwrap_or(pattern);
+    let (pattern, whole_line) = match pattern.strip_suffix('$') {
+        Some(rest) if !rest.ends_with('\\') => (rest, true),
+        _ => (pattern, false),
+    };
+    let mut text = String::new();
+    let mut chars = pattern.chars();
+    while let Some(character) = chars.next() {
+        if character == '\\' {
+            text.extend(chars.next());
+        } else {
+            text.push(character);
+        }
+    }
+    Some(Address::Pattern { text, whole_line })
+}
+
+/// The `tags` file in `directory` or the nearest directory above it.
+pub fn tags_file(directory: &Path) -> Option<PathBuf> {
+    directory
+        .ancestors()
+        .map(|directory| directory.join(TAGS_FILE_NAME))
+        .find(|path| path.is_file())
+}
@@ -1,16 +1,23 @@
-use std::io::{stdout, Error, Write};
+use std::{
+    fs::OpenOptions,
+    io::{stderr, stdout, BufWriter, Error, Write},
+    sync::{Mutex, OnceLock},
+};
 
 use crossterm::{
-    cursor::{Hide, MoveTo, Show},
-    queue,
-    style::Print,
+    cursor::{Hide, MoveTo, SetCursorStyle, Show},
+    event::{DisableFocusChange, EnableFocusChange},
+    style::{Print, PrintStyledContent, Stylize},
     terminal::{
         disable_raw_mode, enable_raw_mode, size, Clear, ClearType, EnterAlternateScreen,
         LeaveAlternateScreen,
     },
-    Command,
+    Command, QueueableCommand,
 };
 
+/// Where the UI is drawn when it must not go to stdout, see `Terminal::draw_to_tty`.
+static TTY_OUTPUT: OnceLock<Mutex<Box<dyn Write + Send>>> = OnceLock::new();
+
 #[derive(Default, Copy, Clone)]
 pub struct Size {
     pub height: usize,
@@ -33,7 +40,9 @@ pub struct Terminal;
 
 impl Terminal {
     pub fn terminate() -> Result<(), Error> {
+        Self::queue_command(DisableFocusChange)?;
         Self::leave_alternate_screen()?;
+        Self::set_caret_shape(false)?;
         Self::show_caret()?;
         Self::execute()?;
         disable_raw_mode()?;
@@ -43,11 +52,23 @@ impl Terminal {
     pub fn initialize() -> Result<(), Error> {
         enable_raw_mode()?;
         Self::enter_alternate_screen()?;
+        Self::queue_command(EnableFocusChange)?;
         Self::clear_screen()?;
         Self::execute()?;
         Ok(())
     }
 
+    /// Draws the UI on the controlling terminal instead of stdout, which
+    /// is then left free for the program's output. Falls back to stderr
+    /// where there is no `/dev/tty`. Must be called before `initialize`.
+    pub fn draw_to_tty() {
+        let output: Box<dyn Write + Send> = match OpenOptions::new().write(true).open("/dev/tty") {
+            Ok(tty) => Box::new(BufWriter::new(tty)),
+            Err(_) => Box::new(stderr()),
+        };
+        let _ = TTY_OUTPUT.set(Mutex::new(output));
+    }
+
     pub fn clear_screen() -> Result<(), Error> {
         Self::queue_command(Clear(ClearType::All))?;
         Ok(())
@@ -88,17 +109,49 @@ impl Terminal {
         Ok(())
     }
 
+    /// Shows the caret as an underscore if `overwrite` is set, for overwrite
+    /// mode, and in the terminal's default shape otherwise.
+    pub fn set_caret_shape(overwrite: bool) -> Result<(), Error> {
+        if overwrite {
+            Self::queue_command(SetCursorStyle::SteadyUnderScore)?;
+        } else {
+            Self::queue_command(SetCursorStyle::DefaultUserShape)?;
+        }
+        Ok(())
+    }
+
     pub fn print(string: &str) -> Result<(), Error> {
         Self::queue_command(Print(string))?;
         Ok(())
     }
 
+    /// Prints `string` with foreground and background colors swapped.
+    pub fn print_inverted(string: &str) -> Result<(), Error> {
+        Self::queue_command(PrintStyledContent(string.reverse()))?;
+        Ok(())
+    }
+
+    /// Prints `string` in black on yellow, e.g. for the matches of a search.
+    pub fn print_marked(string: &str) -> Result<(), Error> {
+        Self::queue_command(PrintStyledContent(string.black().on_yellow()))?;
+        Ok(())
+    }
+
     pub fn print_row(row: usize, line_text: &str) -> Result<(), Error> {
         Self::move_caret_to(Position { row, col: 0 })?;
         Self::clear_line()?;
         Self::print(line_text)?;
         Ok(())
     }
+
+    /// Like `print_row`, but with foreground and background colors swapped.
+    pub fn print_inverted_row(row: usize, line_text: &str) -> Result<(), Error> {
+        Self::move_caret_to(Position { row, col: 0 })?;
+        Self::clear_line()?;
+        Self::print_inverted(line_text)?;
+        Ok(())
+    }
+
     /// Returns the current size of this Terminal.
     /// Edge Case for systems with `usize` < `u16`:
     /// * A `Size` representing the terminal size. Any coordinate `z` truncated to `usize` if `usize` < `z` < `u16`
@@ -115,12 +168,24 @@ impl Terminal {
     }
 
     pub fn execute() -> Result<(), Error> {
-        stdout().flush()?;
-        Ok(())
+        Self::with_output(|output| output.flush())
     }
 
     fn queue_command<T: Command>(command: T) -> Result<(), Error> {
-        queue!(stdout(), command)?;
-        Ok(())
+        Self::with_output(|output| {
+            output.queue(command)?;
+            Ok(())
+        })
+    }
+
+    fn with_output(write: impl FnOnce(&mut dyn Write) -> Result<(), Error>) -> Result<(), Error> {
+        if let Some(tty) = TTY_OUTPUT.get() {
+            let mut tty = tty
+                .lock()
+                .map_err(|_| Error::other("Terminal output is poisoned"))?;
+            write(&mut **tty)
+        } else {
+            write(&mut stdout().lock())
+        }
     }
 }
@@ -0,0 +1,99 @@
+use std::{
+    env,
+    fs::{create_dir_all, read_to_string, write},
+    io::Error,
+    path::{Path, PathBuf, MAIN_SEPARATOR},
+};
+
+use super::history::History;
+
+/// The first line of every undo file, so files in an old format are ignored.
+const HEADER: &str = "hecto-undo 3";
+
+/// Stores a buffer's undo history next to a hash of the contents it applies
+/// to, in `$XDG_CACHE_HOME/hecto/undo` (or `~/.cache/hecto/undo`). The
+/// history is only restored if the file still has exactly these contents.
+pub fn store(file_name: &str, contents: &[u8], history: &History) -> Result<(), Error> {
+    let Some(path) = undo_file_path(file_name) else {
+        return Ok(());
+    };
+    store_at(&path, contents, history)
+}
+
+fn store_at(path: &Path, contents: &[u8], history: &History) -> Result<(), Error> {
+    if let Some(directory) = path.parent() {
+        create_dir_all(directory)?;
+    }
+    let text = format!("{HEADER}\n{:016x}\n{}", hash(contents), history.serialize());
+    write(path, text)
+}
+
+/// The history stored for `file_name`, unless there is none or the file
+/// was changed since it was stored.
+pub fn load(file_name: &str, contents: &[u8]) -> Option<History> {
+    load_from(&undo_file_path(file_name)?, contents)
+}
+
+fn load_from(path: &Path, contents: &[u8]) -> Option<History> {
+    let text = read_to_string(path).ok()?;
+    let mut lines = text.splitn(3, '\n');
+    if lines.next()? != HEADER || lines.next()? != format!("{:016x}", hash(contents)) {
+        return None;
+    }
+    History::deserialize(lines.next().unwrap_or_default())
+}
+
+/// The 64-bit FNV-1a hash of `contents`. Unlike the standard library's
+/// hashers, it is fixed, so undo files stay valid across Rust versions.
+fn hash(contents: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    contents.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// One file per edited file, named after its absolute path with `%`
+/// doubled and `/` replaced by `%`, as Vim does.
+fn undo_file_path(file_name: &str) -> Option<PathBuf> {
+    let absolute = Path::new(file_name).canonicalize().ok()?;
+    let name = absolute
+        .to_str()?
+        .replace('%', "%%")
+        .replace(MAIN_SEPARATOR, "%");
+    let cache_home = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(cache_home.join("hecto").join("undo").join(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+
+    use super::{hash, load_from, store_at, History};
+    use crate::editor::history::Change;
+    use crate::editor::location::Location;
+
+    #[test]
+    fn hash_is_fnv1a() {
+        assert_eq!(hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(hash(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn stored_history_loads_for_the_same_contents_only() {
+        let path = temp_dir().join("hecto-undofile-round-trip");
+        let mut history = History::default();
+        history.record(Change::Insert {
+            at: Location::default(),
+            text: "hello".to_string(),
+        });
+        store_at(&path, b"hello\n", &history).unwrap();
+        let loaded = load_from(&path, b"hello\n").unwrap();
+        assert_eq!(loaded.serialize(), history.serialize());
+        assert!(load_from(&path, b"hello!\n").is_none());
+    }
+}
@@ -1,85 +1,2063 @@
-use std::cmp::min;
+use std::{
+    cmp::{min, Ordering, Reverse},
+    collections::HashMap,
+    iter::once,
+    ops::Range,
+};
+use unicode_width::UnicodeWidthStr;
+
+use super::{
+    buffer::Buffer,
+    editorcommand::{Case, Direction, Edit, EditorCommand, SearchDirection, SortOrder},
+    history::Change,
+    line::{is_word_char, Line},
+    location::Location,
+    search::{self, Match, Pattern},
+    snippet::Snippet,
+    terminal::{Position, Size, Terminal},
+};
+
+const NAME: &str = env!("CARGO_PKG_NAME");
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// How many cut or copied texts the kill ring keeps.
+const KILL_RING_SIZE: usize = 16;
+/// The brackets and quotes which are closed automatically.
+const PAIRS: [(char, char); 5] = [('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')];
+/// How many jumps `JumpBack` can go back.
+const JUMP_LIST_SIZE: usize = 100;
+/// The brackets which `JumpToMatchingBracket` finds the counterparts of.
+const BRACKETS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+/// How highlighted cells are shown.
+#[derive(Clone, Copy)]
+enum Style {
+    /// The extra carets, the selection and the current match of a search.
+    Inverted,
+    /// The other matches of the last search.
+    Marked,
+}
+
+/// A screen row's worth of a line when lines are wrapped: the line's index
+/// and which of the line's rows it is.
+type Row = (usize, usize);
+
+/// Shows a `Buffer` on screen and keeps track of the caret within it.
+/// The buffer itself is owned by the `Editor` and passed in as needed.
+// clippy::struct_excessive_bools: the flags are unrelated settings and states.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone)]
+pub struct View {
+    needs_redraw: bool,
+    size: Size,
+    /// Where on screen the view's top left corner is drawn, e.g. below or
+    /// right of another window when the screen is split.
+    origin: Position,
+    location: Location,
+    /// Further carets at which edits are made as well, shown highlighted.
+    /// `location` is always the newest caret, and the view follows it.
+    extra_carets: Vec<Location>,
+    /// Where the selection started; it reaches from there to the caret.
+    selection_anchor: Option<Location>,
+    /// While a block is selected, the line it started on and the screen
+    /// column (not the character index) it started at.
+    block_anchor: Option<Location>,
+    /// The screen column of the block's corner at the caret. It may lie
+    /// beyond the end of the caret's line.
+    block_col: usize,
+    /// The screen column of the caret within its line, which differs
+    /// from `location.x` when there are tabs before it.
+    caret_col: usize,
+    /// The screen column moving up and down returns to on longer lines,
+    /// even after passing through shorter ones. Set by the first of several
+    /// vertical moves in a row, and dropped by any other command.
+    goal_col: Option<usize>,
+    /// The top line and the leftmost screen column shown.
+    scroll_offset: Location,
+    /// How many lines to keep on screen above and below the caret.
+    scrolloff: usize,
+    /// Show long lines across several rows instead of scrolling sideways.
+    /// Up and Down then move by row, and scrolling goes by rows too.
+    wrap: bool,
+    /// With `wrap`, the first row of the top line which is shown, as a
+    /// line may be taller than the screen.
+    top_row: usize,
+    /// Show the welcome screen while the buffer is empty, until a key is
+    /// pressed.
+    welcome: bool,
+    /// Show each line's number in a gutter to the left of the text.
+    line_numbers: bool,
+    /// Number lines by their distance from the caret's line, which is
+    /// numbered as usual.
+    relative_line_numbers: bool,
+    /// With relative line numbers, the caret's line when the gutter was
+    /// last drawn, as the numbers change when it moves to another line.
+    numbered_from: Option<usize>,
+    /// How many screen columns the line numbers take up, including the
+    /// space after them, or 0 if they aren't shown. Without `wrap`, the
+    /// gutter keeps at least that space, for the `←` scroll indicator.
+    gutter_width: usize,
+    tab_width: usize,
+    /// Insert spaces instead of a tab character when Tab is pressed.
+    expand_tab: bool,
+    /// Copy the indentation of the current line to new lines.
+    auto_indent: bool,
+    /// Characters after which new lines are indented one level further.
+    indent_after: String,
+    /// Insert closing brackets and quotes along with opening ones.
+    auto_close: bool,
+    /// Typed characters replace the character under the caret.
+    overwrite: bool,
+    snippets: Vec<Snippet>,
+    /// Words which are replaced when a word boundary is typed after them.
+    abbreviations: HashMap<String, String>,
+    /// The tab stops of the snippet being filled in, in the order Tab
+    /// visits them. Empty if no snippet is being filled in.
+    snippet_stops: Vec<Location>,
+    /// The index of the tab stop the caret was last moved to.
+    snippet_stop: usize,
+    /// While searching, where the caret was and which part of the buffer
+    /// was shown, to go back there if the search is cancelled.
+    search_origin: Option<(Location, Location)>,
+    /// The match of the search, shown highlighted while searching.
+    search_match: Option<Match>,
+    /// The last search, whose matches on screen are all shown highlighted
+    /// until the highlights are cleared.
+    search_pattern: Option<Pattern>,
+    /// The selection when the search or replacement started, which only
+    /// matches within it count for.
+    search_region: Option<(Location, Location)>,
+    search_direction: SearchDirection,
+    /// Where the caret was before each jump in the current buffer, oldest
+    /// first. Locations are not adjusted to edits, so they may be a little off.
+    jumps: Vec<Location>,
+    /// The entry of `jumps` the caret went back to, or the length of
+    /// `jumps` if it didn't go back.
+    jump_index: usize,
+    /// The texts last cut or copied, newest first, shared by all buffers.
+    kill_ring: Vec<String>,
+    /// Whether the last command was a cut, so the next one adds to the
+    /// newest entry of the kill ring instead of starting a new one.
+    kill_continues: bool,
+    /// How many `Recenter` commands came right before the current one,
+    /// which decides where it puts the caret's line.
+    recenter_presses: usize,
+}
+
+impl View {
+    pub fn render(&mut self, buffer: &Buffer) {
+        // The gutter widens when the buffer gets longer, e.g. from outside the view.
+        if self.update_gutter_width(buffer) {
+            self.scroll_location_into_view(buffer);
+        }
+        if self.relative_line_numbers
+            && self.gutter_width > 0
+            && self.numbered_from != Some(self.location.y)
+        {
+            self.needs_redraw = true;
+        }
+        if !self.needs_redraw {
+            return;
+        }
+
+        let Size { height, .. } = self.size;
+        let width = self.text_width();
+        if height == 0 || width == 0 {
+            return;
+        }
+        // we allow this since we don't care if our welcome message is put _exactly_ in the middle.
+        // it's allowed to be a bit too far up or down
+        #[allow(clippy::integer_division)]
+        let vertical_center = height / 3;
+        let welcome = if self.welcome && buffer.is_empty() {
+            Self::build_welcome_lines()
+        } else {
+            Vec::new()
+        };
+        let (mut y, mut line_row) = self.top();
+
+        for current_row in 0..height {
+            let at = self.origin.row.saturating_add(current_row);
+            if let Some(line) = buffer.line(y) {
+                self.render_gutter(at, (line_row == 0).then(|| self.line_number(y)));
+                if self.wrap {
+                    let starts = self.row_starts(&line);
+                    let left = starts.get(line_row).copied().unwrap_or(0);
+                    let right = starts
+                        .get(line_row.saturating_add(1))
+                        .copied()
+                        .unwrap_or_else(|| left.saturating_add(width));
+                    self.render_line_part(at, y, &line, left..right);
+                    line_row = line_row.saturating_add(1);
+                    if line_row >= starts.len() {
+                        y = y.saturating_add(1);
+                        line_row = 0;
+                    }
+                } else {
+                    let left = self.scroll_offset.x;
+                    let right = self.scroll_offset.x.saturating_add(width);
+                    self.render_line_part(at, y, &line, left..right);
+                    self.render_scroll_indicators(at, &line, left..right);
+                    y = y.saturating_add(1);
+                }
+            } else if let Some(text) = current_row
+                .checked_sub(vertical_center)
+                .and_then(|index| welcome.get(index))
+            {
+                self.render_line(at, &Self::centered(self.size.width, text));
+            } else {
+                self.render_line(at, "~");
+            }
+        }
+        self.numbered_from = Some(self.location.y);
+        self.needs_redraw = false;
+    }
+
+    pub fn handle_command(&mut self, command: EditorCommand, buffer: &Buffer) {
+        self.kill_continues = false;
+        if !matches!(
+            command,
+            EditorCommand::Move(_) | EditorCommand::Select(_) | EditorCommand::Resize(_)
+        ) {
+            self.goal_col = None;
+        }
+        let recenter_presses = std::mem::take(&mut self.recenter_presses);
+        if !matches!(
+            command,
+            EditorCommand::SelectBlock(_) | EditorCommand::Resize(_)
+        ) && self.block_anchor.take().is_some()
+        {
+            self.needs_redraw = true;
+        }
+        match command {
+            EditorCommand::Resize(size) => self.resize(self.origin, size, buffer),
+            EditorCommand::Move(direction) => {
+                if self.selection_anchor.take().is_some() {
+                    self.needs_redraw = true;
+                }
+                self.move_text_location(&direction, buffer);
+            }
+            EditorCommand::Select(direction) => {
+                if self.selection_anchor.is_none() {
+                    self.selection_anchor = Some(self.location);
+                    self.extra_carets.clear();
+                }
+                self.move_text_location(&direction, buffer);
+                self.needs_redraw = true;
+            }
+            EditorCommand::SelectBlock(direction) => self.select_block(&direction, buffer),
+            EditorCommand::AddCaretAbove => self.add_caret_vertically(&Direction::Up, buffer),
+            EditorCommand::AddCaretBelow => self.add_caret_vertically(&Direction::Down, buffer),
+            EditorCommand::AddCaretAtNextMatch => self.add_caret_at_next_match(buffer),
+            EditorCommand::ScrollUp => self.scroll_by_line(true, buffer),
+            EditorCommand::ScrollDown => self.scroll_by_line(false, buffer),
+            EditorCommand::JumpBack => self.jump_back(buffer),
+            EditorCommand::JumpForward => {
+                let next = self.jump_index.saturating_add(1);
+                if let Some(&location) = self.jumps.get(next) {
+                    self.jump_index = next;
+                    self.move_to(location, buffer);
+                }
+            }
+            EditorCommand::Recenter => self.recenter(recenter_presses, buffer),
+            EditorCommand::JumpToMatchingBracket => {
+                if let Some(location) = matching_bracket(self.location, buffer) {
+                    self.go_to(location, buffer);
+                }
+            }
+            EditorCommand::RemoveExtraCarets => {
+                if !self.extra_carets.is_empty() || self.selection_anchor.is_some() {
+                    self.extra_carets.clear();
+                    self.selection_anchor = None;
+                    self.needs_redraw = true;
+                }
+            }
+            EditorCommand::ClearSearchHighlights => {
+                if self.search_pattern.take().is_some() {
+                    self.needs_redraw = true;
+                }
+            }
+            EditorCommand::ToggleOverwrite => self.overwrite = !self.overwrite,
+            EditorCommand::Copy => self.copy(buffer),
+            EditorCommand::Edit(_)
+            | EditorCommand::Save
+            | EditorCommand::SaveAs
+            | EditorCommand::ConvertLineEndings
+            | EditorCommand::SortLines
+            | EditorCommand::GoToLine
+            | EditorCommand::SetMark
+            | EditorCommand::JumpToMark
+            | EditorCommand::GoToDefinition
+            | EditorCommand::OpenFileAtCaret
+            | EditorCommand::Search(_)
+            | EditorCommand::RepeatSearch { .. }
+            | EditorCommand::SearchWordAtCaret
+            | EditorCommand::Grep
+            | EditorCommand::CountMatches
+            | EditorCommand::CommandLine
+            | EditorCommand::SplitWindow(_)
+            | EditorCommand::FocusOtherWindow
+            | EditorCommand::ResizeWindow(_)
+            | EditorCommand::CloseWindow
+            | EditorCommand::Replace
+            | EditorCommand::ReplaceAll
+            | EditorCommand::QuotedInsert
+            | EditorCommand::PasteFromKillRing
+            | EditorCommand::ToggleHexView
+            | EditorCommand::NewBuffer
+            | EditorCommand::OpenRecentFile
+            | EditorCommand::FindFile
+            | EditorCommand::SwitchBuffer
+            | EditorCommand::NextBuffer
+            | EditorCommand::PreviousBuffer
+            | EditorCommand::Quit => {}
+        }
+    }
+
+    /// Applies `edit` to `buffer` at every caret and moves the carets past
+    /// the change. Typing replaces the selection, and Tab indents it if it
+    /// spans several lines. Tab after a snippet's trigger expands it, and
+    /// then Tab and Shift+Tab move between its tab stops. Undo and redo go
+    /// back to a single caret.
+    pub fn handle_edit(&mut self, edit: &Edit, buffer: &mut Buffer) {
+        let kill_continues = std::mem::take(&mut self.kill_continues);
+        self.recenter_presses = 0;
+        self.goal_col = None;
+        if self.edit_block(edit, buffer) {
+            return;
+        }
+        let keeps_snippet = matches!(
+            edit,
+            Edit::Insert(_)
+                | Edit::InsertLiteral(_)
+                | Edit::InsertTab
+                | Edit::InsertNewline
+                | Edit::DeleteBackward
+                | Edit::Delete
+                | Edit::Dedent
+                | Edit::Paste(_)
+        );
+        if !keeps_snippet {
+            self.snippet_stops.clear();
+        }
+        let caret = match edit {
+            Edit::Undo => buffer.undo(),
+            Edit::Redo => buffer.redo(),
+            Edit::UndoEarlier => buffer.undo_earlier(),
+            Edit::UndoLater => buffer.undo_later(),
+            Edit::InsertTab if self.move_to_snippet_stop(true, buffer) => return,
+            Edit::Dedent if self.move_to_snippet_stop(false, buffer) => return,
+            Edit::InsertTab if self.expand_snippet(buffer) => return,
+            Edit::InsertTab
+                if self
+                    .selection()
+                    .is_some_and(|(start, end)| start.y != end.y) =>
+            {
+                self.indent_lines(buffer);
+                return;
+            }
+            Edit::Dedent => {
+                self.dedent_lines(buffer);
+                return;
+            }
+            Edit::Duplicate => {
+                self.duplicate(buffer);
+                return;
+            }
+            Edit::ChangeCase(case) => {
+                self.change_case(*case, buffer);
+                return;
+            }
+            Edit::SortLines(order) => {
+                self.sort_lines(*order, buffer);
+                return;
+            }
+            Edit::ToggleComment => {
+                self.toggle_comment(buffer);
+                return;
+            }
+            Edit::KillLine => {
+                self.kill(kill_continues, buffer);
+                return;
+            }
+            Edit::MoveLinesUp | Edit::MoveLinesDown => {
+                self.move_lines(matches!(edit, Edit::MoveLinesDown), buffer);
+                return;
+            }
+            _ => {
+                self.edit_at_carets(edit, buffer);
+                return;
+            }
+        };
+        if let Some(location) = caret {
+            self.location = location;
+            self.extra_carets.clear();
+            self.selection_anchor = None;
+            self.snap_location(buffer);
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Applies `edit` to the selected block, if there is one. Typing replaces
+    /// the block with what was typed on each of its lines, deleting removes
+    /// it and cutting moves it into the kill ring, one line of the block per
+    /// line of text. Lines ending left of the block are left alone. Other
+    /// edits just drop the block. Returns whether `edit` was applied.
+    fn edit_block(&mut self, edit: &Edit, buffer: &mut Buffer) -> bool {
+        let Some((lines, cols)) = self.block() else {
+            return false;
+        };
+        self.block_anchor = None;
+        self.needs_redraw = true;
+        if !matches!(
+            edit,
+            Edit::Insert(_)
+                | Edit::InsertLiteral(_)
+                | Edit::InsertTab
+                | Edit::Paste(_)
+                | Edit::DeleteBackward
+                | Edit::Delete
+                | Edit::KillLine
+        ) {
+            return false;
+        }
+        buffer.start_undo_group();
+        let mut carets = Vec::new();
+        let mut texts = Vec::new();
+        for y in lines.rev() {
+            let Some(line) = buffer.line(y) else {
+                continue;
+            };
+            if line.column_of(line.len(), self.tab_width) < cols.start {
+                continue;
+            }
+            let start = Location {
+                x: line.index_at_column(cols.start, self.tab_width),
+                y,
+            };
+            let end = Location {
+                x: line.index_at_column(cols.end, self.tab_width),
+                y,
+            };
+            texts.push(line.chars_between(start.x, end.x));
+            drop(line);
+            buffer.delete_range(start, end);
+            carets.push(start);
+        }
+        if let Some(index) = carets.iter().position(|caret| caret.y == self.location.y) {
+            self.location = carets.swap_remove(index);
+            self.extra_carets = carets;
+        } else if let Some(first) = carets.pop() {
+            self.location = first;
+            self.extra_carets = carets;
+        }
+        match edit {
+            Edit::KillLine => {
+                texts.reverse();
+                self.push_to_kill_ring(texts.join("\n"));
+                self.extra_carets.clear();
+            }
+            Edit::DeleteBackward | Edit::Delete if !cols.is_empty() => {}
+            _ => self.edit_at_carets(edit, buffer),
+        }
+        buffer.end_undo_group();
+        self.snap_location(buffer);
+        true
+    }
+
+    /// Applies `edit` at each caret, starting with the last one in the
+    /// buffer, and shifts the carets after it by what the edit changed.
+    /// Typing and deleting remove the selection first; other edits just
+    /// drop it. With several carets or a selection, the edits form a single
+    /// undo step.
+    fn edit_at_carets(&mut self, edit: &Edit, buffer: &mut Buffer) {
+        let replaces_selection = matches!(
+            edit,
+            Edit::Insert(_)
+                | Edit::InsertLiteral(_)
+                | Edit::InsertTab
+                | Edit::InsertNewline
+                | Edit::DeleteBackward
+                | Edit::Delete
+                | Edit::Paste(_)
+        );
+        let selection = self.selection().filter(|_| replaces_selection);
+        if self.selection_anchor.take().is_some() {
+            self.needs_redraw = true;
+        }
+        let grouped = selection.is_some() || !self.extra_carets.is_empty();
+        if grouped {
+            buffer.start_undo_group();
+        }
+        let mut changed = false;
+        if let Some((start, end)) = selection {
+            changed = buffer.delete_range(start, end);
+            self.location = start;
+            self.needs_redraw = true;
+        }
+        let mut carets: Vec<Location> = once(self.location)
+            .chain(self.extra_carets.iter().copied())
+            .collect();
+        let mut order: Vec<usize> = (0..carets.len()).collect();
+        order.sort_by_key(|&index| Reverse((carets[index].y, carets[index].x)));
+        if selection.is_some() && matches!(edit, Edit::DeleteBackward | Edit::Delete) {
+            order.clear();
+        }
+        for (position, &index) in order.iter().enumerate() {
+            let Some(caret_edit) = self.edit_at(edit, carets[index], buffer) else {
+                continue;
+            };
+            changed = true;
+            carets[index] = caret_edit.caret;
+            for &later in &order[..position] {
+                carets[later] = carets[later].shifted(caret_edit.old_end, caret_edit.new_end);
+            }
+            let old_end = (caret_edit.old_end.y, caret_edit.old_end.x);
+            for stop in &mut self.snippet_stops {
+                if (stop.y, stop.x) >= old_end {
+                    *stop = stop.shifted(caret_edit.old_end, caret_edit.new_end);
+                }
+            }
+        }
+        if grouped {
+            buffer.end_undo_group();
+        }
+        if changed {
+            self.location = carets[0];
+            self.extra_carets = carets.split_off(1);
+            self.snap_location(buffer);
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Applies `edit` at `location` and returns what it changed, first
+    /// expanding an abbreviation before `location` if `edit` ends a word.
+    fn edit_at(&self, edit: &Edit, location: Location, buffer: &mut Buffer) -> Option<CaretEdit> {
+        let ends_word = match edit {
+            Edit::Insert(character) => !is_word_char(*character),
+            Edit::InsertNewline => true,
+            _ => false,
+        };
+        let Some(expanded) = ends_word
+            .then(|| self.expand_abbreviation(location, buffer))
+            .flatten()
+        else {
+            return self.edit_after_expansion(edit, location, buffer);
+        };
+        let caret_edit = self
+            .edit_after_expansion(edit, expanded, buffer)
+            .unwrap_or(CaretEdit::to_end(expanded, expanded));
+        Some(CaretEdit {
+            old_end: location,
+            ..caret_edit
+        })
+    }
+
+    /// Replaces the abbreviation right before `location`, if there is one,
+    /// as one undo step. Returns where the replacement ends. The text
+    /// since the last whitespace is tried first, then the last word.
+    fn expand_abbreviation(&self, location: Location, buffer: &mut Buffer) -> Option<Location> {
+        if self.abbreviations.is_empty() {
+            return None;
+        }
+        let before = buffer.line(location.y)?.chars_between(0, location.x);
+        let token = before
+            .rsplit(char::is_whitespace)
+            .next()
+            .unwrap_or_default();
+        let word_start = token
+            .char_indices()
+            .rev()
+            .take_while(|&(_, character)| is_word_char(character))
+            .last()
+            .map_or(token.len(), |(index, _)| index);
+        let (abbreviation, replacement) = [token, &token[word_start..]]
+            .into_iter()
+            .find_map(|candidate| Some((candidate, self.abbreviations.get(candidate)?)))?;
+        let start = Location {
+            x: location.x.saturating_sub(abbreviation.chars().count()),
+            y: location.y,
+        };
+        buffer.start_undo_group();
+        buffer.delete_range(start, location);
+        let end = buffer.insert(start, replacement);
+        buffer.end_undo_group();
+        end
+    }
+
+    /// Applies `edit` at `location` and returns what it changed.
+    fn edit_after_expansion(
+        &self,
+        edit: &Edit,
+        location: Location,
+        buffer: &mut Buffer,
+    ) -> Option<CaretEdit> {
+        match edit {
+            Edit::Insert(character) if self.overwrite => {
+                Self::overwrite_at(location, *character, buffer)
+            }
+            Edit::Insert(character) if self.auto_close => {
+                Self::insert_with_auto_close(*character, location, buffer)
+            }
+            Edit::Insert(character) | Edit::InsertLiteral(character) => buffer
+                .insert(location, &character.to_string())
+                .map(|caret| CaretEdit::to_end(location, caret)),
+            Edit::InsertTab => {
+                let text = if self.expand_tab {
+                    let col = buffer
+                        .line(location.y)
+                        .map_or(0, |line| line.column_of(location.x, self.tab_width));
+                    let spaces = self
+                        .tab_width
+                        .saturating_sub(col.checked_rem(self.tab_width).unwrap_or(0));
+                    " ".repeat(spaces.max(1))
+                } else {
+                    "\t".to_string()
+                };
+                buffer
+                    .insert(location, &text)
+                    .map(|caret| CaretEdit::to_end(location, caret))
+            }
+            Edit::InsertNewline => {
+                let text = format!("\n{}", self.new_line_indentation(location, buffer));
+                buffer
+                    .insert(location, &text)
+                    .map(|caret| CaretEdit::to_end(location, caret))
+            }
+            Edit::DeleteBackward => {
+                if location == Location::default() {
+                    return None;
+                }
+                if self.auto_close {
+                    if let Some(caret_edit) = Self::delete_empty_pair(location, buffer) {
+                        return Some(caret_edit);
+                    }
+                }
+                let start = self.moved(location, &Direction::Left, buffer);
+                buffer
+                    .delete(start)
+                    .then_some(CaretEdit::to_end(location, start))
+            }
+            Edit::Delete => {
+                let end = self.moved(location, &Direction::Right, buffer);
+                buffer
+                    .delete(location)
+                    .then_some(CaretEdit::to_end(end, location))
+            }
+            Edit::Paste(index) => {
+                let text = self.kill_ring.get(*index)?;
+                buffer
+                    .insert(location, text)
+                    .map(|caret| CaretEdit::to_end(location, caret))
+            }
+            Edit::JoinLines => Self::join_lines_at(location, buffer),
+            Edit::TransposeChars => Self::transpose_chars_at(location, buffer),
+            Edit::TransposeWords => Self::transpose_words_at(location, buffer),
+            Edit::AddToNumber(amount) => Self::add_to_number_at(location, *amount, buffer),
+            Edit::ChangeCase(_)
+            | Edit::SortLines(_)
+            | Edit::ToggleComment
+            | Edit::KillLine
+            | Edit::Dedent
+            | Edit::Duplicate
+            | Edit::MoveLinesUp
+            | Edit::MoveLinesDown
+            | Edit::Undo
+            | Edit::Redo
+            | Edit::UndoEarlier
+            | Edit::UndoLater => None,
+        }
+    }
+
+    /// Replaces the word before the caret with the snippet it triggers, if
+    /// any, and moves to the snippet's first tab stop. Snippets for the
+    /// buffer's file type take precedence over those for all files.
+    fn expand_snippet(&mut self, buffer: &mut Buffer) -> bool {
+        if !self.extra_carets.is_empty() || self.selection().is_some() {
+            return false;
+        }
+        let Location { x, y } = self.location;
+        let Some(line) = buffer.line(y) else {
+            return false;
+        };
+        let Some(word) = line.words().into_iter().find(|word| word.end == x) else {
+            return false;
+        };
+        let trigger = line.chars_between(word.start, word.end);
+        let file_type = buffer.file_type();
+        let matching = |snippet: &&Snippet, specific: bool| {
+            snippet.trigger == trigger
+                && (if specific {
+                    snippet.file_type == Some(file_type)
+                } else {
+                    snippet.file_type.is_none()
+                })
+        };
+        let Some(snippet) = self
+            .snippets
+            .iter()
+            .find(|snippet| matching(snippet, true))
+            .or_else(|| {
+                self.snippets
+                    .iter()
+                    .find(|snippet| matching(snippet, false))
+            })
+        else {
+            return false;
+        };
+        let indentation = line.chars_between(0, line.indentation_len());
+        let (text, stops) = snippet.expand(&indentation, &self.indent_unit());
+        drop(line);
+        let start = Location { x: word.start, y };
+        buffer.start_undo_group();
+        buffer.delete_range(start, self.location);
+        let inserted = buffer.insert(start, &text).is_some();
+        buffer.end_undo_group();
+        if !inserted {
+            return false;
+        }
+        self.snippet_stops = stops
+            .into_iter()
+            .map(|stop| {
+                if stop.y == 0 {
+                    Location {
+                        x: start.x.saturating_add(stop.x),
+                        y,
+                    }
+                } else {
+                    Location {
+                        x: stop.x,
+                        y: y.saturating_add(stop.y),
+                    }
+                }
+            })
+            .collect();
+        self.snippet_stop = 0;
+        self.location = self.snippet_stops.first().copied().unwrap_or(start);
+        if self.snippet_stops.len() <= 1 {
+            self.snippet_stops.clear();
+        }
+        self.snap_location(buffer);
+        self.needs_redraw = true;
+        true
+    }
 
-mod buffer;
-mod line;
-mod location;
+    /// Moves to the next or previous tab stop of the snippet being filled
+    /// in, if any. Reaching the last one finishes the snippet.
+    fn move_to_snippet_stop(&mut self, next: bool, buffer: &Buffer) -> bool {
+        if self.snippet_stops.is_empty() {
+            return false;
+        }
+        self.snippet_stop = if next {
+            self.snippet_stop.saturating_add(1)
+        } else {
+            self.snippet_stop.saturating_sub(1)
+        };
+        if let Some(&stop) = self.snippet_stops.get(self.snippet_stop) {
+            self.location = stop;
+        }
+        if self.snippet_stop.saturating_add(1) >= self.snippet_stops.len() {
+            self.snippet_stops.clear();
+        }
+        self.selection_anchor = None;
+        self.extra_carets.clear();
+        self.snap_location(buffer);
+        self.needs_redraw = true;
+        true
+    }
+
+    /// Inserts `character`, along with its closing bracket or quote if it
+    /// is an opening one, placing the caret between them. A closing one
+    /// which was inserted that way and is still at the caret is typed over
+    /// instead. Quotes aren't closed right after a word, where they are
+    /// more likely apostrophes.
+    fn insert_with_auto_close(
+        character: char,
+        location: Location,
+        buffer: &mut Buffer,
+    ) -> Option<CaretEdit> {
+        let char_at = |x: usize| {
+            buffer
+                .line(location.y)
+                .and_then(|line| line.chars_between(x, x.saturating_add(1)).chars().next())
+        };
+        let next = char_at(location.x);
+        let previous = location.x.checked_sub(1).and_then(char_at);
+        if next == Some(character) && buffer.is_auto_closer(location) {
+            buffer.remove_auto_closer(location);
+            let caret = Location {
+                x: location.x.saturating_add(1),
+                y: location.y,
+            };
+            return Some(CaretEdit {
+                old_end: location,
+                new_end: location,
+                caret,
+            });
+        }
+        let pair = PAIRS
+            .iter()
+            .find(|&&(opener, _)| opener == character)
+            .filter(|&&(opener, closer)| {
+                opener != closer || !previous.is_some_and(char::is_alphanumeric)
+            });
+        let Some(&(_, closer)) = pair else {
+            return buffer
+                .insert(location, &character.to_string())
+                .map(|caret| CaretEdit::to_end(location, caret));
+        };
+        let new_end = buffer.insert(location, &format!("{character}{closer}"))?;
+        let caret = Location {
+            x: location.x.saturating_add(1),
+            y: location.y,
+        };
+        buffer.add_auto_closer(caret);
+        Some(CaretEdit {
+            old_end: location,
+            new_end,
+            caret,
+        })
+    }
+
+    /// Deletes both brackets or quotes of an empty pair around `location`
+    /// whose closer was inserted along with the opener.
+    fn delete_empty_pair(location: Location, buffer: &mut Buffer) -> Option<CaretEdit> {
+        if !buffer.is_auto_closer(location) {
+            return None;
+        }
+        let start = Location {
+            x: location.x.checked_sub(1)?,
+            y: location.y,
+        };
+        let end = Location {
+            x: location.x.saturating_add(1),
+            y: location.y,
+        };
+        let around = buffer.text_between(start, end);
+        let mut chars = around.chars();
+        let pair = (chars.next()?, chars.next()?);
+        if !PAIRS.contains(&pair) || !buffer.delete_range(start, end) {
+            return None;
+        }
+        Some(CaretEdit::to_end(end, start))
+    }
+
+    /// Replaces the grapheme cluster at `location` with `character`, or
+    /// inserts it at the end of the line. Consecutive overwrites form one
+    /// undo step, split into words like typing.
+    fn overwrite_at(location: Location, character: char, buffer: &mut Buffer) -> Option<CaretEdit> {
+        let (end, after_whitespace) = buffer.line(location.y).map_or((None, false), |line| {
+            let previous = location
+                .x
+                .checked_sub(1)
+                .map(|x| line.chars_between(x, location.x));
+            (
+                (location.x < line.len()).then(|| line.next_grapheme_boundary(location.x)),
+                previous.is_some_and(|previous| previous.starts_with(char::is_whitespace)),
+            )
+        });
+        if after_whitespace && !character.is_whitespace() {
+            buffer.end_undo_step();
+        }
+        buffer.start_continuing_undo_group(location);
+        let caret_edit = match end {
+            Some(end) => Self::replace_within_line(
+                location.y,
+                location.x..end,
+                &character.to_string(),
+                buffer,
+            ),
+            None => buffer
+                .insert(location, &character.to_string())
+                .map(|caret| CaretEdit::to_end(location, caret)),
+        };
+        buffer.end_undo_group();
+        caret_edit
+    }
+
+    /// Joins the line after `location` onto its line, replacing the line's
+    /// trailing whitespace and the next line's indentation with a single
+    /// space. No space is added if either line is blank. The caret goes to
+    /// the start of the joined text.
+    fn join_lines_at(location: Location, buffer: &mut Buffer) -> Option<CaretEdit> {
+        let y = location.y;
+        let (text_end, indentation_len, next_is_blank) = {
+            let line = buffer.line(y)?;
+            let next = buffer.line(y.saturating_add(1))?;
+            let indentation_len = next.indentation_len();
+            (
+                line.trailing_whitespace_start(),
+                indentation_len,
+                indentation_len == next.len(),
+            )
+        };
+        let separator = if text_end == 0 || next_is_blank {
+            ""
+        } else {
+            " "
+        };
+        let start = Location { x: text_end, y };
+        let old_end = Location {
+            x: indentation_len,
+            y: y.saturating_add(1),
+        };
+        buffer.start_undo_group();
+        buffer.delete_range(start, old_end);
+        let new_end = if separator.is_empty() {
+            Some(start)
+        } else {
+            buffer.insert(start, separator)
+        };
+        buffer.end_undo_group();
+        new_end.map(|new_end| CaretEdit::to_end(old_end, new_end))
+    }
+
+    /// Swaps the grapheme clusters before and at `location`, or the last two
+    /// of the line at its end.
+    fn transpose_chars_at(location: Location, buffer: &mut Buffer) -> Option<CaretEdit> {
+        let y = location.y;
+        let line = buffer.line(y)?;
+        let x = if location.x >= line.len() {
+            line.previous_grapheme_boundary(line.len())
+        } else {
+            location.x
+        };
+        let start = line.previous_grapheme_boundary(x);
+        let end = line.next_grapheme_boundary(x);
+        if start == x || x == end {
+            return None;
+        }
+        let text = format!(
+            "{}{}",
+            line.chars_between(x, end),
+            line.chars_between(start, x)
+        );
+        drop(line);
+        Self::replace_within_line(y, start..end, &text, buffer)
+    }
+
+    /// Swaps the last word ending at or before `location` with the first
+    /// word starting at or after it. Within a word, the caret counts as
+    /// being at its end.
+    fn transpose_words_at(location: Location, buffer: &mut Buffer) -> Option<CaretEdit> {
+        let y = location.y;
+        let line = buffer.line(y)?;
+        let words = line.words();
+        let x = words
+            .iter()
+            .find(|word| word.start < location.x && location.x < word.end)
+            .map_or(location.x, |word| word.end);
+        let first = words.iter().rev().find(|word| word.end <= x)?;
+        let second = words.iter().find(|word| word.start >= x)?;
+        let text = format!(
+            "{}{}{}",
+            line.chars_between(second.start, second.end),
+            line.chars_between(first.end, second.start),
+            line.chars_between(first.start, first.end)
+        );
+        let range = first.start..second.end;
+        drop(line);
+        Self::replace_within_line(y, range, &text, buffer)
+    }
 
-use self::line::Line;
-use buffer::Buffer;
-use location::Location;
+    /// Adds `amount` to the first number on the line of `location` which
+    /// ends after it. The caret goes to the number's last digit.
+    fn add_to_number_at(location: Location, amount: i64, buffer: &mut Buffer) -> Option<CaretEdit> {
+        let y = location.y;
+        let text = buffer.line(y)?.to_string();
+        let chars: Vec<char> = text.chars().collect();
+        let range = number_ranges(&chars)
+            .into_iter()
+            .find(|range| range.end > location.x)?;
+        let number: String = chars[range.clone()].iter().collect();
+        let replacement = added_to_number(&number, amount)?;
+        let caret_edit = Self::replace_within_line(y, range, &replacement, buffer)?;
+        Some(CaretEdit {
+            caret: Location {
+                x: caret_edit.caret.x.saturating_sub(1),
+                y,
+            },
+            ..caret_edit
+        })
+    }
+
+    /// Replaces the characters `range` of line `y` with `text`, as one undo step.
+    fn replace_within_line(
+        y: usize,
+        range: Range<usize>,
+        text: &str,
+        buffer: &mut Buffer,
+    ) -> Option<CaretEdit> {
+        let start = Location { x: range.start, y };
+        let end = Location { x: range.end, y };
+        buffer.start_undo_group();
+        buffer.delete_range(start, end);
+        let caret = buffer.insert(start, text);
+        buffer.end_undo_group();
+        caret.map(|caret| CaretEdit::to_end(end, caret))
+    }
+
+    /// Starts selecting a block at the caret if none is selected, and moves
+    /// its corner at the caret. Left and Right move by one screen column,
+    /// also beyond the end of the line, so blocks can reach past short lines.
+    fn select_block(&mut self, direction: &Direction, buffer: &Buffer) {
+        if self.block_anchor.is_none() {
+            self.block_anchor = Some(Location {
+                x: self.caret_col,
+                y: self.location.y,
+            });
+            self.block_col = self.caret_col;
+            self.selection_anchor = None;
+            self.extra_carets.clear();
+        }
+        let last_line = buffer.height().saturating_sub(1);
+        let mut y = self.location.y;
+        match direction {
+            Direction::Up | Direction::ParagraphUp => y = y.saturating_sub(1),
+            Direction::Down | Direction::ParagraphDown => {
+                y = min(y.saturating_add(1), last_line);
+            }
+            Direction::PageUp => y = y.saturating_sub(self.size.height.saturating_sub(1)),
+            Direction::PageDown => {
+                y = min(
+                    y.saturating_add(self.size.height.saturating_sub(1)),
+                    last_line,
+                );
+            }
+            Direction::HalfPageUp => y = y.saturating_sub(self.half_page()),
+            Direction::HalfPageDown => y = min(y.saturating_add(self.half_page()), last_line),
+            Direction::Left | Direction::WordLeft => {
+                self.block_col = self.block_col.saturating_sub(1);
+            }
+            Direction::Right | Direction::WordRight => {
+                self.block_col = self.block_col.saturating_add(1);
+            }
+            Direction::Home => self.block_col = 0,
+            Direction::End => {
+                self.block_col = buffer
+                    .line(y)
+                    .map_or(0, |line| line.column_of(line.len(), self.tab_width));
+            }
+        }
+        let x = buffer.line(y).map_or(0, |line| {
+            line.index_at_column(self.block_col, self.tab_width)
+        });
+        self.location = Location { x, y };
+        self.snap_location(buffer);
+        self.caret_col = self.block_col;
+        self.scroll_location_into_view(buffer);
+        self.needs_redraw = true;
+    }
+
+    /// The lines and the screen columns of the selected block, if there is one.
+    fn block(&self) -> Option<(Range<usize>, Range<usize>)> {
+        let anchor = self.block_anchor?;
+        let lines = min(anchor.y, self.location.y)..anchor.y.max(self.location.y).saturating_add(1);
+        let cols = min(anchor.x, self.block_col)..anchor.x.max(self.block_col);
+        Some((lines, cols))
+    }
+
+    /// The start and end of the selection, in order, if anything is selected.
+    fn selection(&self) -> Option<(Location, Location)> {
+        let anchor = self.selection_anchor?;
+        let caret = self.location;
+        match (anchor.y, anchor.x).cmp(&(caret.y, caret.x)) {
+            Ordering::Less => Some((anchor, caret)),
+            Ordering::Greater => Some((caret, anchor)),
+            Ordering::Equal => None,
+        }
+    }
+
+    /// The lines Tab and Shift+Tab indent or dedent: those in the selection,
+    /// or else those with a caret. A selection ending at the start of a
+    /// line doesn't include that line.
+    fn lines_to_indent(&self) -> Vec<usize> {
+        if let Some((start, end)) = self.selection() {
+            let last = if end.x == 0 && end.y > start.y {
+                end.y.saturating_sub(1)
+            } else {
+                end.y
+            };
+            return (start.y..=last).collect();
+        }
+        let mut lines: Vec<usize> = once(self.location)
+            .chain(self.extra_carets.iter().copied())
+            .map(|caret| caret.y)
+            .collect();
+        lines.sort_unstable();
+        lines.dedup();
+        lines
+    }
+
+    /// Indents the non-empty lines to indent by one level, as one undo step.
+    fn indent_lines(&mut self, buffer: &mut Buffer) {
+        let unit = self.indent_unit();
+        let width = unit.chars().count();
+        buffer.start_undo_group();
+        for y in self.lines_to_indent() {
+            if buffer.line(y).is_some_and(|line| line.len() > 0)
+                && buffer.insert(Location { x: 0, y }, &unit).is_some()
+            {
+                self.shift_carets_on_line(y, |x| if x > 0 { x.saturating_add(width) } else { 0 });
+            }
+        }
+        buffer.end_undo_group();
+        self.snap_location(buffer);
+        self.needs_redraw = true;
+    }
+
+    /// Removes a tab or up to a tab width of spaces from the start of each
+    /// line to indent, as one undo step.
+    fn dedent_lines(&mut self, buffer: &mut Buffer) {
+        buffer.start_undo_group();
+        for y in self.lines_to_indent() {
+            let Some(line) = buffer.line(y) else {
+                continue;
+            };
+            let leading = line.chars_between(0, self.tab_width);
+            let width = if leading.starts_with('\t') {
+                1
+            } else {
+                leading
+                    .chars()
+                    .take_while(|&character| character == ' ')
+                    .count()
+            };
+            if width > 0 && buffer.delete_range(Location { x: 0, y }, Location { x: width, y }) {
+                self.shift_carets_on_line(y, |x| x.saturating_sub(width));
+            }
+        }
+        buffer.end_undo_group();
+        self.snap_location(buffer);
+        self.needs_redraw = true;
+    }
+
+    /// Cuts the selection, or the caret's line including its line break,
+    /// into the kill ring. If the previous command was a cut as well, the
+    /// text is added to the ring's newest entry. Extra carets are removed.
+    fn kill(&mut self, continues: bool, buffer: &mut Buffer) {
+        let selected = self.selection();
+        let (start, end, text) = if let Some((start, end)) = selected {
+            (start, end, buffer.text_between(start, end))
+        } else {
+            let y = self.location.y;
+            let Some(line) = buffer.line(y) else {
+                return;
+            };
+            let text = format!("{line}\n");
+            let end_of_line = Location { x: line.len(), y };
+            drop(line);
+            if y.saturating_add(1) < buffer.height() {
+                (
+                    Location { x: 0, y },
+                    Location {
+                        x: 0,
+                        y: y.saturating_add(1),
+                    },
+                    text,
+                )
+            } else if let Some(above) = y.checked_sub(1) {
+                // The last line has no line break of its own, so the one
+                // before it goes instead.
+                let end_of_above = Location {
+                    x: buffer.line(above).map_or(0, |line| line.len()),
+                    y: above,
+                };
+                (end_of_above, end_of_line, text)
+            } else {
+                (Location { x: 0, y }, end_of_line, text)
+            }
+        };
+        buffer.end_undo_step();
+        if !buffer.delete_range(start, end) {
+            return;
+        }
+        buffer.end_undo_step();
+        match self.kill_ring.first_mut() {
+            Some(newest) if continues => newest.push_str(&text),
+            _ => self.push_to_kill_ring(text),
+        }
+        self.kill_continues = true;
+        self.extra_carets.clear();
+        self.selection_anchor = None;
+        self.location = Location {
+            x: if selected.is_some() { start.x } else { 0 },
+            y: start.y,
+        };
+        self.snap_location(buffer);
+        self.needs_redraw = true;
+    }
+
+    /// Copies the selection, or the caret's line including its line break,
+    /// into the kill ring, and drops the selection.
+    fn copy(&mut self, buffer: &Buffer) {
+        let text = if let Some((start, end)) = self.selection() {
+            buffer.text_between(start, end)
+        } else if let Some(line) = buffer.line(self.location.y) {
+            format!("{line}\n")
+        } else {
+            return;
+        };
+        self.push_to_kill_ring(text);
+        if self.selection_anchor.take().is_some() {
+            self.needs_redraw = true;
+        }
+    }
+
+    fn push_to_kill_ring(&mut self, text: String) {
+        self.kill_ring.insert(0, text);
+        self.kill_ring.truncate(KILL_RING_SIZE);
+    }
+
+    /// The texts which can be pasted, newest first.
+    pub fn kill_ring(&self) -> &[String] {
+        &self.kill_ring
+    }
+
+    /// Inserts a copy of the selection after it and selects the copy, or
+    /// copies the caret's line below it and moves the caret along. The copy
+    /// is an undo step of its own. Extra carets are removed.
+    fn duplicate(&mut self, buffer: &mut Buffer) {
+        let (at, text) = if let Some((start, end)) = self.selection() {
+            (end, buffer.text_between(start, end))
+        } else {
+            let Some(line) = buffer.line(self.location.y) else {
+                return;
+            };
+            let at = Location {
+                x: line.len(),
+                y: self.location.y,
+            };
+            (at, format!("\n{line}"))
+        };
+        buffer.end_undo_step();
+        let Some(caret) = buffer.insert(at, &text) else {
+            return;
+        };
+        buffer.end_undo_step();
+        self.extra_carets.clear();
+        if self.selection_anchor.is_some() {
+            self.selection_anchor = Some(at);
+            self.location = caret;
+        } else {
+            self.location.y = self.location.y.saturating_add(1);
+        }
+        self.snap_location(buffer);
+        self.needs_redraw = true;
+    }
+
+    /// Swaps the selected lines, or the line with the caret, with the line
+    /// above or below them, as one undo step. The caret and the selection
+    /// move along with the lines; extra carets are removed.
+    fn move_lines(&mut self, down: bool, buffer: &mut Buffer) {
+        let lines = match self.selection() {
+            Some(_) => self.lines_to_indent(),
+            None => vec![self.location.y],
+        };
+        let (Some(&first), Some(&last)) = (lines.first(), lines.last()) else {
+            return;
+        };
+        let line_len = |buffer: &Buffer, y: usize| buffer.line(y).map_or(0, |line| line.len());
+        buffer.start_undo_group();
+        let moved = if down {
+            let below = last.saturating_add(1);
+            if below >= buffer.height() {
+                None
+            } else {
+                let text = buffer.line(below).map(|line| line.to_string());
+                let end_of_block = Location {
+                    x: line_len(buffer, last),
+                    y: last,
+                };
+                let end_of_below = Location {
+                    x: line_len(buffer, below),
+                    y: below,
+                };
+                buffer.delete_range(end_of_block, end_of_below);
+                text.and_then(|text| {
+                    buffer.insert(Location { x: 0, y: first }, &format!("{text}\n"))
+                })
+            }
+        } else if let Some(above) = first.checked_sub(1) {
+            let text = buffer.line(above).map(|line| line.to_string());
+            buffer.delete_range(Location { x: 0, y: above }, Location { x: 0, y: first });
+            let end_of_block = Location {
+                x: line_len(buffer, above.saturating_add(last).saturating_sub(first)),
+                y: above.saturating_add(last).saturating_sub(first),
+            };
+            text.and_then(|text| buffer.insert(end_of_block, &format!("\n{text}")))
+        } else {
+            None
+        };
+        buffer.end_undo_group();
+        if moved.is_none() {
+            return;
+        }
+        self.extra_carets.clear();
+        let carets = once(&mut self.location).chain(self.selection_anchor.iter_mut());
+        for caret in carets {
+            caret.y = if down {
+                caret.y.saturating_add(1)
+            } else {
+                caret.y.saturating_sub(1)
+            };
+        }
+        self.snap_location(buffer);
+        self.needs_redraw = true;
+    }
+
+    /// Changes the case of the selection, or of the word at the caret, as
+    /// one undo step. The selection stays selected.
+    fn change_case(&mut self, case: Case, buffer: &mut Buffer) {
+        let range = self.selection().or_else(|| {
+            let y = self.location.y;
+            let word = buffer.line(y)?.word_around(self.location.x)?;
+            Some((Location { x: word.start, y }, Location { x: word.end, y }))
+        });
+        let Some((start, end)) = range else {
+            return;
+        };
+        let text = buffer.text_between(start, end);
+        let changed = match case {
+            Case::Upper => text.to_uppercase(),
+            Case::Lower => text.to_lowercase(),
+            Case::Title => title_case(&text),
+        };
+        if changed == text {
+            return;
+        }
+        buffer.start_undo_group();
+        buffer.delete_range(start, end);
+        let new_end = buffer.insert(start, &changed);
+        buffer.end_undo_group();
+        // Upper-casing may make the text longer, e.g. 'ß' becomes "SS".
+        if let (Some(anchor), Some(new_end)) = (self.selection_anchor, new_end) {
+            if anchor == start {
+                self.location = new_end;
+            } else {
+                self.selection_anchor = Some(new_end);
+            }
+        }
+        self.snap_location(buffer);
+        self.needs_redraw = true;
+    }
+
+    /// Sorts the selected lines, or those with a caret, among themselves,
+    /// or all lines if there aren't several of them, as one undo step.
+    fn sort_lines(&mut self, order: SortOrder, buffer: &mut Buffer) {
+        let mut lines = self.lines_to_indent();
+        if lines.len() < 2 {
+            lines = (0..buffer.height()).collect();
+        }
+        let texts: Vec<String> = lines
+            .iter()
+            .map_while(|&y| buffer.line(y).map(|line| line.to_string()))
+            .collect();
+        if texts.len() < lines.len() {
+            return;
+        }
+        let mut sorted: Vec<&str> = texts.iter().map(String::as_str).collect();
+        match order {
+            SortOrder::Lexicographic => sorted.sort_unstable(),
+            SortOrder::Numeric => {
+                sorted.sort_by(|a, b| leading_number(a).cmp(&leading_number(b)));
+            }
+            SortOrder::Unique => {
+                sorted.sort_unstable();
+                sorted.dedup();
+            }
+        }
+        if sorted == texts {
+            return;
+        }
+        buffer.start_undo_group();
+        // From the bottom up, so the lines still to change keep their
+        // indices. Lines left over after removing duplicates are deleted.
+        for (index, (&y, old)) in lines.iter().zip(&texts).enumerate().rev() {
+            let line_start = Location { x: 0, y };
+            let line_end = Location {
+                x: old.chars().count(),
+                y,
+            };
+            match sorted.get(index) {
+                Some(&new) if new == old => {}
+                Some(&new) => {
+                    buffer.delete_range(line_start, line_end);
+                    buffer.insert(line_start, new);
+                }
+                // Never the first line, so there's one above to join onto.
+                None => {
+                    let above = y.saturating_sub(1);
+                    let start = Location {
+                        x: buffer.line(above).map_or(0, |line| line.len()),
+                        y: above,
+                    };
+                    buffer.delete_range(start, line_end);
+                }
+            }
+        }
+        buffer.end_undo_group();
+        self.extra_carets.clear();
+        self.snap_location(buffer);
+        self.needs_redraw = true;
+    }
+
+    /// Comments out the lines to indent with the file type's comment leader,
+    /// placed at their common indentation, or removes the leaders if all of
+    /// them are comments already. Blank lines are left alone.
+    fn toggle_comment(&mut self, buffer: &mut Buffer) {
+        let Some(leader) = buffer.file_type().comment_leader() else {
+            return;
+        };
+        let leader_len = leader.chars().count();
+        let mut lines = Vec::new();
+        for y in self.lines_to_indent() {
+            let Some(line) = buffer.line(y) else {
+                continue;
+            };
+            let indentation_len = line.indentation_len();
+            if indentation_len < line.len() {
+                let rest = line.chars_between(indentation_len, line.len());
+                lines.push((y, indentation_len, rest));
+            }
+        }
+        let Some(common_indentation) = lines.iter().map(|(_, indentation, _)| *indentation).min()
+        else {
+            return;
+        };
+        let commented = lines.iter().all(|(_, _, rest)| rest.starts_with(leader));
+        buffer.start_undo_group();
+        for (y, indentation_len, rest) in lines {
+            if commented {
+                let len = if rest[leader.len()..].starts_with(' ') {
+                    leader_len.saturating_add(1)
+                } else {
+                    leader_len
+                };
+                let start = Location {
+                    x: indentation_len,
+                    y,
+                };
+                let end = Location {
+                    x: indentation_len.saturating_add(len),
+                    y,
+                };
+                if buffer.delete_range(start, end) {
+                    self.shift_carets_on_line(y, |x| {
+                        x.saturating_sub(min(len, x.saturating_sub(indentation_len)))
+                    });
+                }
+            } else {
+                let at = Location {
+                    x: common_indentation,
+                    y,
+                };
+                if buffer.insert(at, &format!("{leader} ")).is_some() {
+                    let len = leader_len.saturating_add(1);
+                    self.shift_carets_on_line(y, |x| {
+                        if x >= common_indentation {
+                            x.saturating_add(len)
+                        } else {
+                            x
+                        }
+                    });
+                }
+            }
+        }
+        buffer.end_undo_group();
+        self.snap_location(buffer);
+        self.needs_redraw = true;
+    }
+
+    /// Moves the carets and the selection anchor on line `y` to `shift(x)`.
+    fn shift_carets_on_line(&mut self, y: usize, shift: impl Fn(usize) -> usize) {
+        let carets = once(&mut self.location)
+            .chain(self.selection_anchor.iter_mut())
+            .chain(self.extra_carets.iter_mut());
+        for caret in carets.filter(|caret| caret.y == y) {
+            caret.x = shift(caret.x);
+        }
+    }
+
+    /// The indentation for a line split off at `location`: that of the line
+    /// before the caret, one level deeper after one of `indent_after`.
+    fn new_line_indentation(&self, location: Location, buffer: &Buffer) -> String {
+        if !self.auto_indent {
+            return String::new();
+        }
+        let Some(line) = buffer.line(location.y) else {
+            return String::new();
+        };
+        let before = line.chars_between(0, location.x);
+        let mut indentation: String = before
+            .chars()
+            .take_while(|&character| character == ' ' || character == '\t')
+            .collect();
+        let opens_block = before
+            .trim_end()
+            .chars()
+            .last()
+            .is_some_and(|last| self.indent_after.contains(last));
+        if opens_block {
+            indentation.push_str(&self.indent_unit());
+        }
+        indentation
+    }
+
+    /// What one level of indentation consists of.
+    fn indent_unit(&self) -> String {
+        if self.expand_tab {
+            " ".repeat(self.tab_width)
+        } else {
+            "\t".to_string()
+        }
+    }
+
+    /// Adds a caret above the topmost or below the bottommost caret.
+    fn add_caret_vertically(&mut self, direction: &Direction, buffer: &Buffer) {
+        let carets = once(self.location).chain(self.extra_carets.iter().copied());
+        let outermost = match direction {
+            Direction::Up => carets.min_by_key(|caret| (caret.y, caret.x)),
+            _ => carets.max_by_key(|caret| (caret.y, caret.x)),
+        };
+        let Some(outermost) = outermost else {
+            return;
+        };
+        let y = match direction {
+            Direction::Up => outermost.y.checked_sub(1),
+            _ => Some(outermost.y.saturating_add(1)).filter(|&y| y < buffer.height()),
+        };
+        if let Some(y) = y {
+            self.add_caret(Location { x: outermost.x, y }, buffer);
+        }
+    }
+
+    /// Adds a caret at the next occurrence of the word at the newest caret,
+    /// at the same position within the word, wrapping around at the end of
+    /// the buffer.
+    fn add_caret_at_next_match(&mut self, buffer: &Buffer) {
+        let Location { x, y } = self.location;
+        let Some((word, range)) = buffer.line(y).and_then(|line| {
+            let range = line.word_around(x)?;
+            Some((line.chars_between(range.start, range.end), range))
+        }) else {
+            return;
+        };
+        let offset = x.saturating_sub(range.start);
+        let height = buffer.height();
+        let mut from = range.end;
+        for step in 0..=height {
+            let line_index = y.saturating_add(step).checked_rem(height).unwrap_or(0);
+            let Some(line) = buffer.line(line_index) else {
+                continue;
+            };
+            while let Some(start) = line.find_word(&word, from) {
+                let caret = Location {
+                    x: start.saturating_add(offset),
+                    y: line_index,
+                };
+                if caret != self.location && !self.extra_carets.contains(&caret) {
+                    self.add_caret(caret, buffer);
+                    return;
+                }
+                from = start.saturating_add(1);
+            }
+            from = 0;
+        }
+    }
+
+    /// Makes `location` the newest caret, keeping the others.
+    fn add_caret(&mut self, location: Location, buffer: &Buffer) {
+        self.extra_carets.push(self.location);
+        self.location = location;
+        self.snap_location(buffer);
+        self.needs_redraw = true;
+    }
+
+    /// Sets how tabs are shown and what the Tab key inserts.
+    pub fn set_tabs(&mut self, tab_width: usize, expand_tab: bool) {
+        self.tab_width = tab_width;
+        self.expand_tab = expand_tab;
+        self.needs_redraw = true;
+    }
+
+    pub fn set_abbreviations(&mut self, abbreviations: HashMap<String, String>) {
+        self.abbreviations = abbreviations;
+    }
+
+    pub fn set_snippets(&mut self, snippets: Vec<Snippet>) {
+        self.snippets = snippets;
+    }
+
+    pub fn set_scrolloff(&mut self, scrolloff: usize) {
+        self.scrolloff = scrolloff;
+    }
+
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+        self.needs_redraw = true;
+    }
+
+    /// Shows the welcome screen while the buffer is empty, e.g. when no
+    /// file was opened.
+    pub fn show_welcome(&mut self) {
+        self.welcome = true;
+        self.needs_redraw = true;
+    }
+
+    /// Stops showing the welcome screen, e.g. once a key is pressed.
+    pub fn hide_welcome(&mut self) {
+        if self.welcome {
+            self.welcome = false;
+            self.needs_redraw = true;
+        }
+    }
+
+    pub fn set_line_numbers(&mut self, line_numbers: bool, relative: bool) {
+        self.line_numbers = line_numbers;
+        self.relative_line_numbers = relative;
+        self.needs_redraw = true;
+    }
+
+    pub fn set_auto_close(&mut self, auto_close: bool) {
+        self.auto_close = auto_close;
+    }
+
+    /// Sets how new lines are indented.
+    pub fn set_auto_indent(&mut self, auto_indent: bool, indent_after: &str) {
+        self.auto_indent = auto_indent;
+        self.indent_after = indent_after.to_string();
+    }
+
+    /// Switches to showing `buffer`, placing the caret where it was when
+    /// the buffer was last shown.
+    pub fn show_buffer(&mut self, buffer: &Buffer) {
+        self.location = buffer.last_location;
+        self.snippet_stops.clear();
+        self.extra_carets.clear();
+        self.selection_anchor = None;
+        self.block_anchor = None;
+        self.jumps.clear();
+        self.jump_index = 0;
+        self.scroll_offset = Location::default();
+        self.top_row = 0;
+        self.snap_location(buffer);
+        self.needs_redraw = true;
+    }
+
+    /// Marks the view for a redraw, e.g. after the buffer was changed
+    /// from outside the view.
+    pub fn mark_redraw(&mut self) {
+        self.needs_redraw = true;
+    }
+
+    /// Whether the next `render` will draw the view.
+    pub const fn needs_redraw(&self) -> bool {
+        self.needs_redraw
+    }
+
+    /// Makes the next cut start a new entry of the kill ring, e.g. after a
+    /// command the view doesn't see came in between.
+    pub fn end_kill_sequence(&mut self) {
+        self.kill_continues = false;
+    }
+
+    /// Takes over the kill ring of `other`, the view which had the focus
+    /// before this one, as the kill ring is shared by all windows.
+    pub fn take_kill_ring(&mut self, other: &mut Self) {
+        self.kill_ring = std::mem::take(&mut other.kill_ring);
+    }
+
+    /// How many lines of the buffer must be available to render the view
+    /// and to move the caret by up to a page from its current location.
+    pub fn lines_needed(&self) -> usize {
+        let page = self.size.height.saturating_mul(2);
+        self.scroll_offset
+            .y
+            .max(self.location.y)
+            .saturating_add(page)
+    }
+
+    /// The word at or right before the caret, e.g. to look it up.
+    pub fn word_at_caret(&self, buffer: &Buffer) -> Option<String> {
+        let line = buffer.line(self.location.y)?;
+        let word = line.word_around(self.location.x)?;
+        Some(line.chars_between(word.start, word.end))
+    }
+
+    /// The path-like text at or right before the caret: everything up to
+    /// whitespace, quotes, brackets and separators such as `,` and `;`.
+    pub fn path_at_caret(&self, buffer: &Buffer) -> Option<String> {
+        let line = buffer.line(self.location.y)?;
+        let path = line.run_around(self.location.x, |character| {
+            !character.is_whitespace() && !"\"'`<>()[]{},;|".contains(character)
+        })?;
+        Some(line.chars_between(path.start, path.end))
+    }
+
+    /// Starts searching from the caret in `direction`, or within the
+    /// selection from its start or end if there is one.
+    pub fn start_search(&mut self, direction: SearchDirection) {
+        self.search_origin = Some((self.location, self.scroll_offset));
+        self.search_direction = direction;
+        self.search_region = self.selection();
+    }
+
+    /// Whether the search only finds matches within the selection.
+    pub const fn searches_selection(&self) -> bool {
+        self.search_region.is_some()
+    }
+
+    /// Moves the caret to the nearest match of `pattern` from where the
+    /// search started, wrapping around the end of the buffer, and highlights
+    /// it, or back to where the search started if there is none. Returns
+    /// whether the search wrapped around if there is a match.
+    pub fn search(&mut self, pattern: &Pattern, buffer: &Buffer) -> Option<bool> {
+        let (origin, _) = self.search_origin?;
+        let from = match (self.search_region, self.search_direction) {
+            (Some((start, _)), SearchDirection::Forward) => start,
+            (Some((_, end)), SearchDirection::Backward) => end,
+            (None, _) => origin,
+        };
+        let found = search::find_wrapping(
+            buffer,
+            pattern,
+            from,
+            self.search_direction,
+            self.search_region,
+        );
+        self.search_match = found.map(|(found, _)| found);
+        self.search_pattern = Some(pattern.clone());
+        self.move_to(
+            self.search_match.map_or(origin, |found| found.start),
+            buffer,
+        );
+        found.map(|(_, wrapped)| wrapped)
+    }
+
+    /// Leaves the caret at the match, remembering where the search started
+    /// in the jump list.
+    pub fn finish_search(&mut self, buffer: &Buffer) {
+        self.search_match = None;
+        self.search_region = None;
+        self.needs_redraw = true;
+        if let Some((origin, _)) = self.search_origin.take() {
+            let found = self.location;
+            if found != origin {
+                self.location = origin;
+                self.go_to(found, buffer);
+            }
+        }
+    }
+
+    /// Goes to the nearest match of `pattern` in `direction` which doesn't
+    /// start at the caret, wrapping around the end of the buffer, remembering
+    /// where the caret was in the jump list, and highlights the matches
+    /// again. Returns whether the search wrapped around if there is a match.
+    pub fn find_next(
+        &mut self,
+        pattern: &Pattern,
+        direction: SearchDirection,
+        buffer: &Buffer,
+    ) -> Option<bool> {
+        let from = match direction {
+            SearchDirection::Forward => Location {
+                x: self.location.x.saturating_add(1),
+                ..self.location
+            },
+            SearchDirection::Backward => self.location,
+        };
+        self.search_pattern = Some(pattern.clone());
+        self.needs_redraw = true;
+        let (found, wrapped) = search::find_wrapping(buffer, pattern, from, direction, None)?;
+        self.go_to(found.start, buffer);
+        Some(wrapped)
+    }
+
+    /// Starts going through matches to replace them, within the selection
+    /// if there is one. Returns where to look for the first match: at the
+    /// start of the selection, or else at the caret.
+    pub fn start_replace(&mut self) -> Location {
+        self.search_region = self.selection();
+        self.search_region.map_or(self.location, |(start, _)| start)
+    }
+
+    /// Moves the caret to the first match of `pattern` at or after `from`
+    /// and highlights it, e.g. to ask whether to replace it. Returns
+    /// whether there is one.
+    pub fn show_next_match(&mut self, pattern: &Pattern, from: Location, buffer: &Buffer) -> bool {
+        self.search_match = search::find(
+            buffer,
+            pattern,
+            from,
+            SearchDirection::Forward,
+            self.search_region,
+        );
+        self.search_pattern = Some(pattern.clone());
+        self.needs_redraw = true;
+        let Some(found) = self.search_match else {
+            return false;
+        };
+        self.move_to(found.start, buffer);
+        true
+    }
+
+    /// Replaces the whole highlighted match of `pattern` with `replacement`,
+    /// in which `$1` and the like stand for its groups, as one undo step
+    /// and moves the caret past it. Returns where to look for the next
+    /// match: after the replacement, and a character further for an empty
+    /// match so it isn't found again.
+    pub fn replace_match(
+        &mut self,
+        pattern: &Pattern,
+        replacement: &str,
+        buffer: &mut Buffer,
+    ) -> Location {
+        let Some(Match { start, .. }) = self.search_match.take() else {
+            return self.location;
+        };
+        let Some((range, replacement)) = buffer.line(start.y).and_then(|line| {
+            search::replacement_at(&line.to_string(), pattern, start.x, replacement)
+        }) else {
+            return self.location;
+        };
+        let end = Location {
+            x: range.end,
+            y: start.y,
+        };
+        buffer.start_undo_group();
+        buffer.delete_range(start, end);
+        let after = buffer.insert(start, &replacement).unwrap_or(start);
+        buffer.end_undo_group();
+        // The rest of the line moved along with the end of the match.
+        if let Some((_, region_end)) = &mut self.search_region {
+            if region_end.y == end.y {
+                region_end.x = region_end.x.saturating_sub(end.x).saturating_add(after.x);
+            }
+        }
+        self.move_to(after, buffer);
+        if start == end {
+            Location {
+                x: after.x.saturating_add(1),
+                ..after
+            }
+        } else {
+            after
+        }
+    }
 
-use super::{
-    editorcommand::{Direction, EditorCommand},
-    terminal::{Position, Size, Terminal},
-};
+    /// Replaces all matches of `pattern` in the selection, or in the whole
+    /// buffer if nothing is selected, with `replacement` as one undo step.
+    /// Returns how many were replaced.
+    pub fn replace_all(
+        &mut self,
+        pattern: &Pattern,
+        replacement: &str,
+        buffer: &mut Buffer,
+    ) -> usize {
+        let last = buffer.height().saturating_sub(1);
+        let (start, end) = self.selection().unwrap_or((
+            Location::default(),
+            Location {
+                x: buffer.line(last).map_or(0, |line| line.len()),
+                y: last,
+            },
+        ));
+        let mut count: usize = 0;
+        buffer.start_undo_group();
+        for y in start.y..=end.y {
+            let Some(line_len) = buffer.line(y).map(|line| line.len()) else {
+                break;
+            };
+            let from = Location {
+                x: if y == start.y { start.x } else { 0 },
+                y,
+            };
+            let to = Location {
+                x: if y == end.y { end.x } else { line_len },
+                y,
+            };
+            let text = buffer.text_between(from, to);
+            let (replaced, found) = search::replace_all(&text, pattern, replacement);
+            if replaced != text {
+                buffer.delete_range(from, to);
+                buffer.insert(from, &replaced);
+            }
+            count = count.saturating_add(found);
+        }
+        buffer.end_undo_group();
+        self.selection_anchor = None;
+        self.extra_carets.clear();
+        self.snap_location(buffer);
+        self.needs_redraw = true;
+        count
+    }
 
-const NAME: &str = env!("CARGO_PKG_NAME");
-const VERSION: &str = env!("CARGO_PKG_VERSION");
+    /// Stops highlighting the match asked about, leaving the caret there.
+    pub fn finish_replace(&mut self) {
+        self.search_match = None;
+        self.search_region = None;
+        self.needs_redraw = true;
+    }
 
-pub struct View {
-    buffer: Buffer,
-    needs_redraw: bool,
-    size: Size,
-    location: Location,
-    scroll_offset: Location,
-}
+    /// Goes back to where the search started, without highlights, and
+    /// selects what was selected then again.
+    pub fn cancel_search(&mut self, buffer: &Buffer) {
+        self.search_match = None;
+        self.search_pattern = None;
+        let region = self.search_region.take();
+        if let Some((origin, scroll_offset)) = self.search_origin.take() {
+            self.scroll_offset = scroll_offset;
+            self.top_row = 0;
+            self.move_to(origin, buffer);
+            self.selection_anchor =
+                region.map(|(start, end)| if start == origin { end } else { start });
+        }
+        self.needs_redraw = true;
+    }
 
-impl View {
-    pub fn render(&mut self) {
-        if !self.needs_redraw {
-            return;
+    /// Like `move_to`, but remembers where the caret was in the jump list,
+    /// dropping the jumps `JumpBack` went back over.
+    pub fn go_to(&mut self, location: Location, buffer: &Buffer) {
+        self.jumps.truncate(self.jump_index);
+        if self.jumps.last() != Some(&self.location) {
+            self.jumps.push(self.location);
+        }
+        if self.jumps.len() > JUMP_LIST_SIZE {
+            self.jumps.remove(0);
         }
+        self.jump_index = self.jumps.len();
+        self.move_to(location, buffer);
+    }
 
-        let Size { height, width } = self.size;
-        if height == 0 || width == 0 {
+    /// Goes back to the location before the last jump, or the one before
+    /// the jump `JumpBack` last went back to.
+    fn jump_back(&mut self, buffer: &Buffer) {
+        let Some(previous) = self.jump_index.checked_sub(1) else {
             return;
+        };
+        // Remember where the caret is now, so `JumpForward` can return here.
+        if self.jump_index == self.jumps.len() {
+            self.jumps.push(self.location);
         }
-        // we allow this since we don't care if our welcome message is put _exactly_ in the middle.
-        // it's allowed to be a bit too far up or down
-        #[allow(clippy::integer_division)]
-        let vertical_center = height / 3;
-        let top = self.scroll_offset.y;
+        self.jump_index = previous;
+        self.move_to(self.jumps[previous], buffer);
+    }
 
-        for current_row in 0..height {
-            if let Some(line) = self.buffer.lines.get(current_row.saturating_add(top)) {
-                let left = self.scroll_offset.x;
-                let right = self.scroll_offset.x.saturating_add(width);
+    /// Moves the caret to `location`, or as close to it as the text
+    /// allows, dropping the selection and extra carets.
+    fn move_to(&mut self, location: Location, buffer: &Buffer) {
+        self.location = location;
+        self.extra_carets.clear();
+        self.selection_anchor = None;
+        self.block_anchor = None;
+        self.snap_location(buffer);
+        self.needs_redraw = true;
+    }
 
-                Self::render_line(current_row, &line.get(left..right));
-            } else if current_row == vertical_center && self.buffer.is_empty() {
-                Self::render_line(current_row, &Self::build_welcome_message(width));
-            } else {
-                Self::render_line(current_row, "~");
-            }
-        }
-        self.needs_redraw = false;
+    pub const fn is_overwrite(&self) -> bool {
+        self.overwrite
     }
 
-    pub fn handle_command(&mut self, command: EditorCommand) {
-        match command {
-            EditorCommand::Resize(size) => self.resize(size),
-            EditorCommand::Move(direction) => self.move_text_location(&direction),
-            EditorCommand::Quit => {}
-        }
+    pub const fn location(&self) -> Location {
+        self.location
     }
 
-    pub fn load(&mut self, file_name: &str) {
-        if let Ok(buffer) = Buffer::load(file_name) {
-            self.buffer = buffer;
-            self.needs_redraw = true;
-        }
+    pub fn get_position(&self, buffer: &Buffer) -> Position {
+        let position: Position = if self.wrap {
+            let (caret_row, col) = self.row_of(self.location, buffer);
+            let row = self.rows_between(self.top(), caret_row, self.size.height, buffer);
+            Position { col, row }
+        } else {
+            Location {
+                x: self.caret_col,
+                y: self.location.y,
+            }
+            .subtract(&self.scroll_offset)
+            .into()
+        };
+        self.screen_position(
+            position.row.saturating_add(self.origin.row),
+            position.col.saturating_add(self.gutter_width),
+        )
     }
 
-    pub fn get_position(&self) -> Position {
-        self.location.subtract(&self.scroll_offset).into()
+    /// Moves all carets in `direction`. Moving by a page or half a page
+    /// scrolls the text by as many lines as the caret moved, so it stays on
+    /// the same row. With `wrap`, carets move by rows instead of lines.
+    fn move_text_location(&mut self, direction: &Direction, buffer: &Buffer) {
+        let old_y = self.location.y;
+        let (caret_row, col) = self.row_of(self.location, buffer);
+        let goal_col = self.goal_col.take().unwrap_or(col);
+        let target_row = self
+            .wrapped_row_moved(caret_row, direction, buffer)
+            .unwrap_or_else(|| {
+                self.location = self.moved(self.location, direction, buffer);
+                (self.location.y, 0)
+            });
+        if matches!(
+            direction,
+            Direction::Up
+                | Direction::Down
+                | Direction::PageUp
+                | Direction::PageDown
+                | Direction::HalfPageUp
+                | Direction::HalfPageDown
+        ) {
+            self.location = self.location_in_row(target_row, goal_col, buffer);
+            self.goal_col = Some(goal_col);
+        }
+        for index in 0..self.extra_carets.len() {
+            let caret = self.extra_carets[index];
+            let (row, col) = self.row_of(caret, buffer);
+            self.extra_carets[index] = self.wrapped_row_moved(row, direction, buffer).map_or_else(
+                || self.moved(caret, direction, buffer),
+                |row| self.location_in_row(row, col, buffer),
+            );
+        }
+        if matches!(
+            direction,
+            Direction::PageUp
+                | Direction::PageDown
+                | Direction::HalfPageUp
+                | Direction::HalfPageDown
+        ) {
+            if self.wrap {
+                let top = if target_row < caret_row {
+                    let moved = self.rows_between(target_row, caret_row, usize::MAX, buffer);
+                    self.row_before(self.top(), moved, buffer)
+                } else {
+                    let moved = self.rows_between(caret_row, target_row, usize::MAX, buffer);
+                    self.row_after(self.top(), moved, buffer)
+                };
+                self.set_top(top);
+            } else {
+                let top = self.scroll_offset.y;
+                let top = if self.location.y < old_y {
+                    top.saturating_sub(old_y.saturating_sub(self.location.y))
+                } else {
+                    top.saturating_add(self.location.y.saturating_sub(old_y))
+                };
+                if top != self.scroll_offset.y {
+                    self.scroll_offset.y = top;
+                    self.top_row = 0;
+                    self.needs_redraw = true;
+                }
+            }
+        }
+        self.snap_location(buffer);
+        if !self.extra_carets.is_empty() {
+            self.needs_redraw = true;
+        }
     }
 
+    /// Where a caret at `location` goes when moved in `direction`.
     // clippy::arithmetic_side_effects: This function performs arithmetic calculations
     // after explicitly checking that the target value will be within bounds
     #[allow(clippy::arithmetic_side_effects)]
-    fn move_text_location(&mut self, direction: &Direction) {
-        let Location { mut x, mut y } = self.location;
+    fn moved(&self, location: Location, direction: &Direction, buffer: &Buffer) -> Location {
+        let Location { mut x, mut y } = location;
         let Size { height, .. } = self.size;
+        let is_blank = |y: usize| buffer.line(y).is_none_or(|line| line.is_blank());
         // This match moves the position but does not check for all boundaries
         // The final boundary checking happens after the match statement
 
@@ -88,58 +2066,282 @@ impl View {
             Direction::Down => y = y.saturating_add(1),
             Direction::Left => {
                 if x > 0 {
-                    x -= 1;
+                    x = buffer
+                        .line(y)
+                        .map_or(0, |line| line.previous_grapheme_boundary(x));
                 } else if y > 0 {
                     y -= 1;
-                    x = self.buffer.lines.get(y).map_or(0, Line::len);
+                    x = buffer.line(y).map_or(0, |line| line.len());
                 }
             }
             Direction::Right => {
-                let width = self.buffer.lines.get(y).map_or(0, Line::len);
+                let width = buffer.line(y).map_or(0, |line| line.len());
                 if x < width {
-                    x += 1;
+                    x = buffer
+                        .line(y)
+                        .map_or(width, |line| line.next_grapheme_boundary(x));
                 } else {
                     y = y.saturating_add(1);
                     x = 0;
                 }
             }
-            Direction::PageUp => y = y.saturating_sub(height).saturating_add(1),
-            Direction::PageDown => y = y.saturating_add(height).saturating_sub(1),
-            Direction::Home => x = 0,
-            Direction::End => x = self.buffer.lines.get(y).map_or(0, Line::len),
+            Direction::PageUp => y = y.saturating_sub(height.saturating_sub(1)),
+            Direction::PageDown => y = y.saturating_add(height.saturating_sub(1)),
+            Direction::HalfPageUp => y = y.saturating_sub(self.half_page()),
+            Direction::HalfPageDown => y = y.saturating_add(self.half_page()),
+            Direction::Home => {
+                // The first press goes to the start of the text, the second
+                // to the start of the line.
+                let indentation = buffer.line(y).map_or(0, |line| line.indentation_len());
+                x = if x == indentation { 0 } else { indentation };
+            }
+            Direction::End => x = buffer.line(y).map_or(0, |line| line.len()),
+            Direction::WordLeft => return Self::word_left(location, buffer),
+            Direction::WordRight => return Self::word_right(location, buffer),
+            Direction::ParagraphUp => {
+                y = (0..y)
+                    .rev()
+                    .find(|&above| is_blank(above) && !is_blank(above.saturating_add(1)))
+                    .unwrap_or(0);
+                x = 0;
+            }
+            Direction::ParagraphDown => {
+                let last_line = buffer.height().saturating_sub(1);
+                y = (y.saturating_add(1)..buffer.height())
+                    .find(|&below| is_blank(below) && !is_blank(below.saturating_sub(1)))
+                    .unwrap_or(last_line);
+                x = if y == last_line && !is_blank(y) {
+                    buffer.line(y).map_or(0, |line| line.len())
+                } else {
+                    0
+                };
+            }
         }
 
-        // snap x to valid position
-        x = self
-            .buffer
-            .lines
-            .get(y)
-            .map_or(0, |line| min(line.len(), x));
+        snapped(Location { x, y }, buffer)
+    }
 
-        // snap y to valid position
-        y = min(y, self.buffer.lines.len());
+    /// The start of the last word starting before `location`. If there is
+    /// none, the start of the line, and from there the start of the last
+    /// word of the line above, or its end if it has no words.
+    fn word_left(location: Location, buffer: &Buffer) -> Location {
+        let Location { x, y } = location;
+        let start_before = |y: usize, x: usize| {
+            buffer.line(y).and_then(|line| {
+                line.word_segments()
+                    .iter()
+                    .rev()
+                    .find(|word| word.start < x)
+                    .map(|word| word.start)
+            })
+        };
+        if let Some(start) = start_before(y, x) {
+            return Location { x: start, y };
+        }
+        match y.checked_sub(1).filter(|_| x == 0) {
+            Some(above) => {
+                let end = buffer.line(above).map_or(0, |line| line.len());
+                let x = start_before(above, end).unwrap_or(end);
+                Location { x, y: above }
+            }
+            None => Location { x: 0, y },
+        }
+    }
 
-        self.location = Location { x, y };
-        self.scroll_location_into_view();
+    /// The end of the first word ending after `location`. If there is none,
+    /// the end of the line, and from there the end of the first word of the
+    /// line below, or its start if it has no words.
+    fn word_right(location: Location, buffer: &Buffer) -> Location {
+        let Location { x, y } = location;
+        let end_after = |y: usize, x: usize| {
+            buffer.line(y).and_then(|line| {
+                line.word_segments()
+                    .iter()
+                    .find(|word| word.end > x)
+                    .map(|word| word.end)
+            })
+        };
+        if let Some(end) = end_after(y, x) {
+            return Location { x: end, y };
+        }
+        let len = buffer.line(y).map_or(0, |line| line.len());
+        let below = y.saturating_add(1);
+        if x < len || below >= buffer.height() {
+            return Location { x: len, y };
+        }
+        let x = end_after(below, 0).unwrap_or(0);
+        Location { x, y: below }
+    }
+
+    /// Moves the carets back into the text if they lie outside of it, and
+    /// merges carets which ended up at the same location.
+    pub fn snap_location(&mut self, buffer: &Buffer) {
+        self.location = snapped(self.location, buffer);
+        let mut extra_carets = Vec::new();
+        for caret in self.extra_carets.drain(..) {
+            let caret = snapped(caret, buffer);
+            if caret != self.location && !extra_carets.contains(&caret) {
+                extra_carets.push(caret);
+            }
+        }
+        self.extra_carets = extra_carets;
+        let Location { x, y } = self.location;
+        self.caret_col = buffer
+            .line(y)
+            .map_or(0, |line| line.column_of(x, self.tab_width));
+        self.scroll_location_into_view(buffer);
+    }
+
+    /// Moves the carets, the selection and the top line along with
+    /// `changes` made to the buffer through another view.
+    pub fn adjust_to(&mut self, changes: &[Change], buffer: &Buffer) {
+        if changes.is_empty() {
+            return;
+        }
+        for change in changes {
+            self.location = self.location.adjusted_to(change);
+            for caret in &mut self.extra_carets {
+                *caret = caret.adjusted_to(change);
+            }
+            for anchor in [&mut self.selection_anchor, &mut self.block_anchor]
+                .into_iter()
+                .flatten()
+            {
+                *anchor = anchor.adjusted_to(change);
+            }
+            let top = Location {
+                x: 0,
+                y: self.scroll_offset.y,
+            };
+            self.scroll_offset.y = top.adjusted_to(change).y;
+        }
+        self.snap_location(buffer);
+        self.needs_redraw = true;
+    }
+
+    /// Scrolls by a line without moving the caret, unless it would leave
+    /// the screen; then it moves along to the top or bottom row. The last
+    /// line can be scrolled up to the top row, but no further.
+    fn scroll_by_line(&mut self, up: bool, buffer: &Buffer) {
+        if self.wrap {
+            self.scroll_by_row(up, buffer);
+            return;
+        }
+        let top = self.scroll_offset.y;
+        let top = if up {
+            top.saturating_sub(1)
+        } else {
+            min(top.saturating_add(1), buffer.height().saturating_sub(1))
+        };
+        if top == self.scroll_offset.y {
+            return;
+        }
+        self.scroll_offset.y = top;
+        let bottom = top.saturating_add(self.size.height.saturating_sub(1));
+        let margin = self.scroll_margin();
+        self.location.y = self
+            .location
+            .y
+            .clamp(top.saturating_add(margin), bottom.saturating_sub(margin));
+        self.snap_location(buffer);
+        self.needs_redraw = true;
+    }
+
+    /// Like `scroll_by_line`, but by a row with `wrap`.
+    fn scroll_by_row(&mut self, up: bool, buffer: &Buffer) {
+        let top = self.top();
+        let top = if up {
+            self.row_before(top, 1, buffer)
+        } else {
+            self.row_after(top, 1, buffer)
+        };
+        if top == self.top() {
+            return;
+        }
+        self.set_top(top);
+        let margin = self.scroll_margin();
+        let first = self.row_after(top, margin, buffer);
+        let last = self.row_after(
+            top,
+            self.size.height.saturating_sub(1).saturating_sub(margin),
+            buffer,
+        );
+        let (caret_row, col) = self.row_of(self.location, buffer);
+        if caret_row < first {
+            self.location = self.location_in_row(first, col, buffer);
+        } else if caret_row > last {
+            self.location = self.location_in_row(last, col, buffer);
+        }
+        self.snap_location(buffer);
+    }
+
+    /// Scrolls so the caret's line is in the middle of the screen, or after
+    /// one or two `Recenter` commands in a row at the top or the bottom.
+    /// The caret stays where it is.
+    fn recenter(&mut self, presses: usize, buffer: &Buffer) {
+        let last_row = self.size.height.saturating_sub(1);
+        let margin = self.scroll_margin();
+        let row = match presses.checked_rem(3) {
+            Some(1) => margin,
+            Some(2) => last_row.saturating_sub(margin),
+            _ => last_row.checked_div(2).unwrap_or(0),
+        };
+        if self.wrap {
+            self.set_top(self.row_before(self.caret_row(buffer), row, buffer));
+        } else {
+            let top = self.location.y.saturating_sub(row);
+            if top != self.scroll_offset.y {
+                self.scroll_offset.y = top;
+                self.needs_redraw = true;
+            }
+        }
+        self.recenter_presses = presses.saturating_add(1);
     }
 
-    fn resize(&mut self, to: Size) {
+    /// How many lines half a page is, for Ctrl+Alt+U and Ctrl+Alt+D.
+    fn half_page(&self) -> usize {
+        self.size.height.checked_div(2).unwrap_or(0).max(1)
+    }
+
+    /// Places the view's top left corner at `origin` on screen.
+    pub fn resize(&mut self, origin: Position, to: Size, buffer: &Buffer) {
+        self.origin = origin;
         self.size = to;
-        self.scroll_location_into_view();
+        self.scroll_location_into_view(buffer);
         self.needs_redraw = true;
     }
 
-    fn scroll_location_into_view(&mut self) {
-        let Location { x, y } = self.location;
-        let Size { width, height } = self.size;
+    /// How many lines to keep above and below the caret: `scrolloff`, but
+    /// at most so many that the caret can still move between them.
+    fn scroll_margin(&self) -> usize {
+        let half = self.size.height.saturating_sub(1).checked_div(2);
+        min(self.scrolloff, half.unwrap_or(0))
+    }
+
+    /// Scrolls so the caret is on screen, along with `scrolloff` lines
+    /// above and below it. Below the last line, there is nothing to keep
+    /// on screen.
+    fn scroll_location_into_view(&mut self, buffer: &Buffer) {
+        self.update_gutter_width(buffer);
+        if self.wrap {
+            self.scroll_row_into_view(buffer);
+            return;
+        }
+        let Location { y, .. } = self.location;
+        let x = self.caret_col;
+        let height = self.size.height;
+        let width = self.text_width();
+        let margin = self.scroll_margin();
         let mut offset_changed = false;
 
         // Scroll vertically
-        if y < self.scroll_offset.y {
-            self.scroll_offset.y = y;
+        let above = y.saturating_sub(margin);
+        let below = min(y.saturating_add(margin), buffer.height().saturating_sub(1)).max(y);
+        if above < self.scroll_offset.y {
+            self.scroll_offset.y = above;
             offset_changed = true;
-        } else if y >= self.scroll_offset.y.saturating_add(height) {
-            self.scroll_offset.y = y.saturating_sub(height).saturating_add(1);
+        } else if below >= self.scroll_offset.y.saturating_add(height) {
+            self.scroll_offset.y = below.saturating_sub(height).saturating_add(1);
             offset_changed = true;
         }
 
@@ -151,21 +2353,432 @@ impl View {
             self.scroll_offset.x = x.saturating_sub(width).saturating_add(1);
             offset_changed = true;
         }
-        self.needs_redraw = offset_changed;
+        self.needs_redraw |= offset_changed;
+    }
+
+    /// Like `scroll_location_into_view`, but counting rows with `wrap`.
+    fn scroll_row_into_view(&mut self, buffer: &Buffer) {
+        let height = self.size.height;
+        let margin = self.scroll_margin();
+        let caret_row = self.caret_row(buffer);
+        let above = self.row_before(caret_row, margin, buffer);
+        let below = self.row_after(caret_row, margin, buffer);
+        let top = self.top();
+        if above < top {
+            self.set_top(above);
+        } else if self.rows_between(top, below, height, buffer) >= height {
+            self.set_top(self.row_before(below, height.saturating_sub(1), buffer));
+        }
+        if self.scroll_offset.x != 0 {
+            self.scroll_offset.x = 0;
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Fits the gutter to the number of the last line of `buffer`, leaving
+    /// it out if line numbers are off or wouldn't leave room for any text.
+    /// Returns whether its width changed.
+    fn update_gutter_width(&mut self, buffer: &Buffer) -> bool {
+        let width = if self.line_numbers {
+            let digits = buffer.height().max(1).to_string().len();
+            Some(digits.saturating_add(1)).filter(|&width| width < self.size.width)
+        } else {
+            None
+        };
+        let width = match width {
+            Some(width) => width,
+            None if !self.wrap && self.size.width > 1 => 1,
+            None => 0,
+        };
+        if width == self.gutter_width {
+            return false;
+        }
+        self.gutter_width = width;
+        self.needs_redraw = true;
+        true
+    }
+
+    /// How many screen columns there are for the text, right of the gutter.
+    /// Without `wrap`, the last column is kept for the `→` scroll indicator.
+    const fn text_width(&self) -> usize {
+        let indicator_width = if self.wrap { 0 } else { 1 };
+        self.size
+            .width
+            .saturating_sub(self.gutter_width)
+            .saturating_sub(indicator_width)
+    }
+
+    /// The first row shown. Without `wrap`, it is always the first row of
+    /// the top line.
+    const fn top(&self) -> Row {
+        (self.scroll_offset.y, self.top_row)
+    }
+
+    fn set_top(&mut self, top: Row) {
+        if top != self.top() {
+            (self.scroll_offset.y, self.top_row) = top;
+            self.needs_redraw = true;
+        }
+    }
+
+    /// The row the caret is in. Without `wrap`, lines have one row.
+    fn caret_row(&self, buffer: &Buffer) -> Row {
+        self.row_of(self.location, buffer).0
+    }
+
+    /// The row `location` is in and its screen column within the row.
+    /// Without `wrap`, lines have one row.
+    fn row_of(&self, location: Location, buffer: &Buffer) -> (Row, usize) {
+        let Some(line) = buffer.line(location.y) else {
+            return ((location.y, 0), 0);
+        };
+        let col = line.column_of(location.x, self.tab_width);
+        if !self.wrap {
+            return ((location.y, 0), col);
+        }
+        let starts = self.row_starts(&line);
+        let row = starts
+            .partition_point(|&start| start <= col)
+            .saturating_sub(1);
+        let start = starts.get(row).copied().unwrap_or(0);
+        ((location.y, row), col.saturating_sub(start))
+    }
+
+    /// The location in `row` at the screen column `col` within the row, or
+    /// the end of the row if it's shorter. The caret stays on a tab or wide
+    /// character which covers the column.
+    fn location_in_row(&self, row: Row, col: usize, buffer: &Buffer) -> Location {
+        let (y, line_row) = row;
+        let x = buffer.line(y).map_or(0, |line| {
+            let starts = if self.wrap {
+                self.row_starts(&line)
+            } else {
+                vec![0]
+            };
+            let col = starts
+                .get(line_row)
+                .copied()
+                .unwrap_or(0)
+                .saturating_add(col);
+            let mut x = line.index_at_column(col, self.tab_width);
+            if line.column_of(x, self.tab_width) > col {
+                x = line.previous_grapheme_boundary(x);
+            }
+            // A wide character which didn't fit into the row starts the next one.
+            let next_row = starts.get(line_row.saturating_add(1));
+            if next_row.is_some_and(|&next| line.column_of(x, self.tab_width) >= next) {
+                x = line.previous_grapheme_boundary(x);
+            }
+            x
+        });
+        Location { x, y }
+    }
+
+    /// The screen columns at which the rows of `line` start with `wrap`.
+    /// The caret can go right after the last character, so a line filling
+    /// its last row gets another one.
+    fn row_starts(&self, line: &Line) -> Vec<usize> {
+        let width = self.text_width();
+        if width == 0 {
+            return vec![0];
+        }
+        let mut starts = line.row_starts(width, self.tab_width);
+        let end = line.column_of(line.len(), self.tab_width);
+        if starts
+            .last()
+            .is_some_and(|&last| end >= last.saturating_add(width))
+        {
+            starts.push(end);
+        }
+        starts
+    }
+
+    /// How many rows `line` takes up with `wrap`.
+    fn rows_of(&self, line: &Line) -> usize {
+        self.row_starts(line).len()
+    }
+
+    fn rows_of_line(&self, y: usize, buffer: &Buffer) -> usize {
+        buffer.line(y).map_or(1, |line| self.rows_of(&line))
+    }
+
+    /// The row `count` rows above `row`, or the buffer's first row.
+    fn row_before(&self, row: Row, count: usize, buffer: &Buffer) -> Row {
+        let (mut y, mut line_row) = row;
+        for _ in 0..count {
+            if line_row > 0 {
+                line_row = line_row.saturating_sub(1);
+            } else if y > 0 {
+                y = y.saturating_sub(1);
+                line_row = self.rows_of_line(y, buffer).saturating_sub(1);
+            } else {
+                break;
+            }
+        }
+        (y, line_row)
+    }
+
+    /// The row a caret in `row` goes to when moved in `direction`, for the
+    /// moves which go by rows rather than lines with `wrap`.
+    fn wrapped_row_moved(&self, row: Row, direction: &Direction, buffer: &Buffer) -> Option<Row> {
+        if !self.wrap {
+            return None;
+        }
+        let page = self.size.height.saturating_sub(1);
+        match direction {
+            Direction::Up => Some(self.row_before(row, 1, buffer)),
+            Direction::Down => Some(self.row_after(row, 1, buffer)),
+            Direction::PageUp => Some(self.row_before(row, page, buffer)),
+            Direction::PageDown => Some(self.row_after(row, page, buffer)),
+            Direction::HalfPageUp => Some(self.row_before(row, self.half_page(), buffer)),
+            Direction::HalfPageDown => Some(self.row_after(row, self.half_page(), buffer)),
+            _ => None,
+        }
+    }
+
+    /// The row `count` rows below `row`, or the buffer's last row.
+    fn row_after(&self, row: Row, count: usize, buffer: &Buffer) -> Row {
+        let (mut y, mut line_row) = row;
+        for _ in 0..count {
+            if line_row.saturating_add(1) < self.rows_of_line(y, buffer) {
+                line_row = line_row.saturating_add(1);
+            } else if y.saturating_add(1) < buffer.height() {
+                y = y.saturating_add(1);
+                line_row = 0;
+            } else {
+                break;
+            }
+        }
+        (y, line_row)
+    }
+
+    /// How many rows `to` lies below `from`, counting no further than `limit`.
+    fn rows_between(&self, from: Row, to: Row, limit: usize, buffer: &Buffer) -> usize {
+        let mut row = from;
+        let mut count: usize = 0;
+        while row < to && count < limit {
+            let next = self.row_after(row, 1, buffer);
+            if next == row {
+                break;
+            }
+            row = next;
+            count = count.saturating_add(1);
+        }
+        count
+    }
+
+    /// The screen columns of line `y` within `visible` which are shown
+    /// highlighted, in order, and how: the extra carets, the selection and
+    /// the match of the search inverted, and the other matches of the last
+    /// search marked where they don't overlap those.
+    fn highlighted_cells(
+        &self,
+        y: usize,
+        line: &Line,
+        visible: Range<usize>,
+    ) -> Vec<(Range<usize>, Style)> {
+        let mut cells: Vec<Range<usize>> = self
+            .extra_carets
+            .iter()
+            .filter(|caret| caret.y == y)
+            .map(|caret| {
+                let start = line.column_of(caret.x, self.tab_width);
+                let end = line.column_of(line.next_grapheme_boundary(caret.x), self.tab_width);
+                start..end.max(start.saturating_add(1)).min(visible.end)
+            })
+            .filter(|cell| visible.contains(&cell.start))
+            .collect();
+        if let Some((start, end)) = self
+            .selection()
+            .filter(|(start, end)| (start.y..=end.y).contains(&y))
+        {
+            let from = if y == start.y {
+                line.column_of(start.x, self.tab_width)
+            } else {
+                0
+            };
+            // The line break of a selected line is shown as one more column.
+            let to = if y == end.y {
+                line.column_of(end.x, self.tab_width)
+            } else {
+                line.column_of(line.len(), self.tab_width).saturating_add(1)
+            };
+            let cell = from.max(visible.start)..to.min(visible.end);
+            if !cell.is_empty() {
+                cells.push(cell);
+            }
+        }
+        if let Some((start, end)) = self
+            .search_match
+            .map(|found| found.extent)
+            .filter(|(start, _)| start.y == y)
+        {
+            let cell = line.column_of(start.x, self.tab_width).max(visible.start)
+                ..line.column_of(end.x, self.tab_width).min(visible.end);
+            if !cell.is_empty() {
+                cells.push(cell);
+            }
+        }
+        // An empty block is shown one column wide, like a caret on each line.
+        if let Some((_, cols)) = self.block().filter(|(lines, _)| lines.contains(&y)) {
+            let cell = cols.start.max(visible.start)
+                ..cols.end.max(cols.start.saturating_add(1)).min(visible.end);
+            if !cell.is_empty() {
+                cells.push(cell);
+            }
+        }
+        let mut cells: Vec<(Range<usize>, Style)> = cells
+            .into_iter()
+            .map(|cell| (cell, Style::Inverted))
+            .collect();
+        if let Some(pattern) = &self.search_pattern {
+            let text = line.to_string();
+            for found in search::find_all(&text, pattern) {
+                let cell = line
+                    .column_of(found.start, self.tab_width)
+                    .max(visible.start)
+                    ..line.column_of(found.end, self.tab_width).min(visible.end);
+                let overlaps = cells
+                    .iter()
+                    .any(|(other, _)| cell.start < other.end && other.start < cell.end);
+                if !cell.is_empty() && !overlaps {
+                    cells.push((cell, Style::Marked));
+                }
+            }
+        }
+        cells.sort_by_key(|(cell, _)| cell.start);
+        cells
+    }
+
+    /// Renders the columns `visible` of `line` with the `highlights` cells
+    /// in their styles. Cells past the end of the line are shown as spaces.
+    fn render_line_with_highlights(
+        &self,
+        at: usize,
+        line: &Line,
+        visible: Range<usize>,
+        highlights: &[(Range<usize>, Style)],
+    ) {
+        let mut result = Terminal::move_caret_to(self.screen_position(at, self.gutter_width));
+        let mut col = visible.start;
+        for (highlight, style) in highlights {
+            let mut cell = line.get_visible(highlight.clone(), self.tab_width);
+            let padding = highlight.len().saturating_sub(cell.width());
+            cell.push_str(&" ".repeat(padding));
+            // Highlights may lie beyond the end of the line, e.g. in a block.
+            let mut before = line.get_visible(col..highlight.start, self.tab_width);
+            let gap = highlight
+                .start
+                .saturating_sub(col)
+                .saturating_sub(before.width());
+            before.push_str(&" ".repeat(gap));
+            result = result
+                .and_then(|()| Terminal::print(&before))
+                .and_then(|()| match style {
+                    Style::Inverted => Terminal::print_inverted(&cell),
+                    Style::Marked => Terminal::print_marked(&cell),
+                });
+            col = highlight.end;
+        }
+        result = result
+            .and_then(|()| Terminal::print(&line.get_visible(col..visible.end, self.tab_width)));
+        debug_assert!(result.is_ok(), "Failed to render line");
+    }
+
+    /// The number shown in the gutter for line `y`.
+    const fn line_number(&self, y: usize) -> usize {
+        if self.relative_line_numbers && y != self.location.y {
+            y.abs_diff(self.location.y)
+        } else {
+            y.saturating_add(1)
+        }
+    }
+
+    /// The position on screen of the view's column `col` in screen row `at`.
+    const fn screen_position(&self, at: usize, col: usize) -> Position {
+        Position {
+            row: at,
+            col: self.origin.col.saturating_add(col),
+        }
+    }
+
+    /// Clears the view's part of screen row `at`, leaving other windows be,
+    /// and draws the gutter into it, with `number` right-aligned, or blank
+    /// for the rows after the first of a line.
+    fn render_gutter(&self, at: usize, number: Option<usize>) {
+        let start = self.screen_position(at, 0);
+        let mut result = Terminal::move_caret_to(start)
+            .and_then(|()| Terminal::print(&" ".repeat(self.size.width)))
+            .and_then(|()| Terminal::move_caret_to(start));
+        if self.gutter_width > 0 {
+            let number = number.map(|number| number.to_string()).unwrap_or_default();
+            let digits = self.gutter_width.saturating_sub(1);
+            result = result.and_then(|()| Terminal::print(&format!("{number:>digits$} ")));
+        }
+        debug_assert!(result.is_ok(), "Failed to render gutter");
     }
 
-    fn render_line(at: usize, line_text: &str) {
-        let result = Terminal::print_row(at, line_text);
+    /// Draws the screen columns `visible` of line `y` into screen row `at`,
+    /// right of the gutter.
+    fn render_line_part(&self, at: usize, y: usize, line: &Line, visible: Range<usize>) {
+        let highlights = self.highlighted_cells(y, line, visible.clone());
+        if highlights.is_empty() {
+            let result = Terminal::move_caret_to(self.screen_position(at, self.gutter_width))
+                .and_then(|()| Terminal::print(&line.get_visible(visible, self.tab_width)));
+            debug_assert!(result.is_ok(), "Failed to render line");
+        } else {
+            self.render_line_with_highlights(at, line, visible, &highlights);
+        }
+    }
+
+    /// Draws `←` into the gutter's last column if the line continues to the
+    /// left of the screen, and `→` into the column kept right of the text
+    /// if it continues to the right.
+    fn render_scroll_indicators(&self, at: usize, line: &Line, visible: Range<usize>) {
+        let line_width = line.column_of(line.len(), self.tab_width);
+        let mut result = Ok(());
+        if visible.start > 0 && line_width > 0 {
+            let col = self.gutter_width.saturating_sub(1);
+            result = Terminal::move_caret_to(self.screen_position(at, col))
+                .and_then(|()| Terminal::print("←"));
+        }
+        if line_width > visible.end {
+            let col = self.gutter_width.saturating_add(visible.len());
+            result = result
+                .and_then(|()| Terminal::move_caret_to(self.screen_position(at, col)))
+                .and_then(|()| Terminal::print("→"));
+        }
+        debug_assert!(result.is_ok(), "Failed to render scroll indicators");
+    }
+
+    /// Draws `line_text` into the view's part of screen row `at`.
+    fn render_line(&self, at: usize, line_text: &str) {
+        let width = self.size.width;
+        let result = Terminal::move_caret_to(self.screen_position(at, 0))
+            .and_then(|()| Terminal::print(&format!("{line_text:<width$}")));
         debug_assert!(result.is_ok(), "Failed to render line");
     }
 
-    fn build_welcome_message(width: usize) -> String {
+    /// The welcome screen's lines: the editor's name and version, and
+    /// the keys to get started with.
+    fn build_welcome_lines() -> Vec<String> {
+        vec![
+            format!("{NAME} editor -- version {VERSION}"),
+            String::new(),
+            "Ctrl+S save   Ctrl+C quit".to_string(),
+            "Ctrl+F find   Ctrl+G go to line".to_string(),
+            "Ctrl+P find a file   Ctrl+R recent files".to_string(),
+        ]
+    }
+
+    /// `text` centered on a row of the empty screen, after its `~`, or just
+    /// the `~` if it doesn't fit.
+    fn centered(width: usize, text: &str) -> String {
         if width == 0 {
             return " ".to_string();
         }
 
-        let welcome_message = format!("{NAME} editor -- version {VERSION}");
-        let len = welcome_message.len();
+        let len = text.len();
 
         if width <= len {
             return "~".to_string();
@@ -176,7 +2789,7 @@ impl View {
         #[allow(clippy::integer_division)]
         let padding = (width.saturating_sub(len).saturating_sub(1)) / 2;
 
-        let mut full_message = format!("~{}{}", " ".repeat(padding), welcome_message);
+        let mut full_message = format!("~{}{}", " ".repeat(padding), text);
         full_message.truncate(width);
         full_message
     }
@@ -185,11 +2798,342 @@ impl View {
 impl Default for View {
     fn default() -> Self {
         Self {
-            buffer: Buffer::default(),
             needs_redraw: true,
-            size: Terminal::size().unwrap_or_default(),
+            size: Size::default(),
+            origin: Position::default(),
             location: Location::default(),
+            extra_carets: Vec::new(),
+            selection_anchor: None,
+            block_anchor: None,
+            block_col: 0,
+            caret_col: 0,
+            scrolloff: 0,
+            wrap: false,
+            top_row: 0,
+            welcome: false,
+            line_numbers: true,
+            relative_line_numbers: false,
+            numbered_from: None,
+            gutter_width: 0,
+            goal_col: None,
             scroll_offset: Location::default(),
+            tab_width: 4,
+            expand_tab: false,
+            auto_indent: true,
+            indent_after: String::new(),
+            auto_close: false,
+            overwrite: false,
+            snippets: Vec::new(),
+            abbreviations: HashMap::new(),
+            snippet_stops: Vec::new(),
+            snippet_stop: 0,
+            search_origin: None,
+            search_match: None,
+            search_pattern: None,
+            search_region: None,
+            search_direction: SearchDirection::Forward,
+            jumps: Vec::new(),
+            jump_index: 0,
+            kill_ring: Vec::new(),
+            kill_continues: false,
+            recenter_presses: 0,
+        }
+    }
+}
+
+/// A decimal number as written, compared exactly however many digits it has.
+struct DecimalNumber<'a> {
+    negative: bool,
+    /// The digits before the point, without leading zeros.
+    integer: &'a str,
+    /// The digits after the point, without trailing zeros.
+    fraction: &'a str,
+}
+
+impl DecimalNumber<'_> {
+    fn is_zero(&self) -> bool {
+        self.integer.is_empty() && self.fraction.is_empty()
+    }
+
+    /// Compares the absolute values: first the number of integer digits,
+    /// then the digits themselves, which for the fractions works without
+    /// padding them to the same length.
+    fn cmp_magnitude(&self, other: &Self) -> Ordering {
+        self.integer
+            .len()
+            .cmp(&other.integer.len())
+            .then_with(|| self.integer.cmp(other.integer))
+            .then_with(|| self.fraction.cmp(other.fraction))
+    }
+}
+
+impl Ord for DecimalNumber<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self.is_zero() && other.is_zero() {
+            return Ordering::Equal;
+        }
+        match (
+            self.negative && !self.is_zero(),
+            other.negative && !other.is_zero(),
+        ) {
+            (false, false) => self.cmp_magnitude(other),
+            (true, true) => other.cmp_magnitude(self),
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+        }
+    }
+}
+
+impl PartialOrd for DecimalNumber<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for DecimalNumber<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for DecimalNumber<'_> {}
+
+/// The longest number `line` starts with, ignoring leading whitespace: an
+/// optional sign and digits, optionally followed by a point and more
+/// digits, so e.g. `1.2.3` starts with 1.2. `None` if it doesn't start
+/// with one, which sorts such lines first.
+fn leading_number(line: &str) -> Option<DecimalNumber<'_>> {
+    let line = line.trim_start();
+    let (negative, rest) = match line.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, line.strip_prefix('+').unwrap_or(line)),
+    };
+    let digits_len = |text: &str| {
+        text.find(|character: char| !character.is_ascii_digit())
+            .unwrap_or(text.len())
+    };
+    let (integer, rest) = rest.split_at(digits_len(rest));
+    let fraction = rest
+        .strip_prefix('.')
+        .map_or("", |rest| &rest[..digits_len(rest)]);
+    if integer.is_empty() && fraction.is_empty() {
+        return None;
+    }
+    Some(DecimalNumber {
+        negative,
+        integer: integer.trim_start_matches('0'),
+        fraction: fraction.trim_end_matches('0'),
+    })
+}
+
+/// The character index ranges of the numbers in `chars`, in order: `0x`
+/// followed by hexadecimal digits, or decimal digits with an optional `-`
+/// which doesn't follow a word.
+fn number_ranges(chars: &[char]) -> Vec<Range<usize>> {
+    let is_digit = |index: usize, radix: u32| chars.get(index).is_some_and(|c| c.is_digit(radix));
+    let mut ranges = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let is_hex = chars[index] == '0'
+            && matches!(chars.get(index.saturating_add(1)), Some('x' | 'X'))
+            && is_digit(index.saturating_add(2), 16);
+        if !is_hex && !is_digit(index, 10) {
+            index = index.saturating_add(1);
+            continue;
+        }
+        let radix = if is_hex { 16 } else { 10 };
+        let mut start = index;
+        let mut end = if is_hex {
+            index.saturating_add(2)
+        } else {
+            index
+        };
+        while is_digit(end, radix) {
+            end = end.saturating_add(1);
+        }
+        let negative = !is_hex
+            && index
+                .checked_sub(1)
+                .is_some_and(|before| chars[before] == '-')
+            && index
+                .checked_sub(2)
+                .is_none_or(|before| !is_word_char(chars[before]));
+        if negative {
+            start = start.saturating_sub(1);
+        }
+        ranges.push(start..end);
+        index = end;
+    }
+    ranges
+}
+
+/// `number`, as found by `number_ranges`, with `amount` added. Leading
+/// zeros and the case of hexadecimal digits are kept. Hexadecimal numbers
+/// wrap around; `None` if a decimal one would overflow.
+fn added_to_number(number: &str, amount: i64) -> Option<String> {
+    if let Some(digits) = number
+        .get(2..)
+        .filter(|_| number.starts_with("0x") || number.starts_with("0X"))
+    {
+        let value = u64::from_str_radix(digits, 16)
+            .ok()?
+            .wrapping_add_signed(amount);
+        let width = digits.len();
+        let new_digits = if digits.chars().any(|c| c.is_ascii_uppercase()) {
+            format!("{value:0width$X}")
+        } else {
+            format!("{value:0width$x}")
+        };
+        return Some(format!("{}{new_digits}", &number[..2]));
+    }
+    let value = number.parse::<i64>().ok()?.checked_add(amount)?;
+    let digits = number.trim_start_matches('-');
+    let width = if digits.starts_with('0') {
+        digits.len()
+    } else {
+        0
+    };
+    let sign = if value < 0 { "-" } else { "" };
+    Some(format!("{sign}{:0width$}", value.unsigned_abs()))
+}
+
+/// The location of the bracket matching the one at `location`, or else
+/// the one right before it, taking nested brackets of the same kind into
+/// account. The search may span any number of lines.
+fn matching_bracket(location: Location, buffer: &Buffer) -> Option<Location> {
+    let chars: Vec<char> = buffer.line(location.y)?.to_string().chars().collect();
+    let (x, opener, closer, forward) = [Some(location.x), location.x.checked_sub(1)]
+        .into_iter()
+        .flatten()
+        .find_map(|x| {
+            let character = *chars.get(x)?;
+            BRACKETS.iter().find_map(|&(opener, closer)| {
+                (character == opener || character == closer).then_some((
+                    x,
+                    opener,
+                    closer,
+                    character == opener,
+                ))
+            })
+        })?;
+    let mut depth: usize = 0;
+    let mut y = location.y;
+    let mut line = chars;
+    let mut next_x = Some(x);
+    loop {
+        while let Some(x) = next_x {
+            let Some(&character) = line.get(x) else {
+                break;
+            };
+            if character == opener || character == closer {
+                let nests = (character == opener) == forward;
+                if nests {
+                    depth = depth.saturating_add(1);
+                } else {
+                    depth = depth.saturating_sub(1);
+                }
+                if depth == 0 {
+                    return Some(Location { x, y });
+                }
+            }
+            next_x = if forward {
+                x.checked_add(1)
+            } else {
+                x.checked_sub(1)
+            };
+        }
+        y = if forward {
+            Some(y.saturating_add(1)).filter(|&below| below < buffer.height())?
+        } else {
+            y.checked_sub(1)?
+        };
+        line = buffer.line(y)?.to_string().chars().collect();
+        next_x = if forward {
+            Some(0)
+        } else {
+            line.len().checked_sub(1)
+        };
+    }
+}
+
+/// What an edit at one caret changed: the text which ended at `old_end`
+/// before the edit ends at `new_end` now. The caret goes to `caret`.
+struct CaretEdit {
+    old_end: Location,
+    new_end: Location,
+    caret: Location,
+}
+
+impl CaretEdit {
+    /// An edit after which the caret is at the end of the changed text.
+    const fn to_end(old_end: Location, new_end: Location) -> Self {
+        Self {
+            old_end,
+            new_end,
+            caret: new_end,
         }
     }
 }
+
+/// Upper-cases the first letter of each word in `text` and lower-cases the rest.
+fn title_case(text: &str) -> String {
+    let mut result = String::new();
+    let mut starts_word = true;
+    for character in text.chars() {
+        if starts_word {
+            result.extend(character.to_uppercase());
+        } else {
+            result.extend(character.to_lowercase());
+        }
+        starts_word = !character.is_alphanumeric();
+    }
+    result
+}
+
+/// Moves `location` back into the text if it lies outside of it or within
+/// a grapheme cluster.
+fn snapped(location: Location, buffer: &Buffer) -> Location {
+    let y = min(location.y, buffer.height());
+    let x = buffer
+        .line(y)
+        .map_or(0, |line| line.grapheme_start(min(line.len(), location.x)));
+    Location { x, y }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::leading_number;
+
+    /// `lines` sorted the way numeric sorting sorts them.
+    fn sorted(lines: &[&'static str]) -> Vec<&'static str> {
+        let mut lines = lines.to_vec();
+        lines.sort_by(|a, b| leading_number(a).cmp(&leading_number(b)));
+        lines
+    }
+
+    #[test]
+    fn versions_sort_by_their_leading_number() {
+        assert_eq!(
+            sorted(&["10 ten", "1.2.3", "none", "-3", "1.10", "  2"]),
+            ["none", "-3", "1.10", "1.2.3", "  2", "10 ten"]
+        );
+    }
+
+    #[test]
+    fn big_integers_compare_exactly() {
+        assert_eq!(
+            sorted(&["9007199254740993", "9007199254740992", "00012"]),
+            ["00012", "9007199254740992", "9007199254740993"]
+        );
+    }
+
+    #[test]
+    fn signs_fractions_and_zeros() {
+        assert_eq!(
+            sorted(&["-1.5", "-1.25", "0.5", "-0", "+0.25", ".75", "-"]),
+            ["-", "-1.5", "-1.25", "-0", "+0.25", "0.5", ".75"]
+        );
+        assert!(leading_number("0.0") == leading_number("-0"));
+        assert!(leading_number("x1").is_none());
+    }
+}